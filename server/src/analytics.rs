@@ -0,0 +1,398 @@
+// server/src/analytics.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Analitik per room / per stream. Yang direkam:
+// - `room_views`: satu baris per koneksi viewer (masuk / keluar) → unique viewer & watch time
+// - `room_samples`: cuplikan tiap ANALYTICS_SAMPLE_SECS (jumlah viewer, chat & add-to-cart
+//   sejak cuplikan sebelumnya) → peak / rata-rata viewer dan time series
+// Chat, keranjang, dan order dihitung dari tabelnya sendiri (chat_messages, cart_items,
+// orders) dalam jendela waktu stream / atribusi stream_id.
+
+use std::{env, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{MySql, Pool};
+
+use crate::{streams, AppState};
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct Sample {
+    pub sampled_at: DateTime<Utc>,
+    pub stream_id: Option<i64>,
+    pub viewers: i32,
+    pub chat_messages: i32,
+    pub cart_adds: i32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct FunnelStep {
+    pub step: &'static str,
+    pub users: i64,
+    // dibanding langkah pertama (viewer unik), 0..1
+    pub rate: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct Summary {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub peak_viewers: i64,
+    pub avg_viewers: f64,
+    pub unique_viewers: i64,
+    // total detik tonton semua koneksi dalam jendela
+    pub watch_secs: i64,
+    pub avg_watch_secs: i64,
+    pub chat_messages: i64,
+    pub chatters: i64,
+    pub cart_adds: i64,
+    pub cart_qty: i64,
+    pub orders: i64,
+    // total order yang tidak batal
+    pub revenue: i64,
+    pub paid_orders: i64,
+    pub paid_revenue: i64,
+    pub funnel: Vec<FunnelStep>,
+}
+
+/// ANALYTICS_SAMPLE_SECS (default 30, minimal 5).
+pub(crate) fn sample_secs_from_env() -> u64 {
+    env::var("ANALYTICS_SAMPLE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30)
+        .max(5)
+}
+
+/// Identitas viewer untuk hitungan unik: user login, atau hash cookie sesi
+/// (sid mentah tidak disimpan), atau acak per koneksi bila tanpa cookie.
+pub(crate) fn viewer_key(user_id: Option<i64>, sid: Option<&str>) -> String {
+    match (user_id, sid) {
+        (Some(id), _) => format!("u:{id}"),
+        (None, Some(sid)) => {
+            let hash = hex::encode(Sha256::digest(sid.as_bytes()));
+            format!("s:{}", &hash[..32])
+        }
+        (None, None) => format!("c:{}", uuid::Uuid::new_v4().simple()),
+    }
+}
+
+/// Catat koneksi viewer masuk; id dipakai untuk `view_ended`.
+pub(crate) async fn view_started(
+    db: &Pool<MySql>,
+    node: &str,
+    room: &str,
+    key: &str,
+    user_id: Option<i64>,
+) -> i64 {
+    sqlx::query!(
+        "INSERT INTO room_views(node, room, viewer_key, user_id) VALUES(?,?,?,?)",
+        node,
+        room,
+        key,
+        user_id
+    )
+    .execute(db)
+    .await
+    .unwrap()
+    .last_insert_id() as i64
+}
+
+pub(crate) async fn view_ended(db: &Pool<MySql>, view_id: i64) {
+    let _ = sqlx::query!("UPDATE room_views SET left_at=NOW() WHERE id=? AND left_at IS NULL", view_id)
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+/// Sesi tonton di-resume di instance lain: pindahkan kepemilikannya.
+pub(crate) async fn view_moved(db: &Pool<MySql>, view_id: i64, node: &str) {
+    let _ = sqlx::query!(
+        "UPDATE room_views SET node=?, seen_at=NOW() WHERE id=? AND left_at IS NULL",
+        node,
+        view_id
+    )
+    .execute(db)
+    .await
+    .unwrap();
+}
+
+/// Detak instance: sesi tonton yang masih terbuka di instance ini terakhir terlihat sekarang.
+pub(crate) async fn touch_views(db: &Pool<MySql>, node: &str) {
+    let _ = sqlx::query!("UPDATE room_views SET seen_at=NOW() WHERE node=? AND left_at IS NULL", node)
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+/// Koneksi yang masih "terbuka" milik instance yang sudah mati dianggap selesai pada
+/// detak terakhir instance itu (`seen_at`), bukan saat ditemukan (instance bisa mati lama).
+pub(crate) async fn close_stale_views(db: &Pool<MySql>, alive: &[String]) {
+    let marks = vec!["?"; alive.len().max(1)].join(",");
+    let sql = format!(
+        "UPDATE room_views SET left_at=COALESCE(seen_at, joined_at)
+         WHERE left_at IS NULL AND node NOT IN ({marks})"
+    );
+    let mut q = sqlx::query(&sql);
+    if alive.is_empty() {
+        q = q.bind("");
+    }
+    for n in alive {
+        q = q.bind(n);
+    }
+    let _ = q.execute(db).await.unwrap();
+}
+
+/// Rekam `room_samples` tiap `secs` detik untuk room yang ada penontonnya
+/// atau sedang punya stream live.
+pub(crate) fn spawn_sampler(state: AppState, secs: u64) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(Duration::from_secs(secs));
+        let mut last = Utc::now();
+        loop {
+            tick.tick().await;
+            let now = Utc::now();
+            touch_views(&state.db, state.bus.node()).await;
+            // beberapa instance: hanya satu yang merekam (hitungan viewer sudah se-cluster)
+            if state.bus.try_lead("analytics_sampler", secs * 2).await {
+                close_stale_views(&state.db, &state.bus.nodes().await).await;
+                take_samples(&state, last, now).await;
+            }
+            last = now;
+        }
+    });
+}
+
+/// Ringkasan metrik room dalam jendela [from, to]. `stream_id` = batasi cuplikan
+/// ke stream itu dan pakai atribusi stream_id untuk keranjang / order; tanpa
+/// stream, keranjang dihitung dari `cart_items.room` dan order dari stream di room ini.
+pub(crate) async fn summary(
+    db: &Pool<MySql>,
+    room: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    stream_id: Option<i64>,
+) -> Summary {
+    let samples = sqlx::query!(
+        r#"
+        SELECT CAST(COALESCE(MAX(viewers), 0) AS SIGNED) AS `peak!: i64`,
+               CAST(COALESCE(SUM(viewers), 0) AS SIGNED) AS `sum!: i64`,
+               COUNT(*) AS `n!: i64`
+        FROM room_samples
+        WHERE room=? AND sampled_at BETWEEN ? AND ? AND (? IS NULL OR stream_id = ?)
+        "#,
+        room,
+        from,
+        to,
+        stream_id,
+        stream_id
+    )
+    .fetch_one(db)
+    .await
+    .unwrap();
+
+    // waktu tonton dipotong ke jendela
+    let views = sqlx::query!(
+        r#"
+        SELECT COUNT(DISTINCT viewer_key) AS `unique!: i64`,
+               CAST(COALESCE(SUM(GREATEST(0, TIMESTAMPDIFF(SECOND,
+                   GREATEST(joined_at, ?), LEAST(COALESCE(left_at, NOW()), ?)))), 0) AS SIGNED)
+               AS `secs!: i64`
+        FROM room_views
+        WHERE room=? AND joined_at < ? AND (left_at IS NULL OR left_at > ?)
+        "#,
+        from,
+        to,
+        room,
+        to,
+        from
+    )
+    .fetch_one(db)
+    .await
+    .unwrap();
+
+    let chat = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS `n!: i64`, COUNT(DISTINCT user_id) AS `users!: i64`
+        FROM chat_messages
+        WHERE room=? AND created_at BETWEEN ? AND ?
+        "#,
+        room,
+        from,
+        to
+    )
+    .fetch_one(db)
+    .await
+    .unwrap();
+
+    let cart = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS `n!: i64`, CAST(COALESCE(SUM(ci.qty), 0) AS SIGNED) AS `qty!: i64`,
+               COUNT(DISTINCT c.user_id) AS `users!: i64`
+        FROM cart_items ci JOIN carts c ON c.id = ci.cart_id
+        WHERE ci.stream_id = ?
+           OR (? IS NULL AND ci.room = ? AND ci.created_at BETWEEN ? AND ?)
+        "#,
+        stream_id,
+        stream_id,
+        room,
+        from,
+        to
+    )
+    .fetch_one(db)
+    .await
+    .unwrap();
+
+    let orders = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS `n!: i64`,
+               CAST(COALESCE(SUM(o.total), 0) AS SIGNED) AS `revenue!: i64`,
+               COUNT(DISTINCT o.user_id) AS `users!: i64`,
+               CAST(COALESCE(SUM(o.status IN ('paid','shipped','done')), 0) AS SIGNED) AS `paid!: i64`,
+               CAST(COALESCE(SUM(IF(o.status IN ('paid','shipped','done'), o.total, 0)), 0) AS SIGNED)
+               AS `paid_revenue!: i64`,
+               COUNT(DISTINCT IF(o.status IN ('paid','shipped','done'), o.user_id, NULL))
+               AS `paid_users!: i64`
+        FROM orders o LEFT JOIN streams s ON s.id = o.stream_id
+        WHERE o.status NOT IN ('cancelled','deleted')
+          AND (o.stream_id = ?
+               OR (? IS NULL AND s.room = ? AND o.created_at BETWEEN ? AND ?))
+        "#,
+        stream_id,
+        stream_id,
+        room,
+        from,
+        to
+    )
+    .fetch_one(db)
+    .await
+    .unwrap();
+
+    let base = views.unique;
+    let step = |step: &'static str, users: i64| FunnelStep {
+        step,
+        users,
+        rate: if base > 0 { users as f64 / base as f64 } else { 0.0 },
+    };
+    let funnel = vec![
+        step("viewed", views.unique),
+        step("chatted", chat.users),
+        step("added_to_cart", cart.users),
+        step("ordered", orders.users),
+        step("paid", orders.paid_users),
+    ];
+
+    Summary {
+        from,
+        to,
+        peak_viewers: samples.peak,
+        avg_viewers: if samples.n > 0 { samples.sum as f64 / samples.n as f64 } else { 0.0 },
+        unique_viewers: views.unique,
+        watch_secs: views.secs,
+        avg_watch_secs: if views.unique > 0 { views.secs / views.unique } else { 0 },
+        chat_messages: chat.n,
+        chatters: chat.users,
+        cart_adds: cart.n,
+        cart_qty: cart.qty,
+        orders: orders.n,
+        revenue: orders.revenue,
+        paid_orders: orders.paid,
+        paid_revenue: orders.paid_revenue,
+        funnel,
+    }
+}
+
+/// Time series cuplikan room dalam jendela (opsional hanya untuk satu stream).
+pub(crate) async fn series(
+    db: &Pool<MySql>,
+    room: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    stream_id: Option<i64>,
+) -> Vec<Sample> {
+    sqlx::query_as!(
+        Sample,
+        r#"
+        SELECT sampled_at, stream_id, viewers, chat_messages, cart_adds
+        FROM room_samples
+        WHERE room=? AND sampled_at BETWEEN ? AND ? AND (? IS NULL OR stream_id = ?)
+        ORDER BY sampled_at ASC, id ASC
+        "#,
+        room,
+        from,
+        to,
+        stream_id,
+        stream_id
+    )
+    .fetch_all(db)
+    .await
+    .unwrap()
+}
+
+/* ===================== helpers lokal ===================== */
+
+async fn take_samples(state: &AppState, since: DateTime<Utc>, now: DateTime<Utc>) {
+    let mut rooms: Vec<(String, usize)> = state.bus.viewer_counts().await.into_iter().collect();
+    // stream live tanpa penonton tetap dicatat (grafik turun ke 0)
+    let live = sqlx::query!("SELECT DISTINCT room FROM streams WHERE status='live'")
+        .fetch_all(&state.db)
+        .await
+        .unwrap();
+    for r in live {
+        if !rooms.iter().any(|(room, _)| *room == r.room) {
+            rooms.push((r.room, 0));
+        }
+    }
+
+    for (room, viewers) in rooms {
+        let mut conn = state.db.acquire().await.unwrap();
+        let stream_id = streams::live_in_room(&mut conn, &room).await;
+        drop(conn);
+
+        let chat = sqlx::query!(
+            "SELECT COUNT(*) AS `n!: i64` FROM chat_messages WHERE room=? AND created_at > ? AND created_at <= ?",
+            room,
+            since,
+            now
+        )
+        .fetch_one(&state.db)
+        .await
+        .unwrap()
+        .n;
+        let adds = sqlx::query!(
+            "SELECT COUNT(*) AS `n!: i64` FROM cart_items WHERE room=? AND created_at > ? AND created_at <= ?",
+            room,
+            since,
+            now
+        )
+        .fetch_one(&state.db)
+        .await
+        .unwrap()
+        .n;
+
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO room_samples(room, stream_id, sampled_at, viewers, chat_messages, cart_adds)
+            VALUES(?,?,?,?,?,?)
+            "#,
+            room,
+            stream_id,
+            now,
+            viewers as i32,
+            chat as i32,
+            adds as i32
+        )
+        .execute(&state.db)
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,526 @@
+// server/src/auctions.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Lelang satu barang per room (barang koleksi / preloved). Host membuka lelang
+// dengan harga awal, kelipatan minimum, dan waktu selesai; penawaran masuk lewat
+// WS `{"t":"bid"}` atau REST. Urutan penawaran ditentukan server (baris lelang
+// dikunci per tawaran), tawaran di detik-detik terakhir memperpanjang waktu
+// (anti-sniping), dan saat lelang ditutup barang langsung masuk keranjang pemenang.
+
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Pool};
+
+use crate::{handlers::cart, is_unique_violation, room_send, ws_protocol::Body, AppState};
+
+// durasi lelang maksimum: 24 jam
+const MAX_AUCTION_SECS: u32 = 24 * 3600;
+// perpanjangan anti-sniping maksimum per tawaran
+const MAX_EXTEND_SECS: u32 = 600;
+const DEFAULT_EXTEND_SECS: u32 = 30;
+
+/// Keadaan lelang (dikirim saat dibuka, ke koneksi yang baru join, dan lewat REST).
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct AuctionOut {
+    pub id: i64,
+    pub room: String,
+    pub product_id: i64,
+    pub variant_id: Option<i64>,
+    pub product_name: String,
+    pub image_url: Option<String>,
+    pub start_price: i32,
+    pub min_increment: i32,
+    // tawaran di bawah sisa waktu ini memperpanjang ends_at
+    pub extend_secs: i32,
+    pub current_bid: Option<i32>,
+    pub current_bidder_id: Option<i64>,
+    pub current_bidder: Option<String>,
+    pub bid_count: i32,
+    // tawaran berikutnya minimal sebesar ini
+    pub min_next: i64,
+    pub ends_at: DateTime<Utc>,
+    // "running" | "closed" | "cancelled"
+    pub status: String,
+    // keranjang pemenang (setelah ditutup)
+    pub cart_id: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct BidOut {
+    pub id: i64,
+    pub user_id: i64,
+    pub user: String,
+    pub amount: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Parameter membuka lelang (REST admin).
+#[derive(Deserialize, Debug)]
+pub(crate) struct StartIn {
+    pub product_id: i64,
+    pub variant_id: Option<i64>,
+    pub start_price: i32,
+    pub min_increment: i32,
+    // lama lelang (detik)
+    pub seconds: u32,
+    // default 30 detik; 0 = tanpa perpanjangan
+    pub extend_secs: Option<u32>,
+}
+
+#[derive(Debug)]
+pub(crate) enum AuctionError {
+    ProductMissing,
+    VariantMissing,
+    VariantRequired,
+    OutOfStock,
+    BadPrice,
+    BadIncrement,
+    BadDuration,
+    AlreadyRunning,
+    NotFound,
+    Closed,
+    AlreadyLeading,
+    BidTooLow { min_next: i64 },
+}
+
+impl AuctionError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuctionError::ProductMissing
+            | AuctionError::VariantMissing
+            | AuctionError::VariantRequired
+            | AuctionError::BadPrice
+            | AuctionError::BadIncrement
+            | AuctionError::BadDuration => "invalid",
+            AuctionError::OutOfStock => "out_of_stock",
+            AuctionError::AlreadyRunning => "auction_running",
+            AuctionError::NotFound => "not_found",
+            AuctionError::Closed => "auction_closed",
+            AuctionError::AlreadyLeading => "already_leading",
+            AuctionError::BidTooLow { .. } => "bid_too_low",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            AuctionError::ProductMissing => "product missing".into(),
+            AuctionError::VariantMissing => "variant missing".into(),
+            AuctionError::VariantRequired => "variant required".into(),
+            AuctionError::OutOfStock => "out of stock".into(),
+            AuctionError::BadPrice => "start_price must be > 0".into(),
+            AuctionError::BadIncrement => "min_increment must be > 0".into(),
+            AuctionError::BadDuration => {
+                format!("seconds must be 1..={MAX_AUCTION_SECS}, extend_secs 0..={MAX_EXTEND_SECS}")
+            }
+            AuctionError::AlreadyRunning => "another auction is running in this room".into(),
+            AuctionError::NotFound => "auction not found".into(),
+            AuctionError::Closed => "auction is closed".into(),
+            AuctionError::AlreadyLeading => "you already hold the highest bid".into(),
+            AuctionError::BidTooLow { min_next } => format!("bid must be at least {min_next}"),
+        }
+    }
+
+    /// Balasan WS ke penawar / host.
+    pub fn into_body(self) -> Body {
+        Body::Error { code: self.code(), message: self.message() }
+    }
+}
+
+impl IntoResponse for AuctionError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            AuctionError::NotFound => axum::http::StatusCode::NOT_FOUND,
+            AuctionError::AlreadyRunning
+            | AuctionError::Closed
+            | AuctionError::AlreadyLeading
+            | AuctionError::BidTooLow { .. } => axum::http::StatusCode::CONFLICT,
+            _ => axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        let mut body = serde_json::json!({ "error": self.code(), "message": self.message() });
+        if let AuctionError::BidTooLow { min_next } = self {
+            body["min_next"] = min_next.into();
+        }
+        (status, Json(body)).into_response()
+    }
+}
+
+/// Lelang yang sedang berjalan di room.
+pub(crate) async fn current(db: &Pool<MySql>, room: &str) -> Option<AuctionOut> {
+    let id = sqlx::query!(
+        "SELECT id FROM auctions WHERE room=? AND status='running' ORDER BY id DESC LIMIT 1",
+        room
+    )
+    .fetch_optional(db)
+    .await
+    .unwrap()?
+    .id;
+    get(db, id).await
+}
+
+pub(crate) async fn get(db: &Pool<MySql>, id: i64) -> Option<AuctionOut> {
+    sqlx::query_as!(
+        AuctionOut,
+        r#"
+        SELECT a.id, a.room, a.product_id, a.variant_id, p.name AS product_name, p.image_url,
+               a.start_price, a.min_increment, a.extend_secs, a.current_bid, a.current_bidder_id,
+               u.name AS `current_bidder?`, a.bid_count,
+               COALESCE(a.current_bid + a.min_increment, a.start_price) AS `min_next!: i64`,
+               a.ends_at, a.status, a.cart_id
+        FROM auctions a
+        JOIN products p ON p.id = a.product_id
+        LEFT JOIN users u ON u.id = a.current_bidder_id
+        WHERE a.id=?
+        "#,
+        id
+    )
+    .fetch_optional(db)
+    .await
+    .unwrap()
+}
+
+/// Tawaran terbaru dulu.
+pub(crate) async fn bids(db: &Pool<MySql>, auction_id: i64, limit: u32) -> Vec<BidOut> {
+    sqlx::query_as!(
+        BidOut,
+        r#"
+        SELECT b.id, b.user_id, u.name AS user, b.amount, b.created_at
+        FROM auction_bids b JOIN users u ON u.id = b.user_id
+        WHERE b.auction_id=?
+        ORDER BY b.id DESC
+        LIMIT ?
+        "#,
+        auction_id,
+        limit
+    )
+    .fetch_all(db)
+    .await
+    .unwrap()
+}
+
+/// Buka lelang di room + siarkan `auction`. Satu room hanya satu lelang berjalan.
+pub(crate) async fn start(
+    state: &AppState,
+    room: &str,
+    created_by: Option<i64>,
+    inp: StartIn,
+) -> Result<AuctionOut, AuctionError> {
+    if inp.start_price <= 0 {
+        return Err(AuctionError::BadPrice);
+    }
+    if inp.min_increment <= 0 {
+        return Err(AuctionError::BadIncrement);
+    }
+    let extend_secs = inp.extend_secs.unwrap_or(DEFAULT_EXTEND_SECS);
+    if inp.seconds == 0 || inp.seconds > MAX_AUCTION_SECS || extend_secs > MAX_EXTEND_SECS {
+        return Err(AuctionError::BadDuration);
+    }
+    check_item(&state.db, inp.product_id, inp.variant_id).await?;
+
+    if current(&state.db, room).await.is_some() {
+        return Err(AuctionError::AlreadyRunning);
+    }
+
+    // start bersamaan: unique key auctions.running_room (room selama status running)
+    // menolak lelang kedua
+    let ends_at = Utc::now() + chrono::Duration::seconds(inp.seconds as i64);
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO auctions(room, product_id, variant_id, start_price, min_increment,
+                             extend_secs, ends_at, created_by)
+        VALUES(?,?,?,?,?,?,?,?)
+        "#,
+        room,
+        inp.product_id,
+        inp.variant_id,
+        inp.start_price,
+        inp.min_increment,
+        extend_secs,
+        ends_at,
+        created_by
+    )
+    .execute(&state.db)
+    .await;
+    let id = match res {
+        Ok(r) => r.last_insert_id() as i64,
+        Err(e) if is_unique_violation(&e) => return Err(AuctionError::AlreadyRunning),
+        Err(e) => panic!("insert auction: {e:?}"),
+    };
+
+    let auction = get(&state.db, id).await.ok_or(AuctionError::NotFound)?;
+    room_send(state, room, Body::Auction(auction.clone())).await;
+    spawn_closer(state, id);
+    Ok(auction)
+}
+
+/// Satu tawaran. `room` = room WS asal tawaran (None dari REST); baris lelang dikunci
+/// sehingga tawaran bersamaan diproses berurutan dan hanya yang valid yang menang.
+pub(crate) async fn bid(
+    state: &AppState,
+    auction_id: i64,
+    room: Option<&str>,
+    user_id: i64,
+    user: &str,
+    amount: i32,
+) -> Result<(), AuctionError> {
+    let mut tx = state.db.begin().await.unwrap();
+    let a = sqlx::query!(
+        r#"
+        SELECT room, status, start_price, min_increment, extend_secs, current_bid,
+               current_bidder_id, bid_count, ends_at
+        FROM auctions WHERE id=?
+        FOR UPDATE
+        "#,
+        auction_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+    let Some(a) = a else {
+        return Err(AuctionError::NotFound);
+    };
+    if room.is_some_and(|r| r != a.room) {
+        return Err(AuctionError::NotFound);
+    }
+    let now = Utc::now();
+    if a.status != "running" || a.ends_at <= now {
+        return Err(AuctionError::Closed);
+    }
+    if a.current_bidder_id == Some(user_id) {
+        return Err(AuctionError::AlreadyLeading);
+    }
+    let min_next = a
+        .current_bid
+        .map_or(a.start_price as i64, |b| b as i64 + a.min_increment as i64);
+    if (amount as i64) < min_next {
+        return Err(AuctionError::BidTooLow { min_next });
+    }
+
+    // anti-sniping: tawaran di menit-menit akhir mendorong waktu selesai
+    let extend = chrono::Duration::seconds(a.extend_secs as i64);
+    let extended = a.ends_at - now < extend;
+    let ends_at = if extended { now + extend } else { a.ends_at };
+
+    let _ = sqlx::query!(
+        "INSERT INTO auction_bids(auction_id, user_id, amount) VALUES(?,?,?)",
+        auction_id,
+        user_id,
+        amount
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+    let _ = sqlx::query!(
+        r#"
+        UPDATE auctions
+        SET current_bid=?, current_bidder_id=?, bid_count=bid_count+1, ends_at=?
+        WHERE id=?
+        "#,
+        amount,
+        user_id,
+        ends_at,
+        auction_id
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+
+    let body = Body::AuctionBid {
+        auction_id,
+        amount,
+        user_id,
+        user: user.to_string(),
+        bid_count: a.bid_count + 1,
+        min_next: amount as i64 + a.min_increment as i64,
+        ends_at,
+        extended,
+    };
+    room_send(state, &a.room, body).await;
+    Ok(())
+}
+
+/// Tutup sekarang (tidak menunggu ends_at); pemenang = penawar tertinggi saat ini.
+pub(crate) async fn close_now(state: &AppState, auction_id: i64) -> Result<AuctionOut, AuctionError> {
+    // jam server (bukan NOW() DB) agar cocok dengan pengecekan di close()
+    let res = sqlx::query!(
+        "UPDATE auctions SET ends_at=? WHERE id=? AND status='running'",
+        Utc::now(),
+        auction_id
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    if res.rows_affected() == 0 {
+        return Err(lookup_error(&state.db, auction_id).await);
+    }
+    close(state, auction_id).await;
+    get(&state.db, auction_id).await.ok_or(AuctionError::NotFound)
+}
+
+/// Batalkan lelang berjalan tanpa pemenang.
+pub(crate) async fn cancel(state: &AppState, auction_id: i64) -> Result<(), AuctionError> {
+    let res = sqlx::query!(
+        "UPDATE auctions SET status='cancelled', closed_at=NOW() WHERE id=? AND status='running'",
+        auction_id
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    if res.rows_affected() == 0 {
+        return Err(lookup_error(&state.db, auction_id).await);
+    }
+    if let Some(a) = get(&state.db, auction_id).await {
+        room_send(state, &a.room, closed_body(&a, None, None)).await;
+    }
+    Ok(())
+}
+
+/// Pasang ulang timer penutupan untuk lelang yang masih berjalan (setelah server restart).
+pub(crate) async fn resume(state: &AppState) {
+    let running = sqlx::query!("SELECT id FROM auctions WHERE status='running'")
+        .fetch_all(&state.db)
+        .await
+        .unwrap();
+    for r in running {
+        spawn_closer(state, r.id);
+    }
+}
+
+/* ===================== helpers lokal ===================== */
+
+// tidur sampai ends_at; bila waktu diperpanjang tawaran baru, tidur lagi
+fn spawn_closer(state: &AppState, auction_id: i64) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            let row = sqlx::query!("SELECT status, ends_at FROM auctions WHERE id=?", auction_id)
+                .fetch_optional(&state.db)
+                .await
+                .unwrap();
+            let Some(row) = row else { return };
+            if row.status != "running" {
+                return;
+            }
+            match (row.ends_at - Utc::now()).to_std() {
+                Ok(left) if !left.is_zero() => tokio::time::sleep(left).await,
+                _ => break,
+            }
+        }
+        close(&state, auction_id).await;
+    });
+}
+
+// tutup lelang yang sudah lewat waktunya; barang masuk keranjang pemenang dalam transaksi yang sama
+async fn close(state: &AppState, auction_id: i64) {
+    let mut tx = state.db.begin().await.unwrap();
+    let a = sqlx::query!(
+        r#"
+        SELECT room, product_id, variant_id, status, current_bid, current_bidder_id, ends_at
+        FROM auctions WHERE id=?
+        FOR UPDATE
+        "#,
+        auction_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+    // sudah ditutup / dibatalkan, atau baru saja diperpanjang
+    let Some(a) = a.filter(|a| a.status == "running" && a.ends_at <= Utc::now()) else {
+        return;
+    };
+
+    let cart_id = match (a.current_bidder_id, a.current_bid) {
+        (Some(winner), Some(price)) => Some(
+            cart::add_auction_item(&mut tx, winner, a.product_id, a.variant_id, price, &a.room, auction_id)
+                .await,
+        ),
+        _ => None,
+    };
+    let _ = sqlx::query!(
+        "UPDATE auctions SET status='closed', closed_at=NOW(), cart_id=? WHERE id=?",
+        cart_id,
+        auction_id
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+
+    if let Some(out) = get(&state.db, auction_id).await {
+        let body = closed_body(&out, out.current_bidder_id, out.current_bid);
+        room_send(state, &a.room, body).await;
+    }
+}
+
+fn closed_body(a: &AuctionOut, winner_user_id: Option<i64>, amount: Option<i32>) -> Body {
+    Body::AuctionClosed {
+        auction_id: a.id,
+        product_id: a.product_id,
+        status: a.status.clone(),
+        winner_user_id,
+        winner: winner_user_id.and(a.current_bidder.clone()),
+        amount,
+    }
+}
+
+// harga, stok & varian barang yang dilelang
+async fn check_item(db: &Pool<MySql>, product_id: i64, variant_id: Option<i64>) -> Result<(), AuctionError> {
+    let prod = sqlx::query!("SELECT id, stock FROM products WHERE id=? AND is_active=1", product_id)
+        .fetch_optional(db)
+        .await
+        .unwrap();
+    let Some(prod) = prod else {
+        return Err(AuctionError::ProductMissing);
+    };
+    let stock = if let Some(variant_id) = variant_id {
+        let v = sqlx::query!(
+            "SELECT stock FROM product_variants WHERE id=? AND product_id=? AND is_active=1",
+            variant_id,
+            prod.id
+        )
+        .fetch_optional(db)
+        .await
+        .unwrap();
+        let Some(v) = v else {
+            return Err(AuctionError::VariantMissing);
+        };
+        v.stock
+    } else {
+        let has_variants = sqlx::query!(
+            "SELECT id FROM product_variants WHERE product_id=? AND is_active=1 LIMIT 1",
+            prod.id
+        )
+        .fetch_optional(db)
+        .await
+        .unwrap()
+        .is_some();
+        if has_variants {
+            return Err(AuctionError::VariantRequired);
+        }
+        prod.stock
+    };
+    if stock < 1 {
+        return Err(AuctionError::OutOfStock);
+    }
+    Ok(())
+}
+
+// lelang tidak berjalan: belum ada atau sudah selesai
+async fn lookup_error(db: &Pool<MySql>, auction_id: i64) -> AuctionError {
+    match get(db, auction_id).await {
+        Some(_) => AuctionError::Closed,
+        None => AuctionError::NotFound,
+    }
+}
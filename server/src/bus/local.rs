@@ -0,0 +1,272 @@
+// server/src/bus/local.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+//! Bus satu proses: kanal broadcast tokio, hitungan viewer, buffer replay dan sesi
+//! WS yang menunggu resume di memori.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use tokio::sync::{broadcast, Mutex, RwLock};
+
+use super::{
+    numbered, presence_add, presence_remove, replay_after, seq_tail, Bus, Hub, Presence, RoomPresence, Topic,
+    REPLAY_LEN, REPLAY_TTL_SECS,
+};
+
+// selang minimal antar penyapuan buffer replay yang kedaluwarsa
+const SWEEP_SECS: u64 = 60;
+
+pub(crate) struct LocalBus {
+    hub: Hub,
+    node: String,
+    viewers: RwLock<RoomPresence>,
+    replay: Mutex<Replays>,
+    // key → (kedaluwarsa, data)
+    parked: Mutex<HashMap<String, (Instant, String)>>,
+}
+
+struct Replays {
+    rooms: HashMap<String, Replay>,
+    swept: Instant,
+}
+
+struct Replay {
+    // nomor terakhir yang dibagikan
+    seq: u64,
+    buf: VecDeque<(u64, String)>,
+    // pesan terakhir (untuk kedaluwarsa)
+    at: Instant,
+}
+
+impl Replays {
+    // room tanpa pesan selama REPLAY_TTL_SECS dibuang (nomor urutnya mulai lagi dari 1;
+    // client yang resume dengan nomor lama mendapat resync)
+    fn sweep(&mut self, now: Instant) {
+        if now.duration_since(self.swept) < Duration::from_secs(SWEEP_SECS) {
+            return;
+        }
+        self.swept = now;
+        let ttl = Duration::from_secs(REPLAY_TTL_SECS);
+        self.rooms.retain(|_, r| now.duration_since(r.at) < ttl);
+    }
+}
+
+impl LocalBus {
+    pub fn new() -> Self {
+        LocalBus {
+            hub: Hub::default(),
+            node: uuid::Uuid::new_v4().simple().to_string(),
+            viewers: RwLock::new(HashMap::new()),
+            replay: Mutex::new(Replays { rooms: HashMap::new(), swept: Instant::now() }),
+            parked: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl Bus for LocalBus {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn node(&self) -> &str {
+        &self.node
+    }
+
+    async fn nodes(&self) -> Vec<String> {
+        vec![self.node.clone()]
+    }
+
+    async fn publish(&self, topic: &Topic, msg: String) {
+        self.hub.deliver(&topic.key(), msg).await;
+    }
+
+    async fn subscribe(&self, topic: &Topic) -> broadcast::Receiver<String> {
+        self.hub.subscribe(topic).await
+    }
+
+    async fn release(&self, topic: &Topic) {
+        self.hub.release(topic).await;
+    }
+
+    async fn join(&self, room: &str, viewer: &Presence) -> bool {
+        presence_add(&mut *self.viewers.write().await, room, viewer)
+    }
+
+    async fn leave(&self, room: &str, key: &str) -> bool {
+        presence_remove(&mut *self.viewers.write().await, room, key)
+    }
+
+    async fn viewer_counts(&self) -> HashMap<String, usize> {
+        self.viewers.read().await.iter().map(|(room, v)| (room.clone(), v.len())).collect()
+    }
+
+    async fn viewers(&self, room: &str) -> Vec<Presence> {
+        self.viewers.read().await.get(room).map(|v| v.values().cloned().collect()).unwrap_or_default()
+    }
+
+    async fn publish_replayable(&self, room: &str, msg: String) -> u64 {
+        let tail = seq_tail(&msg);
+        let now = Instant::now();
+        // nomor, buffer dan kirim di bawah satu kunci
+        let mut replay = self.replay.lock().await;
+        replay.sweep(now);
+        let r = replay
+            .rooms
+            .entry(room.to_string())
+            .or_insert_with(|| Replay { seq: 0, buf: VecDeque::new(), at: now });
+        r.seq += 1;
+        r.at = now;
+        let seq = r.seq;
+        let msg = numbered(&tail, seq);
+        r.buf.push_back((seq, msg.clone()));
+        while r.buf.len() > REPLAY_LEN {
+            r.buf.pop_front();
+        }
+        self.hub.deliver(&Topic::Room(room.to_string()).key(), msg).await;
+        seq
+    }
+
+    async fn replay(&self, room: &str, since: u64) -> Option<Vec<String>> {
+        let replay = self.replay.lock().await;
+        let r = replay.rooms.get(room)?;
+        let mut buf: Vec<(u64, String)> = r.buf.iter().cloned().collect();
+        buf.sort_by_key(|(seq, _)| *seq);
+        replay_after(buf, r.seq, since)
+    }
+
+    async fn park(&self, key: &str, data: String, ttl_secs: u64) {
+        let now = Instant::now();
+        let mut parked = self.parked.lock().await;
+        parked.retain(|_, (exp, _)| *exp > now);
+        parked.insert(key.to_string(), (now + Duration::from_secs(ttl_secs), data));
+    }
+
+    async fn claim(&self, key: &str) -> Option<String> {
+        let (exp, data) = self.parked.lock().await.remove(key)?;
+        (exp > Instant::now()).then_some(data)
+    }
+
+    async fn try_lead(&self, _task: &str, _ttl_secs: u64) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::tests::viewer;
+
+    async fn recv(rx: &mut broadcast::Receiver<String>) -> String {
+        tokio::time::timeout(Duration::from_secs(1), rx.recv()).await.expect("no message").unwrap()
+    }
+
+    #[tokio::test]
+    async fn publish_reaches_topic_subscribers() {
+        let bus = LocalBus::new();
+        let topic = Topic::Staff("r1".into());
+        let mut rx = bus.subscribe(&topic).await;
+        bus.publish(&topic, "hello".into()).await;
+        assert_eq!(recv(&mut rx).await, "hello");
+
+        // topik lain tidak ikut
+        bus.publish(&Topic::Staff("r2".into()), "other".into()).await;
+        assert!(rx.try_recv().is_err());
+
+        // tanpa subscriber pesan dibuang
+        drop(rx);
+        bus.release(&topic).await;
+        bus.publish(&topic, "lost".into()).await;
+        let mut rx = bus.subscribe(&topic).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn join_leave_counts_unique_viewers() {
+        let bus = LocalBus::new();
+        assert!(bus.join("r1", &viewer("u:1")).await);
+        assert!(!bus.join("r1", &viewer("u:1")).await);
+        assert!(bus.join("r1", &viewer("u:2")).await);
+        assert!(bus.join("r2", &viewer("u:1")).await);
+
+        let counts = bus.viewer_counts().await;
+        assert_eq!(counts["r1"], 2);
+        assert_eq!(counts["r2"], 1);
+        assert_eq!(bus.viewers("r1").await.len(), 2);
+
+        assert!(!bus.leave("r1", "u:1").await);
+        assert!(bus.leave("r1", "u:1").await);
+        assert!(bus.leave("r1", "u:2").await);
+        assert!(!bus.viewer_counts().await.contains_key("r1"));
+        assert!(bus.viewers("r1").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn publish_replayable_numbers_and_replays() {
+        let bus = LocalBus::new();
+        let mut rx = bus.subscribe(&Topic::Room("r1".into())).await;
+        for i in 1..=3u64 {
+            assert_eq!(bus.publish_replayable("r1", format!(r#"{{"n":{i}}}"#)).await, i);
+        }
+        for i in 1..=3u64 {
+            assert_eq!(recv(&mut rx).await, format!(r#"{{"seq":{i},"n":{i}}}"#));
+        }
+        // nomor urut per room
+        assert_eq!(bus.publish_replayable("r2", "{}".into()).await, 1);
+
+        assert_eq!(
+            bus.replay("r1", 1).await,
+            Some(vec![r#"{"seq":2,"n":2}"#.to_string(), r#"{"seq":3,"n":3}"#.to_string()])
+        );
+        assert_eq!(bus.replay("r1", 3).await, Some(vec![]));
+        assert_eq!(bus.replay("r1", 9).await, None);
+        assert_eq!(bus.replay("none", 0).await, None);
+    }
+
+    #[tokio::test]
+    async fn replay_buffer_is_trimmed() {
+        let bus = LocalBus::new();
+        let n = REPLAY_LEN as u64 + 10;
+        for _ in 0..n {
+            bus.publish_replayable("r1", "{}".into()).await;
+        }
+        assert_eq!(bus.replay("r1", 5).await, None);
+        assert_eq!(bus.replay("r1", n - REPLAY_LEN as u64).await.unwrap().len(), REPLAY_LEN);
+    }
+
+    #[tokio::test]
+    async fn idle_replay_buffers_expire() {
+        let bus = LocalBus::new();
+        bus.publish_replayable("r1", "{}".into()).await;
+        bus.replay.lock().await.sweep(Instant::now() + Duration::from_secs(SWEEP_SECS));
+        assert!(bus.replay("r1", 0).await.is_some());
+
+        let later = Instant::now() + Duration::from_secs(REPLAY_TTL_SECS + SWEEP_SECS);
+        bus.replay.lock().await.sweep(later);
+        assert!(bus.replay("r1", 0).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn park_claim_once() {
+        let bus = LocalBus::new();
+        bus.park("t1", "data".into(), 30).await;
+        assert_eq!(bus.claim("t1").await.as_deref(), Some("data"));
+        assert_eq!(bus.claim("t1").await, None);
+
+        // kedaluwarsa
+        bus.park("t2", "data".into(), 0).await;
+        assert_eq!(bus.claim("t2").await, None);
+    }
+}
@@ -0,0 +1,350 @@
+// server/src/bus/mod.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+//! Message bus untuk fan-out room/topik, hitungan viewer, buffer replay room dan
+//! sesi WS yang menunggu resume. Implementasi
+//! `local` (satu proses, perilaku lama) dan `redis` (pub/sub antar instance di
+//! belakang load balancer). Kode lain hanya bicara dengan trait `Bus`.
+
+pub(crate) mod local;
+pub(crate) mod redis;
+
+use std::{collections::HashMap, env, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, RwLock};
+
+/// Topik di bus; setiap koneksi WS subscribe ke beberapa topik.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Topic {
+    // semua koneksi room
+    Room(String),
+    // frame video room (`f`); terpisah agar tertinggal cukup lompat ke frame terbaru
+    Frames(String),
+    // host/moderator room (feed klaim, dsb)
+    Staff(String),
+    // semua koneksi room mana pun (event lintas room, mis. flash sale umum)
+    AllRooms,
+    // `/ws/_events` admin
+    Admin,
+    // `/ws/_events` milik satu user
+    User(i64),
+    // sinkron cache moderasi + kick antar instance
+    Moderation,
+    // token sesi WS yang di-resume (instance asal melepas hitungan viewer-nya)
+    Resumed,
+}
+
+impl Topic {
+    pub fn key(&self) -> String {
+        match self {
+            Topic::Room(r) => format!("room:{r}"),
+            Topic::Frames(r) => format!("frames:{r}"),
+            Topic::Staff(r) => format!("staff:{r}"),
+            Topic::AllRooms => "rooms".into(),
+            Topic::Admin => "admin".into(),
+            Topic::User(id) => format!("user:{id}"),
+            Topic::Moderation => "moderation".into(),
+            Topic::Resumed => "resumed".into(),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        match self {
+            Topic::Room(_) => 512,
+            Topic::Frames(_) => 8,
+            Topic::Admin => 256,
+            Topic::Staff(_) => 128,
+            Topic::User(_) => 32,
+            Topic::AllRooms | Topic::Moderation | Topic::Resumed => 64,
+        }
+    }
+}
+
+/// Satu viewer unik di room (user login / sesi cookie), berapa pun tab-nya.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Presence {
+    // analytics::viewer_key: u:<id> / s:<hash sid>
+    pub key: String,
+    pub user_id: Option<i64>,
+    pub joined_at: DateTime<Utc>,
+    // koneksi yang sedang terbuka
+    #[serde(default)]
+    pub connections: usize,
+}
+
+// room → viewer key → presence
+pub(crate) type RoomPresence = HashMap<String, HashMap<String, Presence>>;
+
+#[async_trait]
+pub(crate) trait Bus: Send + Sync {
+    /// Nama implementasi (untuk log start).
+    fn name(&self) -> &'static str;
+
+    /// Id instance ini (acak per proses).
+    fn node(&self) -> &str;
+
+    /// Id instance yang masih hidup (termasuk instance ini).
+    async fn nodes(&self) -> Vec<String>;
+
+    /// Kirim pesan (JSON envelope) ke subscriber topik di semua instance.
+    async fn publish(&self, topic: &Topic, msg: String);
+
+    /// Subscribe topik di instance ini.
+    async fn subscribe(&self, topic: &Topic) -> broadcast::Receiver<String>;
+
+    /// Buang kanal lokal topik bila sudah tidak ada subscriber.
+    async fn release(&self, topic: &Topic);
+
+    /// Satu koneksi viewer masuk room; true = koneksi pertama viewer ini di room
+    /// (seluruh instance).
+    async fn join(&self, room: &str, viewer: &Presence) -> bool;
+
+    /// Satu koneksi viewer keluar room; true = koneksi terakhirnya.
+    async fn leave(&self, room: &str, key: &str) -> bool;
+
+    /// Jumlah viewer unik per room di seluruh instance.
+    async fn viewer_counts(&self) -> HashMap<String, usize>;
+
+    /// Viewer unik yang sedang menonton room (seluruh instance).
+    async fn viewers(&self, room: &str) -> Vec<Presence>;
+
+    /// Beri `msg` (objek JSON) field `seq` = nomor urut room berikutnya (naik terus,
+    /// sama di semua instance), simpan ke buffer replay room lalu kirim ke `Topic::Room`.
+    /// Ketiganya satu langkah atomik: urutan kirim selalu sama dengan urutan nomor.
+    async fn publish_replayable(&self, room: &str, msg: String) -> u64;
+
+    /// Pesan room setelah `since` dari buffer replay; None = ada yang sudah tidak
+    /// tersimpan (client perlu resync penuh).
+    async fn replay(&self, room: &str, since: u64) -> Option<Vec<String>>;
+
+    /// Simpan data sesi selama `ttl_secs`; `claim` mengambil sekaligus menghapusnya
+    /// (hanya satu pemanggil yang mendapat data).
+    async fn park(&self, key: &str, data: String, ttl_secs: u64);
+    async fn claim(&self, key: &str) -> Option<String>;
+
+    /// Tugas berkala yang cukup dijalankan satu instance (mis. cuplikan analitik):
+    /// true bila instance ini pemegangnya selama `ttl_secs` ke depan.
+    async fn try_lead(&self, task: &str, ttl_secs: u64) -> bool;
+}
+
+/// Kanal broadcast lokal per topik; dipakai kedua implementasi untuk fan-out
+/// ke koneksi di instance ini.
+#[derive(Default)]
+pub(crate) struct Hub {
+    topics: RwLock<HashMap<String, broadcast::Sender<String>>>,
+}
+
+impl Hub {
+    pub async fn subscribe(&self, topic: &Topic) -> broadcast::Receiver<String> {
+        self.topics
+            .write()
+            .await
+            .entry(topic.key())
+            .or_insert_with(|| broadcast::channel::<String>(topic.capacity()).0)
+            .subscribe()
+    }
+
+    pub async fn release(&self, topic: &Topic) {
+        let mut topics = self.topics.write().await;
+        let key = topic.key();
+        if topics.get(&key).is_some_and(|tx| tx.receiver_count() == 0) {
+            topics.remove(&key);
+        }
+    }
+
+    /// Tidak ada subscriber di instance ini → pesan dibuang.
+    pub async fn deliver(&self, key: &str, msg: String) {
+        if let Some(tx) = self.topics.read().await.get(key) {
+            let _ = tx.send(msg);
+        }
+    }
+}
+
+/// Tambah satu koneksi; true = koneksi pertama viewer di map ini.
+fn presence_add(map: &mut RoomPresence, room: &str, viewer: &Presence) -> bool {
+    let p = map
+        .entry(room.to_string())
+        .or_default()
+        .entry(viewer.key.clone())
+        .or_insert_with(|| Presence { connections: 0, ..viewer.clone() });
+    p.connections += 1;
+    p.connections == 1
+}
+
+/// Kurangi satu koneksi; true = koneksi terakhir viewer di map ini.
+fn presence_remove(map: &mut RoomPresence, room: &str, key: &str) -> bool {
+    let Some(viewers) = map.get_mut(room) else { return false };
+    let Some(p) = viewers.get_mut(key) else { return false };
+    p.connections = p.connections.saturating_sub(1);
+    if p.connections > 0 {
+        return false;
+    }
+    viewers.remove(key);
+    if viewers.is_empty() {
+        map.remove(room);
+    }
+    true
+}
+
+/// Gabung presence beberapa instance: satu entri per viewer (koneksi dijumlah,
+/// joined_at paling awal).
+fn presence_merge(into: &mut RoomPresence, room: String, p: Presence) {
+    into.entry(room)
+        .or_default()
+        .entry(p.key.clone())
+        .and_modify(|e| {
+            e.connections += p.connections;
+            e.joined_at = e.joined_at.min(p.joined_at);
+        })
+        .or_insert(p);
+}
+
+/// Panjang buffer replay per room.
+pub(crate) const REPLAY_LEN: usize = 256;
+/// Buffer replay (dan nomor urut) room yang tidak ada pesan selama ini dibuang.
+pub(crate) const REPLAY_TTL_SECS: u64 = 3600;
+
+/// Sisa objek JSON `msg` setelah field `seq` yang disisipkan bus: pesan bernomor =
+/// `{"seq":<n>` + hasil ini.
+fn seq_tail(msg: &str) -> String {
+    let rest = msg.strip_prefix('{').expect("room message is a JSON object");
+    if rest.trim_start().starts_with('}') {
+        rest.to_string()
+    } else {
+        format!(",{rest}")
+    }
+}
+
+fn numbered(tail: &str, seq: u64) -> String {
+    format!("{{\"seq\":{seq}{tail}")
+}
+
+/// Aturan replay yang sama untuk kedua implementasi: `buf` urut naik, `last` = nomor
+/// terakhir yang sudah dibagikan.
+fn replay_after(buf: Vec<(u64, String)>, last: u64, since: u64) -> Option<Vec<String>> {
+    if since > last {
+        // nomor urut direset (mis. server restart)
+        return None;
+    }
+    if since == last {
+        return Some(Vec::new());
+    }
+    let out: Vec<(u64, String)> = buf.into_iter().filter(|(seq, _)| *seq > since).collect();
+    match out.first() {
+        Some((first, _)) if *first == since + 1 => Some(out.into_iter().map(|(_, m)| m).collect()),
+        _ => None,
+    }
+}
+
+/// Pilih bus dari env `BUS` (default: local); `redis` memakai `REDIS_URL`.
+pub(crate) async fn from_env() -> Arc<dyn Bus> {
+    match env::var("BUS").unwrap_or_else(|_| "local".into()).as_str() {
+        "local" => Arc::new(local::LocalBus::new()),
+        "redis" => {
+            let url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".into());
+            match redis::RedisBus::connect(&url).await {
+                Ok(bus) => Arc::new(bus),
+                Err(e) => panic!("❌ gagal konek Redis ({url}): {e}"),
+            }
+        }
+        other => panic!("❌ BUS tidak dikenal: {other}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(super) fn viewer(key: &str) -> Presence {
+        Presence { key: key.to_string(), user_id: None, joined_at: Utc::now(), connections: 0 }
+    }
+
+    #[test]
+    fn presence_add_counts_first_connection_only() {
+        let mut map = RoomPresence::new();
+        assert!(presence_add(&mut map, "r1", &viewer("u:1")));
+        assert!(!presence_add(&mut map, "r1", &viewer("u:1")));
+        assert!(presence_add(&mut map, "r1", &viewer("u:2")));
+        // viewer yang sama di room lain = viewer baru di room itu
+        assert!(presence_add(&mut map, "r2", &viewer("u:1")));
+
+        assert_eq!(map["r1"].len(), 2);
+        assert_eq!(map["r1"]["u:1"].connections, 2);
+        assert_eq!(map["r2"]["u:1"].connections, 1);
+    }
+
+    #[test]
+    fn presence_remove_reports_last_connection() {
+        let mut map = RoomPresence::new();
+        presence_add(&mut map, "r1", &viewer("u:1"));
+        presence_add(&mut map, "r1", &viewer("u:1"));
+        presence_add(&mut map, "r1", &viewer("u:2"));
+
+        assert!(!presence_remove(&mut map, "r1", "u:1"));
+        assert!(presence_remove(&mut map, "r1", "u:1"));
+        assert!(!map["r1"].contains_key("u:1"));
+        // room kosong ikut dibuang
+        assert!(presence_remove(&mut map, "r1", "u:2"));
+        assert!(!map.contains_key("r1"));
+        // tidak dikenal
+        assert!(!presence_remove(&mut map, "r1", "u:2"));
+        assert!(!presence_remove(&mut map, "nope", "u:1"));
+    }
+
+    #[test]
+    fn presence_merge_sums_connections_keeps_earliest_join() {
+        let early = Utc::now() - chrono::Duration::minutes(5);
+        let mut into = RoomPresence::new();
+        presence_merge(&mut into, "r1".into(), Presence { connections: 2, ..viewer("u:1") });
+        presence_merge(&mut into, "r1".into(), Presence { connections: 1, joined_at: early, ..viewer("u:1") });
+        presence_merge(&mut into, "r1".into(), Presence { connections: 1, ..viewer("u:2") });
+
+        let r1 = &into["r1"];
+        assert_eq!(r1.len(), 2);
+        assert_eq!(r1["u:1"].connections, 3);
+        assert_eq!(r1["u:1"].joined_at, early);
+        assert_eq!(r1["u:2"].connections, 1);
+    }
+
+    fn buf(seqs: &[u64]) -> Vec<(u64, String)> {
+        seqs.iter().map(|s| (*s, format!("m{s}"))).collect()
+    }
+
+    #[test]
+    fn replay_after_rules() {
+        // lengkap setelah `since`
+        assert_eq!(replay_after(buf(&[3, 4, 5]), 5, 2), Some(vec!["m3".into(), "m4".into(), "m5".into()]));
+        assert_eq!(replay_after(buf(&[3, 4, 5]), 5, 4), Some(vec!["m5".into()]));
+        // sudah mutakhir
+        assert_eq!(replay_after(buf(&[3, 4, 5]), 5, 5), Some(vec![]));
+        assert_eq!(replay_after(Vec::new(), 0, 0), Some(vec![]));
+        // pesan since+1 sudah terpangkas
+        assert_eq!(replay_after(buf(&[3, 4, 5]), 5, 1), None);
+        // nomor urut direset
+        assert_eq!(replay_after(buf(&[1, 2]), 2, 7), None);
+        // buffer kosong padahal ada yang tertinggal
+        assert_eq!(replay_after(Vec::new(), 5, 3), None);
+    }
+
+    #[test]
+    fn numbered_puts_seq_first() {
+        let msg = r#"{"v":1,"t":"chat"}"#;
+        let out = numbered(&seq_tail(msg), 42);
+        assert_eq!(out, r#"{"seq":42,"v":1,"t":"chat"}"#);
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(v["seq"], 42);
+
+        assert_eq!(numbered(&seq_tail("{}"), 1), r#"{"seq":1}"#);
+    }
+}
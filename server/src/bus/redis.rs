@@ -0,0 +1,476 @@
+// server/src/bus/redis.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+//! Bus antar instance lewat Redis pub/sub.
+//!
+//! - publish → `PUBLISH lss:t:<topik>`; satu task per instance `PSUBSCRIBE lss:t:*` lalu
+//!   meneruskan ke kanal lokal (`Hub`). Pesan sendiri juga lewat Redis, jadi urutan
+//!   per topik sama di semua instance.
+//! - viewer: tiap instance menulis presence-nya ke hash `lss:presence:<node>`
+//!   (field `<room>|<viewer key>`) dan penanda hidup `lss:alive:<node>` (TTL diperbarui
+//!   berkala), terdaftar di set `lss:nodes`; viewer unik = gabungan semua node.
+//!   Instance yang mati hilang sendiri setelah TTL habis.
+//! - replay: satu skrip Lua (atomik) mengambil nomor urut `INCR lss:seq:<room>`,
+//!   menyimpan pesan di sorted set `lss:replay:<room>` (skor = nomor urut, dipangkas
+//!   ke REPLAY_LEN) lalu `PUBLISH` — urutan kirim sama dengan urutan nomor. Kedua key
+//!   kedaluwarsa REPLAY_TTL_SECS setelah pesan terakhir room.
+//! - sesi resume: `SET lss:park:<key> EX`, diambil dengan `GETDEL`.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use ::redis::{aio::ConnectionManager, AsyncCommands, RedisError};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use tokio::sync::{broadcast, Mutex};
+
+use super::{
+    presence_add, presence_merge, presence_remove, replay_after, seq_tail, Bus, Hub, Presence, RoomPresence,
+    Topic, REPLAY_LEN, REPLAY_TTL_SECS,
+};
+
+const PREFIX: &str = "lss:t:";
+const NODES: &str = "lss:nodes";
+// presence node dianggap mati bila tidak diperbarui selama ini
+const PRESENCE_TTL_SECS: i64 = 30;
+const HEARTBEAT_SECS: u64 = 10;
+// KEYS: lss:seq:<room>, lss:replay:<room>
+// ARGV: sisa pesan (seq_tail), REPLAY_LEN, REPLAY_TTL_SECS, channel room
+const PUBLISH_REPLAYABLE: &str = r#"
+local seq = redis.call('INCR', KEYS[1])
+local msg = '{"seq":' .. seq .. ARGV[1]
+redis.call('ZADD', KEYS[2], seq, msg)
+redis.call('ZREMRANGEBYRANK', KEYS[2], 0, -tonumber(ARGV[2]) - 1)
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+redis.call('EXPIRE', KEYS[2], ARGV[3])
+redis.call('PUBLISH', ARGV[4], msg)
+return seq
+"#;
+
+pub(crate) struct RedisBus {
+    conn: ConnectionManager,
+    hub: Arc<Hub>,
+    node: String,
+    // presence instance ini (sumber untuk hash presence)
+    viewers: Arc<Mutex<RoomPresence>>,
+    publish_replayable: ::redis::Script,
+}
+
+impl RedisBus {
+    pub async fn connect(url: &str) -> Result<Self, RedisError> {
+        let client = ::redis::Client::open(url)?;
+        let conn = client.get_connection_manager().await?;
+        let hub = Arc::new(Hub::default());
+        let node = uuid::Uuid::new_v4().simple().to_string();
+
+        spawn_subscriber(client, hub.clone());
+        let bus = RedisBus {
+            conn,
+            hub,
+            node,
+            viewers: Arc::new(Mutex::new(HashMap::new())),
+            publish_replayable: ::redis::Script::new(PUBLISH_REPLAYABLE),
+        };
+        bus.spawn_heartbeat();
+        Ok(bus)
+    }
+
+    fn presence_key(node: &str) -> String {
+        format!("lss:presence:{node}")
+    }
+
+    fn alive_key(node: &str) -> String {
+        format!("lss:alive:{node}")
+    }
+
+    fn presence_field(room: &str, key: &str) -> String {
+        format!("{room}|{key}")
+    }
+
+    // viewer yang sama masih punya koneksi di instance lain?
+    async fn held_elsewhere(&self, room: &str, key: &str) -> bool {
+        let mut c = self.conn.clone();
+        let field = RedisBus::presence_field(room, key);
+        for node in self.nodes().await {
+            if node != self.node && c.hexists(RedisBus::presence_key(&node), &field).await.unwrap_or(false) {
+                return true;
+            }
+        }
+        false
+    }
+
+    // presence semua instance yang hidup, sudah digabung per viewer
+    async fn presence_all(&self) -> RoomPresence {
+        let mut c = self.conn.clone();
+        let mut out = RoomPresence::new();
+        for node in self.nodes().await {
+            let fields: HashMap<String, String> = match c.hgetall(RedisBus::presence_key(&node)).await {
+                Ok(f) => f,
+                Err(e) if node == self.node => {
+                    // Redis tidak bisa dibaca: minimal presence instance ini
+                    eprintln!("redis bus: presence read error: {e}");
+                    return self.viewers.lock().await.clone();
+                }
+                Err(_) => continue,
+            };
+            // TTL habis → hash presence ikut hilang, node mati tidak terhitung
+            for (field, json) in fields {
+                let Some((room, _)) = field.rsplit_once('|') else { continue };
+                let Ok(p) = serde_json::from_str::<Presence>(&json) else { continue };
+                presence_merge(&mut out, room.to_string(), p);
+            }
+        }
+        out
+    }
+
+    // tulis ulang snapshot presence instance ini + perpanjang TTL
+    async fn write_presence(&self) {
+        let snapshot = self.viewers.lock().await.clone();
+        if let Err(e) = write_presence(self.conn.clone(), &self.node, &snapshot).await {
+            eprintln!("redis bus: presence error: {e}");
+        }
+    }
+
+    // snapshot ditulis ulang berkala: TTL diperpanjang dan pulih sendiri bila Redis restart
+    fn spawn_heartbeat(&self) {
+        let conn = self.conn.clone();
+        let node = self.node.clone();
+        let viewers = self.viewers.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(Duration::from_secs(HEARTBEAT_SECS));
+            loop {
+                tick.tick().await;
+                let snapshot = viewers.lock().await.clone();
+                if let Err(e) = write_presence(conn.clone(), &node, &snapshot).await {
+                    eprintln!("redis bus: heartbeat error: {e}");
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl Bus for RedisBus {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    fn node(&self) -> &str {
+        &self.node
+    }
+
+    async fn nodes(&self) -> Vec<String> {
+        let mut c = self.conn.clone();
+        let members: Vec<String> = c.smembers(NODES).await.unwrap_or_default();
+        let mut out = vec![self.node.clone()];
+        for node in members {
+            if node == self.node {
+                continue;
+            }
+            let alive: bool = c.exists(RedisBus::alive_key(&node)).await.unwrap_or(true);
+            if alive {
+                out.push(node);
+            } else {
+                let _: Result<(), RedisError> = c.srem(NODES, &node).await;
+            }
+        }
+        out
+    }
+
+    async fn publish(&self, topic: &Topic, msg: String) {
+        let mut c = self.conn.clone();
+        let res: Result<(), RedisError> = c.publish(format!("{PREFIX}{}", topic.key()), msg).await;
+        if let Err(e) = res {
+            eprintln!("redis bus: publish {} error: {e}", topic.key());
+        }
+    }
+
+    async fn subscribe(&self, topic: &Topic) -> broadcast::Receiver<String> {
+        self.hub.subscribe(topic).await
+    }
+
+    async fn release(&self, topic: &Topic) {
+        self.hub.release(topic).await;
+    }
+
+    async fn join(&self, room: &str, viewer: &Presence) -> bool {
+        let first_here = presence_add(&mut *self.viewers.lock().await, room, viewer);
+        self.write_presence().await;
+        first_here && !self.held_elsewhere(room, &viewer.key).await
+    }
+
+    async fn leave(&self, room: &str, key: &str) -> bool {
+        let last_here = presence_remove(&mut *self.viewers.lock().await, room, key);
+        self.write_presence().await;
+        last_here && !self.held_elsewhere(room, key).await
+    }
+
+    async fn viewer_counts(&self) -> HashMap<String, usize> {
+        self.presence_all().await.into_iter().map(|(room, v)| (room, v.len())).collect()
+    }
+
+    async fn viewers(&self, room: &str) -> Vec<Presence> {
+        self.presence_all().await.remove(room).map(|v| v.into_values().collect()).unwrap_or_default()
+    }
+
+    async fn publish_replayable(&self, room: &str, msg: String) -> u64 {
+        let mut c = self.conn.clone();
+        let channel = format!("{PREFIX}{}", Topic::Room(room.to_string()).key());
+        let res: Result<u64, RedisError> = self
+            .publish_replayable
+            .key(format!("lss:seq:{room}"))
+            .key(format!("lss:replay:{room}"))
+            .arg(seq_tail(&msg))
+            .arg(REPLAY_LEN)
+            .arg(REPLAY_TTL_SECS)
+            .arg(channel)
+            .invoke_async(&mut c)
+            .await;
+        match res {
+            Ok(seq) => seq,
+            Err(e) => {
+                // tidak terkirim: client yang tertinggal pesan ini akan resync
+                eprintln!("redis bus: publish room {room} error: {e}");
+                0
+            }
+        }
+    }
+
+    async fn replay(&self, room: &str, since: u64) -> Option<Vec<String>> {
+        let mut c = self.conn.clone();
+        let last: Option<u64> = c.get(format!("lss:seq:{room}")).await.ok()?;
+        let buf: Vec<(String, u64)> = c
+            .zrangebyscore_withscores(format!("lss:replay:{room}"), since + 1, "+inf")
+            .await
+            .ok()?;
+        replay_after(buf.into_iter().map(|(m, seq)| (seq, m)).collect(), last.unwrap_or(0), since)
+    }
+
+    async fn park(&self, key: &str, data: String, ttl_secs: u64) {
+        let mut c = self.conn.clone();
+        let res: Result<(), RedisError> = c.set_ex(format!("lss:park:{key}"), data, ttl_secs).await;
+        if let Err(e) = res {
+            eprintln!("redis bus: park error: {e}");
+        }
+    }
+
+    async fn claim(&self, key: &str) -> Option<String> {
+        let mut c = self.conn.clone();
+        ::redis::cmd("GETDEL").arg(format!("lss:park:{key}")).query_async(&mut c).await.unwrap_or(None)
+    }
+
+    async fn try_lead(&self, task: &str, ttl_secs: u64) -> bool {
+        let mut c = self.conn.clone();
+        let key = format!("lss:lead:{task}");
+        let got: Result<Option<String>, RedisError> = ::redis::cmd("SET")
+            .arg(&key)
+            .arg(&self.node)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs)
+            .query_async(&mut c)
+            .await;
+        match got {
+            Ok(Some(_)) => true,
+            Ok(None) => {
+                // masih pemegang dari putaran sebelumnya → perpanjang
+                let holder: Option<String> = c.get(&key).await.unwrap_or(None);
+                if holder.as_deref() == Some(self.node.as_str()) {
+                    let _: Result<(), RedisError> = c.expire(&key, ttl_secs as i64).await;
+                    true
+                } else {
+                    false
+                }
+            }
+            Err(e) => {
+                eprintln!("redis bus: lead {task} error: {e}");
+                false
+            }
+        }
+    }
+}
+
+/* ===================== helpers lokal ===================== */
+
+async fn write_presence(
+    mut c: ConnectionManager,
+    node: &str,
+    viewers: &RoomPresence,
+) -> Result<(), RedisError> {
+    let key = RedisBus::presence_key(node);
+    let mut pipe = ::redis::pipe();
+    pipe.atomic().del(&key).ignore();
+    for (room, v) in viewers {
+        for p in v.values() {
+            let json = serde_json::to_string(p).unwrap();
+            pipe.hset(&key, RedisBus::presence_field(room, &p.key), json).ignore();
+        }
+    }
+    pipe.expire(&key, PRESENCE_TTL_SECS)
+        .ignore()
+        .set_ex(RedisBus::alive_key(node), 1, PRESENCE_TTL_SECS as u64)
+        .ignore()
+        .sadd(NODES, node)
+        .ignore();
+    pipe.query_async(&mut c).await
+}
+
+// PSUBSCRIBE sekali per instance; tersambung ulang bila koneksi putus.
+fn spawn_subscriber(client: ::redis::Client, hub: Arc<Hub>) {
+    tokio::spawn(async move {
+        loop {
+            match client.get_async_pubsub().await {
+                Ok(mut pubsub) => {
+                    if let Err(e) = pubsub.psubscribe(format!("{PREFIX}*")).await {
+                        eprintln!("redis bus: psubscribe error: {e}");
+                    } else {
+                        let mut stream = pubsub.on_message();
+                        while let Some(msg) = stream.next().await {
+                            let Some(key) = msg.get_channel_name().strip_prefix(PREFIX) else {
+                                continue;
+                            };
+                            if let Ok(payload) = msg.get_payload::<String>() {
+                                hub.deliver(key, payload).await;
+                            }
+                        }
+                    }
+                    eprintln!("redis bus: subscriber terputus, menyambung ulang");
+                }
+                Err(e) => eprintln!("redis bus: subscriber connect error: {e}"),
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::bus::tests::viewer;
+
+    // butuh Redis sungguhan: dilewati bila REDIS_URL tidak diisi (mis. redis://127.0.0.1/)
+    async fn connect() -> Option<RedisBus> {
+        let Ok(url) = env::var("REDIS_URL") else {
+            eprintln!("REDIS_URL not set, skipping redis bus test");
+            return None;
+        };
+        Some(RedisBus::connect(&url).await.expect("redis connect"))
+    }
+
+    fn unique(name: &str) -> String {
+        format!("test-{name}-{}", uuid::Uuid::new_v4().simple())
+    }
+
+    // PSUBSCRIBE berjalan di task sendiri: kirim ulang sampai subscriber siap
+    async fn ready(bus: &RedisBus, topic: &Topic, rx: &mut broadcast::Receiver<String>) {
+        for _ in 0..50 {
+            bus.publish(topic, "ping".into()).await;
+            if let Ok(Ok(_)) = tokio::time::timeout(Duration::from_millis(100), rx.recv()).await {
+                while rx.try_recv().is_ok() {}
+                return;
+            }
+        }
+        panic!("redis subscriber not ready");
+    }
+
+    async fn recv(rx: &mut broadcast::Receiver<String>) -> String {
+        tokio::time::timeout(Duration::from_secs(2), rx.recv()).await.expect("no message").unwrap()
+    }
+
+    #[tokio::test]
+    async fn publish_reaches_other_instance() {
+        let (Some(a), Some(b)) = (connect().await, connect().await) else { return };
+        let topic = Topic::Staff(unique("pub"));
+        let mut rx = b.subscribe(&topic).await;
+        ready(&b, &topic, &mut rx).await;
+
+        a.publish(&topic, "hello".into()).await;
+        assert_eq!(recv(&mut rx).await, "hello");
+    }
+
+    #[tokio::test]
+    async fn join_leave_counts_across_instances() {
+        let (Some(a), Some(b)) = (connect().await, connect().await) else { return };
+        let room = unique("presence");
+
+        assert!(a.join(&room, &viewer("u:1")).await);
+        // viewer yang sama di instance lain bukan viewer baru
+        assert!(!b.join(&room, &viewer("u:1")).await);
+        assert!(b.join(&room, &viewer("u:2")).await);
+
+        assert_eq!(a.viewer_counts().await[&room], 2);
+        let viewers = b.viewers(&room).await;
+        assert_eq!(viewers.len(), 2);
+        assert_eq!(viewers.iter().find(|p| p.key == "u:1").unwrap().connections, 2);
+
+        // u:1 masih terbuka di b
+        assert!(!a.leave(&room, "u:1").await);
+        assert!(b.leave(&room, "u:1").await);
+        assert!(b.leave(&room, "u:2").await);
+        assert!(!a.viewer_counts().await.contains_key(&room));
+    }
+
+    #[tokio::test]
+    async fn publish_replayable_numbers_and_replays() {
+        let (Some(a), Some(b)) = (connect().await, connect().await) else { return };
+        let room = unique("replay");
+        let topic = Topic::Room(room.clone());
+        let mut rx = b.subscribe(&topic).await;
+        ready(&b, &topic, &mut rx).await;
+
+        // dua instance berbagi satu nomor urut per room
+        assert_eq!(a.publish_replayable(&room, r#"{"n":1}"#.into()).await, 1);
+        assert_eq!(b.publish_replayable(&room, r#"{"n":2}"#.into()).await, 2);
+        assert_eq!(a.publish_replayable(&room, r#"{"n":3}"#.into()).await, 3);
+        for i in 1..=3u64 {
+            assert_eq!(recv(&mut rx).await, format!(r#"{{"seq":{i},"n":{i}}}"#));
+        }
+
+        assert_eq!(
+            b.replay(&room, 1).await,
+            Some(vec![r#"{"seq":2,"n":2}"#.to_string(), r#"{"seq":3,"n":3}"#.to_string()])
+        );
+        assert_eq!(a.replay(&room, 3).await, Some(vec![]));
+        assert_eq!(a.replay(&room, 9).await, None);
+    }
+
+    #[tokio::test]
+    async fn replay_buffer_is_trimmed() {
+        let Some(bus) = connect().await else { return };
+        let room = unique("trim");
+        let n = REPLAY_LEN as u64 + 10;
+        for _ in 0..n {
+            bus.publish_replayable(&room, "{}".into()).await;
+        }
+        assert_eq!(bus.replay(&room, 5).await, None);
+        assert_eq!(bus.replay(&room, n - REPLAY_LEN as u64).await.unwrap().len(), REPLAY_LEN);
+    }
+
+    #[tokio::test]
+    async fn park_claim_once_across_instances() {
+        let (Some(a), Some(b)) = (connect().await, connect().await) else { return };
+        let key = unique("park");
+        a.park(&key, "data".into(), 30).await;
+        assert_eq!(b.claim(&key).await.as_deref(), Some("data"));
+        assert_eq!(a.claim(&key).await, None);
+    }
+
+    #[tokio::test]
+    async fn try_lead_single_holder() {
+        let (Some(a), Some(b)) = (connect().await, connect().await) else { return };
+        let task = unique("lead");
+        assert!(a.try_lead(&task, 30).await);
+        assert!(!b.try_lead(&task, 30).await);
+        // pemegang memperpanjang
+        assert!(a.try_lead(&task, 30).await);
+    }
+}
@@ -0,0 +1,63 @@
+// server/src/chat.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{MySql, Pool};
+
+use crate::ws_protocol::Sender;
+
+/// Satu pesan chat tersimpan (dipakai backfill WS dan API admin).
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct ChatOut {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub user: String,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+    // dihapus moderator (hanya terlihat di log admin)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Simpan pesan chat; mengembalikan id baris.
+pub(crate) async fn save(db: &Pool<MySql>, room: &str, from: &Sender, text: &str) -> i64 {
+    sqlx::query!(
+        "INSERT INTO chat_messages(room, user_id, display_name, text) VALUES(?,?,?,?)",
+        room,
+        from.user_id,
+        from.name.as_deref().unwrap_or("anon"),
+        text
+    )
+    .execute(db)
+    .await
+    .unwrap()
+    .last_insert_id() as i64
+}
+
+/// `n` pesan terakhir di room yang tidak dihapus, urut lama → baru.
+pub(crate) async fn recent(db: &Pool<MySql>, room: &str, n: i64) -> Vec<ChatOut> {
+    let mut rows = sqlx::query_as!(
+        ChatOut,
+        r#"
+        SELECT id, user_id, display_name AS user, text, created_at, deleted_at
+        FROM chat_messages WHERE room=? AND deleted_at IS NULL
+        ORDER BY id DESC LIMIT ?
+        "#,
+        room,
+        n
+    )
+    .fetch_all(db)
+    .await
+    .unwrap();
+    rows.reverse();
+    rows
+}
@@ -0,0 +1,194 @@
+// server/src/claims.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// "Comment to buy": komentar seperti "KEEP A12 2" atau "ambil 3" di chat room
+// langsung masuk ke keranjang pengirim. Format: <keyword> [kode] [qty], kode =
+// products.code atau SKU varian; tanpa kode (atau hanya angka yang bukan kode)
+// berlaku untuk produk yang sedang di-pin di room. Keyword diatur lewat CLAIM_KEYWORDS.
+
+use std::env;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{handlers::cart, showcase, ws_protocol::Sender, AppState};
+
+const MAX_QTY: i32 = 99;
+
+/// Hasil parsing satu komentar.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Claim {
+    // None = produk yang sedang di-pin
+    pub code: Option<String>,
+    pub qty: i32,
+    // qty ditulis eksplisit (kata ketiga)
+    pub qty_given: bool,
+}
+
+/// Satu klaim yang sudah diproses (balasan privat ke pembeli + feed host).
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct ClaimOut {
+    pub id: i64,
+    pub chat_id: Option<i64>,
+    pub user_id: i64,
+    pub user: String,
+    pub code: String,
+    pub qty: i32,
+    pub product_id: Option<i64>,
+    pub variant_id: Option<i64>,
+    pub product_name: Option<String>,
+    // "added" | "failed"
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// CLAIM_KEYWORDS="keep,ambil" (default); kosong = fitur mati.
+pub(crate) fn keywords_from_env() -> Vec<String> {
+    env::var("CLAIM_KEYWORDS")
+        .unwrap_or_else(|_| "keep,ambil".into())
+        .split(',')
+        .map(|k| k.trim().to_lowercase())
+        .filter(|k| !k.is_empty())
+        .collect()
+}
+
+/// "KEEP A12 2" → Claim{A12, 2}; "ambil 3" → Claim{3, 1}; "keep" → Claim{pin, 1};
+/// "keep a12 x2" juga diterima. Bukan klaim bila kata pertama bukan keyword.
+pub(crate) fn parse(keywords: &[String], text: &str) -> Option<Claim> {
+    let mut words = text.split_whitespace();
+    let kw = words.next()?.trim_end_matches([':', ',', '!']).to_lowercase();
+    if !keywords.contains(&kw) {
+        return None;
+    }
+    let code = words
+        .next()
+        .map(|c| c.trim_end_matches([',', '.', '!']).to_uppercase())
+        .filter(|c| !c.is_empty());
+    let (qty, qty_given) = match words.next() {
+        Some(q) => {
+            let q = q.trim_start_matches(['x', 'X']);
+            match q.parse::<i32>() {
+                Ok(n) if (1..=MAX_QTY).contains(&n) => (n, true),
+                Ok(_) => return None,
+                // kata lain setelah kode ("keep A12 yang merah") → qty 1
+                Err(_) => (1, false),
+            }
+        }
+        None => (1, false),
+    };
+    Some(Claim { code, qty, qty_given })
+}
+
+/// Cari produk/varian untuk kode, masukkan ke keranjang pengirim, catat di chat_claims.
+pub(crate) async fn process(
+    state: &AppState,
+    room: &str,
+    from: &Sender,
+    chat_id: Option<i64>,
+    claim: Claim,
+) -> Option<ClaimOut> {
+    let user_id = from.user_id?;
+
+    let mut qty = claim.qty;
+    let (mut product_id, variant_id, mut product_name) = match &claim.code {
+        Some(code) => resolve_code(state, code).await,
+        None => (None, None, None),
+    };
+    // "keep" / "ambil 3" → produk yang sedang di-pin (angka = qty bila bukan kode produk)
+    if product_id.is_none() {
+        let bare_qty = match &claim.code {
+            None => Some(qty),
+            Some(c) if !claim.qty_given => c.parse::<i32>().ok().filter(|n| (1..=MAX_QTY).contains(n)),
+            Some(_) => None,
+        };
+        if let Some(n) = bare_qty {
+            if let Some(pin) = showcase::current(&state.db, room).await {
+                qty = n;
+                product_id = Some(pin.product_id);
+                product_name = Some(pin.name);
+            }
+        }
+    }
+    let code = claim.code.unwrap_or_default();
+
+    let error = match product_id {
+        None if code.is_empty() => Some("no product pinned"),
+        None => Some("unknown product code"),
+        Some(pid) => cart::add_item(&state.db, user_id, pid, variant_id, qty, Some(room))
+            .await
+            .err()
+            .map(|e| e.as_str()),
+    };
+    let status = if error.is_none() { "added" } else { "failed" };
+
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO chat_claims(room, chat_id, user_id, code, qty, product_id, variant_id, status, error)
+        VALUES(?,?,?,?,?,?,?,?,?)
+        "#,
+        room,
+        chat_id,
+        user_id,
+        code,
+        qty,
+        product_id,
+        variant_id,
+        status,
+        error
+    )
+    .execute(&state.db)
+    .await
+    .unwrap()
+    .last_insert_id() as i64;
+
+    Some(ClaimOut {
+        id,
+        chat_id,
+        user_id,
+        user: from.name.clone().unwrap_or_default(),
+        code,
+        qty,
+        product_id,
+        variant_id,
+        product_name,
+        status: status.to_string(),
+        error: error.map(str::to_string),
+        created_at: Utc::now(),
+    })
+}
+
+// kode produk dulu, lalu SKU varian → (product_id, variant_id, nama)
+async fn resolve_code(state: &AppState, code: &str) -> (Option<i64>, Option<i64>, Option<String>) {
+    if let Some(p) = sqlx::query!("SELECT id, name FROM products WHERE code=? AND is_active=1 LIMIT 1", code)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap()
+    {
+        return (Some(p.id), None, Some(p.name));
+    }
+    if let Some(v) = sqlx::query!(
+        r#"
+        SELECT v.id, v.product_id, p.name
+        FROM product_variants v JOIN products p ON p.id = v.product_id
+        WHERE v.sku=? AND v.is_active=1 AND p.is_active=1
+        LIMIT 1
+        "#,
+        code
+    )
+    .fetch_optional(&state.db)
+    .await
+    .unwrap()
+    {
+        return (Some(v.product_id), Some(v.id), Some(v.name));
+    }
+    (None, None, None)
+}
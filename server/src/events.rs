@@ -0,0 +1,44 @@
+// server/src/events.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Topik event per audiens untuk koneksi `/ws/_events` (pengganti satu kanal global):
+// - admin: semua order, statistik viewer, event lintas room
+// - user:  hanya order milik user itu (order / order_update / order_deleted)
+// Event room tetap lewat kanal room masing-masing (lihat room_send). Topik yang
+// diterima ditentukan server dari sesi di handle_socket, bukan dari client.
+// Pengiriman lewat `Bus` sehingga sampai ke koneksi di instance mana pun.
+
+use crate::{
+    bus::Topic,
+    ws_protocol::{self, Body},
+    AppState,
+};
+
+pub(crate) async fn to_admin(state: &AppState, room: Option<&str>, body: Body) {
+    state.bus.publish(&Topic::Admin, ws_protocol::event(room, body)).await;
+}
+
+/// Tidak ada koneksi user → event dibuang (order tetap bisa dibaca via REST).
+pub(crate) async fn to_user(state: &AppState, user_id: i64, body: Body) {
+    state.bus.publish(&Topic::User(user_id), ws_protocol::event(None, body)).await;
+}
+
+/// Event order ke admin + pemilik order.
+pub(crate) async fn order(state: &AppState, order_id: i64, body: Body) {
+    let owner = sqlx::query!("SELECT user_id FROM orders WHERE id=?", order_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap();
+    if let Some(o) = owner {
+        to_user(state, o.user_id, body.clone()).await;
+    }
+    to_admin(state, None, body).await;
+}
@@ -0,0 +1,328 @@
+// server/src/flash_sales.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Flash sale: harga coret terjadwal per produk (berlaku juga untuk semua varian).
+// Harga sale dinilai server saat list produk & saat item masuk keranjang; item
+// sale disimpan sebagai baris keranjang tersendiri (cart_items.flash_sale_id).
+// Kuota (qty_cap) dihitung dari order yang tidak batal + keranjang open yang
+// reservasinya masih berlaku (`RESERVE_MINUTES` sejak `cart_items.reserved_at`,
+// diperbarui setiap qty baris sale bertambah), jadi item yang dihapus, keranjang
+// yang ditinggal dan order batal mengembalikan kuota. Checkout menilai ulang kuota
+// (`confirm`) untuk reservasi yang sudah lewat. Sale dengan `room` hanya berlaku
+// untuk viewer yang menonton room itu.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, MySqlConnection, Pool};
+
+use crate::{bus::Topic, events, room_send, ws_protocol::{self, Body}, AppState};
+
+// interval cek sale yang mulai / selesai (untuk event siaran)
+const TICK_SECS: u64 = 5;
+// lama kuota item sale di keranjang open tetap dipesan (sejak reserved_at)
+const RESERVE_MINUTES: i64 = 15;
+
+/// Flash sale yang sedang berjalan untuk satu produk.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct ActiveSale {
+    pub id: i64,
+    pub product_id: i64,
+    pub sale_price: i32,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    // None = semua room / katalog
+    pub room: Option<String>,
+    // sisa kuota; None = tanpa batas
+    pub remaining: Option<i64>,
+}
+
+/// Kenapa qty tidak bisa (lagi) dibeli dengan harga sale.
+#[derive(Debug)]
+pub(crate) enum QuotaError {
+    Ended,
+    Limit { remaining: i64 },
+}
+
+// kolom sale + qty terpakai (order yang tidak batal + reservasi keranjang open);
+// `?` pertama = RESERVE_MINUTES
+const SALE_SELECT: &str = "
+    SELECT f.id, f.product_id, f.sale_price, f.starts_at, f.ends_at, f.room, f.qty_cap, f.is_active,
+           CAST((SELECT COALESCE(SUM(ci.qty), 0)
+                 FROM cart_items ci
+                 JOIN carts c ON c.id = ci.cart_id
+                 LEFT JOIN orders o ON o.cart_id = c.id
+                 WHERE ci.flash_sale_id = f.id
+                   AND ((c.status = 'open' AND ci.reserved_at > NOW() - INTERVAL ? MINUTE)
+                        OR o.status NOT IN ('cancelled','deleted'))) AS SIGNED) AS used
+    FROM flash_sales f";
+
+#[derive(sqlx::FromRow)]
+struct SaleRow {
+    id: i64,
+    product_id: i64,
+    sale_price: i32,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    room: Option<String>,
+    qty_cap: Option<i32>,
+    is_active: bool,
+    used: i64,
+}
+
+impl SaleRow {
+    fn into_active(self) -> ActiveSale {
+        ActiveSale {
+            id: self.id,
+            product_id: self.product_id,
+            sale_price: self.sale_price,
+            starts_at: self.starts_at,
+            ends_at: self.ends_at,
+            room: self.room,
+            remaining: self.qty_cap.map(|cap| (cap as i64 - self.used).max(0)),
+        }
+    }
+}
+
+/// Sale berjalan per produk untuk katalog; `room` = room yang sedang ditonton viewer.
+/// Bila ada beberapa, dipakai harga termurah yang kuotanya belum habis.
+pub(crate) async fn active_by_product(db: &Pool<MySql>, room: Option<&str>) -> HashMap<i64, ActiveSale> {
+    let sql = format!(
+        "{SALE_SELECT}
+         WHERE f.is_active=1 AND f.starts_at <= NOW() AND f.ends_at > NOW()
+           AND (f.room IS NULL OR f.room = ?)
+         ORDER BY f.sale_price ASC, f.id DESC"
+    );
+    let rows: Vec<SaleRow> = sqlx::query_as(&sql)
+        .bind(RESERVE_MINUTES)
+        .bind(room)
+        .fetch_all(db)
+        .await
+        .unwrap();
+
+    let mut out: HashMap<i64, ActiveSale> = HashMap::new();
+    for r in rows {
+        let sale = r.into_active();
+        // yang sudah habis hanya dipakai bila tidak ada pilihan lain (tampil "habis")
+        let replace = match out.get(&sale.product_id) {
+            None => true,
+            Some(cur) => cur.remaining == Some(0) && sale.remaining != Some(0),
+        };
+        if replace {
+            out.insert(sale.product_id, sale);
+        }
+    }
+    out
+}
+
+/// Pesan kuota `qty` dari sale yang berlaku untuk produk ini dan lebih murah dari
+/// `price` (harga yang dipakai bila tanpa sale). Baris sale dikunci (FOR UPDATE)
+/// sampai transaksi keranjang di-commit agar kuota tidak terlewati.
+/// Ok(None) = tidak ada sale yang lebih murah (atau kuota habis) → `price`, kuota utuh.
+pub(crate) async fn reserve(
+    conn: &mut MySqlConnection,
+    product_id: i64,
+    room: Option<&str>,
+    price: i32,
+    qty: i32,
+) -> Result<Option<ActiveSale>, QuotaError> {
+    let ids = sqlx::query!(
+        r#"
+        SELECT id FROM flash_sales
+        WHERE product_id=? AND is_active=1 AND starts_at <= NOW() AND ends_at > NOW()
+          AND (room IS NULL OR room = ?) AND sale_price < ?
+        ORDER BY sale_price ASC, id DESC
+        "#,
+        product_id,
+        room,
+        price
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+
+    let mut limited = None;
+    for r in ids {
+        let sale = load(conn, r.id, true).await.into_active();
+        match sale.remaining {
+            Some(0) => continue,
+            Some(n) if n < qty as i64 => limited = limited.or(Some(n)),
+            _ => return Ok(Some(sale)),
+        }
+    }
+    match limited {
+        Some(remaining) => Err(QuotaError::Limit { remaining }),
+        None => Ok(None),
+    }
+}
+
+/// Tambah qty pada baris keranjang sale yang sudah ada (update qty di keranjang).
+pub(crate) async fn reserve_more(
+    conn: &mut MySqlConnection,
+    sale_id: i64,
+    extra: i32,
+) -> Result<(), QuotaError> {
+    let row = load(conn, sale_id, true).await;
+    let now = Utc::now();
+    if !row.is_active || row.starts_at > now || row.ends_at <= now {
+        return Err(QuotaError::Ended);
+    }
+    let sale = row.into_active();
+    match sale.remaining {
+        Some(n) if n < extra as i64 => Err(QuotaError::Limit { remaining: n }),
+        _ => Ok(()),
+    }
+}
+
+/// Checkout: `qty` item sale di keranjang `cart_id` harus masih muat di kuota. Baris
+/// sale dikunci sampai order tersimpan; pemakaian dihitung dari order + reservasi
+/// keranjang lain, jadi reservasi sendiri yang sudah lewat dinilai ulang di sini.
+pub(crate) async fn confirm(
+    conn: &mut MySqlConnection,
+    sale_id: i64,
+    cart_id: i64,
+    qty: i64,
+) -> Result<(), QuotaError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT f.qty_cap,
+               CAST((SELECT COALESCE(SUM(ci.qty), 0)
+                     FROM cart_items ci
+                     JOIN carts c ON c.id = ci.cart_id
+                     LEFT JOIN orders o ON o.cart_id = c.id
+                     WHERE ci.flash_sale_id = f.id AND c.id <> ?
+                       AND ((c.status = 'open' AND ci.reserved_at > NOW() - INTERVAL ? MINUTE)
+                            OR o.status NOT IN ('cancelled','deleted'))) AS SIGNED) AS `used!: i64`
+        FROM flash_sales f WHERE f.id=?
+        FOR UPDATE
+        "#,
+        cart_id,
+        RESERVE_MINUTES,
+        sale_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .unwrap();
+    match row.qty_cap.map(|cap| (cap as i64 - row.used).max(0)) {
+        Some(remaining) if remaining < qty => Err(QuotaError::Limit { remaining }),
+        _ => Ok(()),
+    }
+}
+
+/// Siarkan `flash_sale_started` / `flash_sale_ended` saat jadwal tercapai.
+/// Sale khusus room dikirim ke room itu; sale umum ke semua room aktif + topik admin.
+pub(crate) fn spawn_ticker(state: AppState) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(Duration::from_secs(TICK_SECS));
+        loop {
+            tick.tick().await;
+            announce_due(&state).await;
+        }
+    });
+}
+
+/// Event sale selesai untuk sale yang dinonaktifkan admin sebelum waktunya.
+pub(crate) async fn announce_ended(state: &AppState, sale_id: i64) {
+    let row = sqlx::query!(
+        "SELECT product_id, room, notified FROM flash_sales WHERE id=?",
+        sale_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .unwrap();
+    let Some(r) = row.filter(|r| r.notified == "started") else {
+        return;
+    };
+    let res = sqlx::query!("UPDATE flash_sales SET notified='ended' WHERE id=? AND notified='started'", sale_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    if res.rows_affected() == 0 {
+        return;
+    }
+    let body = Body::FlashSaleEnded { id: sale_id, product_id: r.product_id };
+    broadcast(state, r.room.as_deref(), body).await;
+}
+
+/* ===================== helpers lokal ===================== */
+
+async fn load(conn: &mut MySqlConnection, sale_id: i64, lock: bool) -> SaleRow {
+    let sql = if lock {
+        format!("{SALE_SELECT} WHERE f.id=? FOR UPDATE")
+    } else {
+        format!("{SALE_SELECT} WHERE f.id=?")
+    };
+    sqlx::query_as(&sql)
+        .bind(RESERVE_MINUTES)
+        .bind(sale_id)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap()
+}
+
+async fn announce_due(state: &AppState) {
+    let started = sqlx::query!(
+        r#"
+        SELECT id FROM flash_sales
+        WHERE is_active=1 AND notified='none' AND starts_at <= NOW() AND ends_at > NOW()
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+    for r in started {
+        // instance lain bisa menjalankan ticker yang sama: hanya yang berhasil mengubah yang menyiarkan
+        let res = sqlx::query!("UPDATE flash_sales SET notified='started' WHERE id=? AND notified='none'", r.id)
+            .execute(&state.db)
+            .await
+            .unwrap();
+        if res.rows_affected() == 0 {
+            continue;
+        }
+        let mut conn = state.db.acquire().await.unwrap();
+        let sale = load(&mut conn, r.id, false).await.into_active();
+        drop(conn);
+        let room = sale.room.clone();
+        broadcast(state, room.as_deref(), Body::FlashSaleStarted(sale)).await;
+    }
+
+    let ended = sqlx::query!(
+        r#"
+        SELECT id, product_id, room FROM flash_sales
+        WHERE notified='started' AND (ends_at <= NOW() OR is_active=0)
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+    for r in ended {
+        let res = sqlx::query!("UPDATE flash_sales SET notified='ended' WHERE id=? AND notified='started'", r.id)
+            .execute(&state.db)
+            .await
+            .unwrap();
+        if res.rows_affected() == 0 {
+            continue;
+        }
+        let body = Body::FlashSaleEnded { id: r.id, product_id: r.product_id };
+        broadcast(state, r.room.as_deref(), body).await;
+    }
+}
+
+async fn broadcast(state: &AppState, room: Option<&str>, body: Body) {
+    match room {
+        Some(room) => room_send(state, room, body).await,
+        None => {
+            state.bus.publish(&Topic::AllRooms, ws_protocol::event(None, body.clone())).await;
+            events::to_admin(state, None, body).await;
+        }
+    }
+}
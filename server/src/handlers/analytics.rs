@@ -0,0 +1,216 @@
+// server/src/handlers/analytics.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Dashboard analitik admin: ringkasan + time series per stream / per room,
+// dan ekspor XLSX per stream. Perekaman ada di analytics.rs.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    analytics::{self, Sample, Summary},
+    ensure_admin,
+    streams::{self, StreamOut},
+    AppState,
+};
+
+#[derive(Deserialize)]
+struct RangeQuery {
+    // default: 24 jam terakhir
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/streams/:id/analytics", get(api_admin_stream_analytics))
+        .route("/api/admin/streams/:id/analytics/export", get(api_admin_stream_analytics_export_xlsx))
+        .route("/api/admin/rooms/:room/analytics", get(api_admin_room_analytics))
+}
+
+/* ===================== Handlers ===================== */
+
+async fn api_admin_stream_analytics(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let (stream, summary, series) = match load_stream(&state, id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+    Json(json!({ "stream": stream, "summary": summary, "series": series })).into_response()
+}
+
+// Jendela waktu bebas (maks. 31 hari); order dihitung dari stream di room ini.
+async fn api_admin_room_analytics(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+    Query(q): Query<RangeQuery>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let to = q.to.unwrap_or_else(Utc::now);
+    let from = q.from.unwrap_or(to - Duration::hours(24));
+    if from >= to {
+        return (axum::http::StatusCode::BAD_REQUEST, "from must be before to").into_response();
+    }
+    if to - from > Duration::days(31) {
+        return (axum::http::StatusCode::BAD_REQUEST, "range too large (max 31 days)").into_response();
+    }
+
+    let summary = analytics::summary(&state.db, &room, from, to, None).await;
+    let series = analytics::series(&state.db, &room, from, to, None).await;
+    Json(json!({ "room": room, "summary": summary, "series": series })).into_response()
+}
+
+// EXPORT: ringkasan, funnel, dan time series satu stream ke .xlsx
+async fn api_admin_stream_analytics_export_xlsx(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let (stream, s, series) = match load_stream(&state, id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    let mut wb = rust_xlsxwriter::Workbook::new();
+
+    // Sheet 1: ringkasan
+    let ws = wb.add_worksheet();
+    ws.set_name("Ringkasan").ok();
+    ws.set_column_width(0, 28).ok();
+    ws.set_column_width(1, 32).ok();
+    let text_rows: [(&str, String); 6] = [
+        ("Stream ID", stream.id.to_string()),
+        ("Judul", stream.title.clone()),
+        ("Room", stream.room.clone()),
+        ("Host", stream.host.clone().unwrap_or_default()),
+        ("Mulai", s.from.to_rfc3339()),
+        ("Selesai", s.to.to_rfc3339()),
+    ];
+    let num_rows: [(&str, f64); 14] = [
+        ("Peak viewer", s.peak_viewers as f64),
+        ("Rata-rata viewer", s.avg_viewers),
+        ("Viewer unik", s.unique_viewers as f64),
+        ("Total watch time (detik)", s.watch_secs as f64),
+        ("Rata-rata watch time (detik)", s.avg_watch_secs as f64),
+        ("Pesan chat", s.chat_messages as f64),
+        ("User yang chat", s.chatters as f64),
+        ("Add-to-cart", s.cart_adds as f64),
+        ("Qty di keranjang", s.cart_qty as f64),
+        ("Order", s.orders as f64),
+        ("Omzet", s.revenue as f64),
+        ("Order dibayar", s.paid_orders as f64),
+        ("Omzet dibayar", s.paid_revenue as f64),
+        ("Durasi (detik)", (s.to - s.from).num_seconds() as f64),
+    ];
+    let mut r: u32 = 0;
+    for (label, value) in text_rows {
+        ws.write(r, 0, label).ok();
+        ws.write(r, 1, value).ok();
+        r += 1;
+    }
+    for (label, value) in num_rows {
+        ws.write(r, 0, label).ok();
+        ws.write_number(r, 1, value).ok();
+        r += 1;
+    }
+
+    // Sheet 2: funnel konversi
+    let ws = wb.add_worksheet();
+    ws.set_name("Funnel").ok();
+    ws.write(0, 0, "Langkah").ok();
+    ws.write(0, 1, "User").ok();
+    ws.write(0, 2, "Rasio").ok();
+    ws.set_column_width(0, 18).ok();
+    let mut r: u32 = 1;
+    for step in &s.funnel {
+        ws.write(r, 0, step.step).ok();
+        ws.write_number(r, 1, step.users as f64).ok();
+        ws.write_number(r, 2, step.rate).ok();
+        r += 1;
+    }
+
+    // Sheet 3: time series
+    let ws = wb.add_worksheet();
+    ws.set_name("Time series").ok();
+    ws.write(0, 0, "Waktu").ok();
+    ws.write(0, 1, "Viewer").ok();
+    ws.write(0, 2, "Chat").ok();
+    ws.write(0, 3, "Add-to-cart").ok();
+    ws.set_column_width(0, 26).ok();
+    let mut r: u32 = 1;
+    for p in &series {
+        ws.write(r, 0, p.sampled_at.to_rfc3339()).ok();
+        ws.write_number(r, 1, p.viewers as f64).ok();
+        ws.write_number(r, 2, p.chat_messages as f64).ok();
+        ws.write_number(r, 3, p.cart_adds as f64).ok();
+        r += 1;
+    }
+
+    let buf = match wb.save_to_buffer() {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("export stream analytics XLSX error: {e:?}");
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "export error").into_response();
+        }
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        axum::http::header::CONTENT_TYPE,
+        HeaderValue::from_static("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    );
+    headers.insert(
+        axum::http::header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&format!("attachment; filename=\"stream-{}.xlsx\"", stream.id)).unwrap(),
+    );
+    (headers, buf).into_response()
+}
+
+/* ===================== helpers lokal ===================== */
+
+// Jendela stream: started_at .. ended_at (atau sekarang bila masih live).
+async fn load_stream(
+    state: &AppState,
+    id: i64,
+) -> Result<(StreamOut, Summary, Vec<Sample>), Response> {
+    let Some(stream) = streams::get(&state.db, id).await else {
+        return Err((axum::http::StatusCode::NOT_FOUND, "stream not found").into_response());
+    };
+    let Some(from) = stream.started_at else {
+        return Err((axum::http::StatusCode::CONFLICT, "stream not started").into_response());
+    };
+    let to = stream.ended_at.unwrap_or_else(Utc::now);
+
+    let summary = analytics::summary(&state.db, &stream.room, from, to, Some(id)).await;
+    let series = analytics::series(&state.db, &stream.room, from, to, Some(id)).await;
+    Ok((stream, summary, series))
+}
@@ -0,0 +1,211 @@
+// server/src/handlers/auctions.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Lelang lewat REST: lihat lelang room, menawar (sama dengan WS `bid`), dan
+// kontrol admin (buka / tutup sekarang / batalkan). Lihat auctions.rs.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    auctions::{self, StartIn},
+    ensure_admin, get_user_from_cookie, AppState,
+};
+
+#[derive(Deserialize)]
+struct BidIn {
+    amount: i32,
+}
+
+#[derive(Deserialize)]
+struct AuctionsQuery {
+    room: Option<String>,
+    // "running" | "closed" | "cancelled"
+    status: Option<String>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct AuctionRow {
+    id: i64,
+    room: String,
+    product_id: i64,
+    product_name: String,
+    start_price: i32,
+    current_bid: Option<i32>,
+    winner_id: Option<i64>,
+    winner: Option<String>,
+    bid_count: i32,
+    ends_at: DateTime<Utc>,
+    status: String,
+    cart_id: Option<i64>,
+    created_at: DateTime<Utc>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/rooms/:room/auction", get(api_room_auction))
+        .route("/api/auctions/:id", get(api_auction_detail))
+        .route("/api/auctions/:id/bids", post(api_auction_bid))
+        .route("/api/admin/auctions", get(api_admin_auctions_list))
+        .route("/api/admin/rooms/:room/auctions", post(api_admin_auction_start))
+        .route("/api/admin/auctions/:id/close", post(api_admin_auction_close))
+        .route("/api/admin/auctions/:id/cancel", post(api_admin_auction_cancel))
+}
+
+/* ===================== Handlers ===================== */
+
+// Publik: lelang yang sedang berjalan di room (null bila tidak ada).
+async fn api_room_auction(State(state): State<AppState>, Path(room): Path<String>) -> Response {
+    Json(auctions::current(&state.db, &room).await).into_response()
+}
+
+// Publik: keadaan lelang + 20 tawaran terakhir.
+async fn api_auction_detail(State(state): State<AppState>, Path(id): Path<i64>) -> Response {
+    let Some(auction) = auctions::get(&state.db, id).await else {
+        return (axum::http::StatusCode::NOT_FOUND, "auction not found").into_response();
+    };
+    let bids = auctions::bids(&state.db, id, 20).await;
+    Json(json!({ "auction": auction, "bids": bids })).into_response()
+}
+
+async fn api_auction_bid(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Json(inp): Json<BidIn>,
+) -> Response {
+    let (_, user) = get_user_from_cookie(&jar, &state).await;
+    let Some(u) = user else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "login first").into_response();
+    };
+    match auctions::bid(&state, id, None, u.id, &u.name, inp.amount).await {
+        Ok(()) => Json(json!({"ok": true})).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+// Terbaru dulu; total data lewat header X-Total-Count.
+async fn api_admin_auctions_list(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(q): Query<AuctionsQuery>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let room = q.room.as_deref().filter(|r| !r.is_empty());
+    let status = match q.status.as_deref().filter(|s| !s.is_empty()) {
+        None => None,
+        Some(s @ ("running" | "closed" | "cancelled")) => Some(s),
+        Some(_) => return (axum::http::StatusCode::BAD_REQUEST, "invalid status").into_response(),
+    };
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    let page = q.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * limit;
+
+    let total = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS n FROM auctions
+        WHERE (? IS NULL OR room = ?) AND (? IS NULL OR status = ?)
+        "#,
+        room,
+        room,
+        status,
+        status
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap()
+    .n;
+
+    let rows = sqlx::query_as!(
+        AuctionRow,
+        r#"
+        SELECT a.id, a.room, a.product_id, p.name AS product_name, a.start_price, a.current_bid,
+               a.current_bidder_id AS winner_id, u.name AS `winner?`, a.bid_count, a.ends_at,
+               a.status, a.cart_id, a.created_at
+        FROM auctions a
+        JOIN products p ON p.id = a.product_id
+        LEFT JOIN users u ON u.id = a.current_bidder_id
+        WHERE (? IS NULL OR a.room = ?) AND (? IS NULL OR a.status = ?)
+        ORDER BY a.id DESC
+        LIMIT ? OFFSET ?
+        "#,
+        room,
+        room,
+        status,
+        status,
+        limit,
+        offset
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+    (headers, Json(rows)).into_response()
+}
+
+async fn api_admin_auction_start(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+    Json(inp): Json<StartIn>,
+) -> Response {
+    let Some(admin) = ensure_admin(&jar, &state).await else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    };
+    match auctions::start(&state, &room, Some(admin.id), inp).await {
+        Ok(a) => Json(a).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+// Palu diketuk sekarang: penawar tertinggi menang.
+async fn api_admin_auction_close(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    match auctions::close_now(&state, id).await {
+        Ok(a) => Json(a).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn api_admin_auction_cancel(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    match auctions::cancel(&state, id).await {
+        Ok(()) => Json(json!({"ok": true})).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -20,32 +20,56 @@ use axum_extra::extract::cookie::CookieJar;
 use serde::{Deserialize, Serialize};
 use sqlx::{MySql, Pool};
 
-use crate::{get_user_from_cookie, AppState};
+use crate::{
+    flash_sales::{self, QuotaError},
+    get_user_from_cookie, showcase, streams,
+    vouchers::{self, VoucherError},
+    AppState,
+};
 
 #[derive(Deserialize)]
 struct AddItemReq {
     product_id: i64,
+    // wajib jika produk punya varian aktif
+    variant_id: Option<i64>,
     qty: i32,
+    // room live yang sedang ditonton (atribusi produk yang di-pin)
+    room: Option<String>,
 }
 #[derive(Deserialize)]
 struct UpdateItemReq {
     qty: i32,
 }
+#[derive(Deserialize)]
+struct ApplyVoucherReq {
+    code: String,
+}
 
 #[derive(Serialize)]
 struct CartView {
     cart_id: i64,
     items: Vec<CartItemView>,
     subtotal: i64,
+    voucher: Option<vouchers::Applied>,
+    // voucher terpasang tapi (sekarang) tidak memenuhi syarat
+    voucher_error: Option<&'static str>,
+    discount: i64,
+    total: i64,
 }
 #[derive(Serialize)]
 struct CartItemView {
     id: i64,
     product_id: i64,
+    variant_id: Option<i64>,
+    sku: Option<String>,
+    variant_label: Option<String>,
     name: String,
     qty: i32,
     price: i32,
     line_total: i64,
+    // baris harga flash sale / barang menang lelang
+    flash_sale_id: Option<i64>,
+    auction_id: Option<i64>,
 }
 
 pub fn routes() -> Router<AppState> {
@@ -54,6 +78,8 @@ pub fn routes() -> Router<AppState> {
         .route("/api/cart/items", post(api_cart_add_item))
         .route("/api/cart/items/:id", put(api_cart_update_item))
         .route("/api/cart/items/:id", delete(api_cart_delete_item))
+        .route("/api/cart/voucher", post(api_cart_apply_voucher))
+        .route("/api/cart/voucher", delete(api_cart_remove_voucher))
 }
 
 /* ==================== handlers ==================== */
@@ -71,8 +97,10 @@ async fn api_cart_get(
 
     let rows = sqlx::query!(
         r#"
-        SELECT ci.id, ci.product_id, ci.qty, ci.price_at_add, p.name
+        SELECT ci.id, ci.product_id, ci.variant_id, ci.qty, ci.price_at_add, p.name,
+               ci.flash_sale_id, ci.auction_id, ci.watch_room, v.sku, v.option1, v.option2, v.option3
         FROM cart_items ci JOIN products p ON ci.product_id=p.id
+        LEFT JOIN product_variants v ON ci.variant_id=v.id
         WHERE ci.cart_id=?
         "#,
         cart_id
@@ -82,21 +110,55 @@ async fn api_cart_get(
     .unwrap();
 
     let mut items = vec![];
+    let mut lines = vec![];
     let mut subtotal: i64 = 0;
     for r in rows {
         let line = r.qty as i64 * r.price_at_add as i64;
         subtotal += line;
+        lines.push(vouchers::Line { product_id: r.product_id, line_total: line, room: r.watch_room });
         items.push(CartItemView {
             id: r.id,
             product_id: r.product_id,
+            variant_id: r.variant_id,
+            sku: r.sku,
+            variant_label: variant_label(&[r.option1, r.option2, r.option3]),
             name: r.name,
             qty: r.qty,
             price: r.price_at_add,
             line_total: line,
+            flash_sale_id: r.flash_sale_id,
+            auction_id: r.auction_id,
         });
     }
 
-    Json(CartView { cart_id, items, subtotal }).into_response()
+    // voucher dinilai ulang setiap kali (isi keranjang / kuota bisa berubah)
+    let voucher_id = sqlx::query!("SELECT voucher_id FROM carts WHERE id=?", cart_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap()
+        .voucher_id;
+    let (voucher, voucher_error) = match voucher_id {
+        None => (None, None),
+        Some(vid) => {
+            let mut conn = state.db.acquire().await.unwrap();
+            match vouchers::evaluate(&mut conn, vid, u.id, &lines, false).await {
+                Ok(a) => (Some(a), None),
+                Err(e) => (None, Some(e.as_str())),
+            }
+        }
+    };
+    let discount = voucher.as_ref().map_or(0, |v| v.discount);
+
+    Json(CartView {
+        cart_id,
+        items,
+        subtotal,
+        voucher,
+        voucher_error,
+        discount,
+        total: subtotal - discount,
+    })
+    .into_response()
 }
 
 async fn api_cart_add_item(
@@ -110,47 +172,10 @@ async fn api_cart_add_item(
     }
     let u = user.unwrap();
 
-    let prod = sqlx::query!(
-        "SELECT id, price_idr FROM products WHERE id=? AND is_active=1",
-        req.product_id
-    )
-    .fetch_optional(&state.db)
-    .await
-    .unwrap();
-    if prod.is_none() {
-        return (axum::http::StatusCode::BAD_REQUEST, "product missing").into_response();
-    }
-    let prod = prod.unwrap();
-
-    let cart_id = ensure_viewer_cart(&state.db, u.id).await;
-
-    if let Some(ci) = sqlx::query!(
-        "SELECT id, qty FROM cart_items WHERE cart_id=? AND product_id=?",
-        cart_id,
-        prod.id
-    )
-    .fetch_optional(&state.db)
-    .await
-    .unwrap()
-    {
-        let _ = sqlx::query!("UPDATE cart_items SET qty=? WHERE id=?", ci.qty + req.qty, ci.id)
-            .execute(&state.db)
-            .await
-            .unwrap();
-    } else {
-        let _ = sqlx::query!(
-            "INSERT INTO cart_items(cart_id,product_id,qty,price_at_add) VALUES(?,?,?,?)",
-            cart_id,
-            prod.id,
-            req.qty,
-            prod.price_idr
-        )
-        .execute(&state.db)
-        .await
-        .unwrap();
+    match add_item(&state.db, u.id, req.product_id, req.variant_id, req.qty, req.room.as_deref()).await {
+        Ok(_) => Json(serde_json::json!({ "ok": true })).into_response(),
+        Err(e) => e.into_response(),
     }
-
-    Json(serde_json::json!({ "ok": true })).into_response()
 }
 
 async fn api_cart_update_item(
@@ -167,8 +192,12 @@ async fn api_cart_update_item(
 
     let row = sqlx::query!(
         r#"
-        SELECT ci.id FROM cart_items ci
+        SELECT ci.id, ci.cart_id, ci.product_id, ci.variant_id, ci.qty, ci.auction_id,
+               ci.flash_sale_id, COALESCE(v.stock, p.stock) AS `stock!: i32`
+        FROM cart_items ci
         JOIN carts c ON ci.cart_id=c.id
+        JOIN products p ON ci.product_id=p.id
+        LEFT JOIN product_variants v ON ci.variant_id=v.id
         WHERE ci.id=? AND c.user_id=? AND c.status='open' LIMIT 1
         "#,
         item_id,
@@ -180,18 +209,60 @@ async fn api_cart_update_item(
     if row.is_none() {
         return (axum::http::StatusCode::FORBIDDEN, "no access").into_response();
     }
+    let row = row.unwrap();
 
+    // item hasil lelang: qty tetap 1 (boleh dihapus)
+    if row.auction_id.is_some() && req.qty > 1 {
+        return (axum::http::StatusCode::CONFLICT, "auction item qty is fixed").into_response();
+    }
+    // produk yang sama bisa ada di beberapa baris (harga normal / flash sale / lelang)
+    let others = sqlx::query!(
+        r#"
+        SELECT CAST(COALESCE(SUM(qty), 0) AS SIGNED) AS `n!: i64`
+        FROM cart_items WHERE cart_id=? AND product_id=? AND variant_id <=> ? AND id<>?
+        "#,
+        row.cart_id,
+        row.product_id,
+        row.variant_id,
+        item_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap()
+    .n as i32;
+    if others + req.qty > row.stock {
+        return out_of_stock(row.product_id, row.variant_id, others + req.qty, row.stock);
+    }
+
+    let mut tx = state.db.begin().await.unwrap();
+    // tambah qty baris flash sale = pesan kuota lagi (sale harus masih berjalan)
+    if let Some(sale_id) = row.flash_sale_id.filter(|_| req.qty > row.qty) {
+        match flash_sales::reserve_more(&mut tx, sale_id, req.qty - row.qty).await {
+            Ok(()) => {}
+            Err(QuotaError::Limit { remaining }) => return flash_sale_limit(remaining),
+            Err(QuotaError::Ended) => {
+                return (axum::http::StatusCode::CONFLICT, "flash sale ended").into_response();
+            }
+        }
+    }
     if req.qty <= 0 {
         let _ = sqlx::query!("DELETE FROM cart_items WHERE id=?", item_id)
-            .execute(&state.db)
+            .execute(&mut *tx)
             .await
             .unwrap();
     } else {
-        let _ = sqlx::query!("UPDATE cart_items SET qty=? WHERE id=?", req.qty, item_id)
-            .execute(&state.db)
-            .await
-            .unwrap();
+        // qty bertambah = reservasi sale diperbarui
+        let _ = sqlx::query!(
+            "UPDATE cart_items SET qty=?, reserved_at=IF(? > qty, NOW(), reserved_at) WHERE id=?",
+            req.qty,
+            req.qty,
+            item_id
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
     }
+    tx.commit().await.unwrap();
 
     Json(serde_json::json!({ "ok": true })).into_response()
 }
@@ -231,8 +302,362 @@ async fn api_cart_delete_item(
     Json(serde_json::json!({ "ok": true })).into_response()
 }
 
+// Pasang kode voucher ke cart open (menggantikan voucher sebelumnya).
+async fn api_cart_apply_voucher(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(req): Json<ApplyVoucherReq>,
+) -> Response {
+    let (_, user) = get_user_from_cookie(&jar, &state).await;
+    let Some(u) = user else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "login first").into_response();
+    };
+    let cart_id = ensure_viewer_cart(&state.db, u.id).await;
+
+    let mut conn = state.db.acquire().await.unwrap();
+    let Some(voucher_id) = vouchers::find_by_code(&mut conn, &req.code).await else {
+        return VoucherError::NotFound.into_response();
+    };
+
+    // syarat room dinilai dari cart_items.watch_room di evaluate
+    let lines: Vec<vouchers::Line> = sqlx::query!(
+        "SELECT product_id, qty, price_at_add, watch_room FROM cart_items WHERE cart_id=?",
+        cart_id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| vouchers::Line {
+        product_id: r.product_id,
+        line_total: r.qty as i64 * r.price_at_add as i64,
+        room: r.watch_room,
+    })
+    .collect();
+
+    let applied = match vouchers::evaluate(&mut conn, voucher_id, u.id, &lines, false).await {
+        Ok(a) => a,
+        Err(e) => return e.into_response(),
+    };
+
+    let _ = sqlx::query!("UPDATE carts SET voucher_id=? WHERE id=?", voucher_id, cart_id)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+
+    Json(applied).into_response()
+}
+
+async fn api_cart_remove_voucher(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Response {
+    let (_, user) = get_user_from_cookie(&jar, &state).await;
+    let Some(u) = user else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "login first").into_response();
+    };
+
+    let _ = sqlx::query!(
+        "UPDATE carts SET voucher_id=NULL WHERE user_id=? AND status='open'",
+        u.id
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+
+    Json(serde_json::json!({ "ok": true })).into_response()
+}
+
+/* ================ tambah item (dipakai juga oleh comment-to-buy) ================ */
+
+/// Alasan item gagal masuk keranjang.
+#[derive(Debug)]
+pub(crate) enum AddItemError {
+    BadQty,
+    ProductMissing,
+    VariantMissing,
+    VariantRequired,
+    OutOfStock { product_id: i64, variant_id: Option<i64>, requested: i32, available: i32 },
+    // kuota flash sale tinggal `remaining` (> 0)
+    FlashSaleLimit { remaining: i64 },
+}
+
+impl AddItemError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AddItemError::BadQty => "qty must be > 0",
+            AddItemError::ProductMissing => "product missing",
+            AddItemError::VariantMissing => "variant missing",
+            AddItemError::VariantRequired => "variant required",
+            AddItemError::OutOfStock { .. } => "out of stock",
+            AddItemError::FlashSaleLimit { .. } => "flash sale quota exceeded",
+        }
+    }
+}
+
+impl IntoResponse for AddItemError {
+    fn into_response(self) -> Response {
+        match self {
+            AddItemError::OutOfStock { product_id, variant_id, requested, available } => {
+                out_of_stock(product_id, variant_id, requested, available)
+            }
+            AddItemError::FlashSaleLimit { remaining } => flash_sale_limit(remaining),
+            e => (axum::http::StatusCode::BAD_REQUEST, e.as_str()).into_response(),
+        }
+    }
+}
+
+/// Tambah `qty` ke keranjang open milik user (dibuat bila belum ada); item yang sama
+/// dengan harga yang sama digabung. Stok dicek terhadap total qty di keranjang. Produk
+/// yang sedang di-pin di etalase `room` dicatat dengan room itu dan memakai harga live
+/// di baris tersendiri; `room` yang disebut selalu dicatat sebagai `watch_room` (syarat
+/// voucher room), walau produknya tidak di-pin. Flash sale yang berjalan memakai harga
+/// sale di baris tersendiri (kuota dipesan). Mengembalikan id keranjang.
+pub(crate) async fn add_item(
+    db: &Pool<MySql>,
+    user_id: i64,
+    product_id: i64,
+    variant_id: Option<i64>,
+    qty: i32,
+    room: Option<&str>,
+) -> Result<i64, AddItemError> {
+    if qty <= 0 {
+        return Err(AddItemError::BadQty);
+    }
+
+    let prod = sqlx::query!(
+        "SELECT id, price_idr, stock FROM products WHERE id=? AND is_active=1",
+        product_id
+    )
+    .fetch_optional(db)
+    .await
+    .unwrap();
+    let Some(prod) = prod else {
+        return Err(AddItemError::ProductMissing);
+    };
+
+    // harga & stok diambil dari varian bila ada
+    let (price, stock) = if let Some(variant_id) = variant_id {
+        let v = sqlx::query!(
+            "SELECT price_idr, stock FROM product_variants WHERE id=? AND product_id=? AND is_active=1",
+            variant_id,
+            prod.id
+        )
+        .fetch_optional(db)
+        .await
+        .unwrap();
+        let Some(v) = v else {
+            return Err(AddItemError::VariantMissing);
+        };
+        (v.price_idr.unwrap_or(prod.price_idr), v.stock)
+    } else {
+        let has_variants = sqlx::query!(
+            "SELECT id FROM product_variants WHERE product_id=? AND is_active=1 LIMIT 1",
+            prod.id
+        )
+        .fetch_optional(db)
+        .await
+        .unwrap()
+        .is_some();
+        if has_variants {
+            return Err(AddItemError::VariantRequired);
+        }
+        (prod.price_idr, prod.stock)
+    };
+
+    // harga live hanya bila request menyebut room tempat produk di-pin
+    let pin = match room {
+        Some(r) => showcase::active_for_product(db, prod.id, r).await,
+        None => None,
+    };
+    let mut price = pin.flatten().unwrap_or(price);
+    let pin_room = pin.and(room);
+
+    let cart_id = ensure_viewer_cart(db, user_id).await;
+    let mut tx = db.begin().await.unwrap();
+
+    // flash sale: kuota hanya dipesan bila harga sale lebih murah dari harga pin / normal,
+    // dikunci sampai item tersimpan
+    let sale = match flash_sales::reserve(&mut tx, prod.id, room, price, qty).await {
+        Ok(s) => s,
+        Err(QuotaError::Limit { remaining }) => return Err(AddItemError::FlashSaleLimit { remaining }),
+        Err(QuotaError::Ended) => None,
+    };
+    if let Some(s) = &sale {
+        price = s.sale_price;
+    }
+    let sale_id = sale.map(|s| s.id);
+
+    // atribusi ke stream yang sedang live di room tempat viewer menonton
+    let stream_id = match room {
+        Some(r) => streams::live_in_room(&mut tx, r).await,
+        None => None,
+    };
+
+    // cek stok terhadap total qty di cart (stok baru benar-benar dipotong saat checkout)
+    let in_cart = sqlx::query!(
+        r#"
+        SELECT CAST(COALESCE(SUM(qty), 0) AS SIGNED) AS `n!: i64`
+        FROM cart_items WHERE cart_id=? AND product_id=? AND variant_id <=> ?
+        "#,
+        cart_id,
+        prod.id,
+        variant_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap()
+    .n as i32;
+    if in_cart + qty > stock {
+        return Err(AddItemError::OutOfStock {
+            product_id: prod.id,
+            variant_id,
+            requested: in_cart + qty,
+            available: stock,
+        });
+    }
+
+    // item sale / lelang / harga live punya baris sendiri; hanya digabung dengan baris
+    // yang harga & room-nya (pin maupun tontonan) sama (qty lama tidak ikut berubah harga)
+    let existing = sqlx::query!(
+        r#"
+        SELECT id, qty FROM cart_items
+        WHERE cart_id=? AND product_id=? AND variant_id <=> ?
+          AND auction_id IS NULL AND flash_sale_id <=> ?
+          AND room <=> ? AND watch_room <=> ? AND price_at_add=?
+        LIMIT 1
+        "#,
+        cart_id,
+        prod.id,
+        variant_id,
+        sale_id,
+        pin_room,
+        room,
+        price
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+
+    if let Some(ci) = existing {
+        // qty bertambah = reservasi sale diperbarui (kuota baru saja dipesan untuk semua qty)
+        let _ = sqlx::query!(
+            "UPDATE cart_items SET qty=?, stream_id=COALESCE(?, stream_id), reserved_at=NOW() WHERE id=?",
+            ci.qty + qty,
+            stream_id,
+            ci.id
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    } else {
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO cart_items
+                (cart_id,product_id,variant_id,qty,price_at_add,room,watch_room,flash_sale_id,stream_id)
+            VALUES(?,?,?,?,?,?,?,?,?)
+            "#,
+            cart_id,
+            prod.id,
+            variant_id,
+            qty,
+            price,
+            pin_room,
+            room,
+            sale_id,
+            stream_id
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    }
+    tx.commit().await.unwrap();
+
+    Ok(cart_id)
+}
+
+/// Barang pemenang lelang: qty 1 dengan harga tawaran akhir, tidak digabung dengan
+/// item biasa. Dipanggil di dalam transaksi penutupan lelang; mengembalikan id keranjang.
+pub(crate) async fn add_auction_item(
+    tx: &mut sqlx::Transaction<'_, MySql>,
+    user_id: i64,
+    product_id: i64,
+    variant_id: Option<i64>,
+    price: i32,
+    room: &str,
+    auction_id: i64,
+) -> i64 {
+    let open = sqlx::query!(
+        "SELECT id FROM carts WHERE user_id=? AND status='open' LIMIT 1",
+        user_id
+    )
+    .fetch_optional(&mut **tx)
+    .await
+    .unwrap();
+    let cart_id = match open {
+        Some(r) => r.id,
+        None => sqlx::query!("INSERT INTO carts(user_id,status) VALUES(?, 'open')", user_id)
+            .execute(&mut **tx)
+            .await
+            .unwrap()
+            .last_insert_id() as i64,
+    };
+    let stream_id = streams::live_in_room(&mut **tx, room).await;
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO cart_items
+            (cart_id,product_id,variant_id,qty,price_at_add,room,watch_room,auction_id,stream_id)
+        VALUES(?,?,?,1,?,?,?,?,?)
+        "#,
+        cart_id,
+        product_id,
+        variant_id,
+        price,
+        room,
+        room,
+        auction_id,
+        stream_id
+    )
+    .execute(&mut **tx)
+    .await
+    .unwrap();
+    cart_id
+}
+
 /* ================ helpers lokal ================ */
 
+fn flash_sale_limit(remaining: i64) -> Response {
+    (
+        axum::http::StatusCode::CONFLICT,
+        Json(serde_json::json!({ "error": "flash sale quota exceeded", "remaining": remaining })),
+    )
+        .into_response()
+}
+
+fn out_of_stock(product_id: i64, variant_id: Option<i64>, requested: i32, available: i32) -> Response {
+    (
+        axum::http::StatusCode::CONFLICT,
+        Json(serde_json::json!({
+            "error": "out of stock",
+            "product_id": product_id,
+            "variant_id": variant_id,
+            "requested": requested,
+            "available": available.max(0),
+        })),
+    )
+        .into_response()
+}
+
+// "M / Merah" dari nilai opsi varian; None untuk item tanpa varian
+pub(crate) fn variant_label(options: &[Option<String>]) -> Option<String> {
+    let parts: Vec<&str> = options.iter().flatten().map(String::as_str).collect();
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" / "))
+    }
+}
+
 async fn ensure_viewer_cart(db: &Pool<MySql>, user_id: i64) -> i64 {
     if let Some(r) = sqlx::query!(
         "SELECT id FROM carts WHERE user_id=? AND status='open' LIMIT 1",
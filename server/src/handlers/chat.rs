@@ -0,0 +1,156 @@
+// server/src/handlers/chat.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Log chat per room (disimpan oleh handle_socket, lihat chat.rs) untuk admin.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+
+use crate::{chat::ChatOut, ensure_admin, AppState};
+
+#[derive(Deserialize)]
+struct ChatLogQuery {
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/rooms/:room/chat", get(api_admin_room_chat))
+        .route("/api/admin/rooms/:room/chat/export", get(api_admin_room_chat_export_xlsx))
+}
+
+/* ===================== Handlers ===================== */
+
+// Terbaru dulu; total data lewat header X-Total-Count.
+async fn api_admin_room_chat(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+    Query(q): Query<ChatLogQuery>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let limit = q.limit.unwrap_or(100).clamp(1, 500);
+    let page = q.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * limit;
+
+    let total = sqlx::query!("SELECT COUNT(*) AS n FROM chat_messages WHERE room=?", room)
+        .fetch_one(&state.db)
+        .await
+        .unwrap()
+        .n;
+
+    let rows = sqlx::query_as!(
+        ChatOut,
+        r#"
+        SELECT id, user_id, display_name AS user, text, created_at, deleted_at
+        FROM chat_messages WHERE room=?
+        ORDER BY id DESC
+        LIMIT ? OFFSET ?
+        "#,
+        room,
+        limit,
+        offset
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+    (headers, Json(rows)).into_response()
+}
+
+// EXPORT: seluruh chat room ke .xlsx (urut waktu)
+async fn api_admin_room_chat_export_xlsx(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+
+    let rows = sqlx::query_as!(
+        ChatOut,
+        r#"
+        SELECT id, user_id, display_name AS user, text, created_at, deleted_at
+        FROM chat_messages WHERE room=?
+        ORDER BY id
+        "#,
+        room
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let mut wb = rust_xlsxwriter::Workbook::new();
+    let ws = wb.add_worksheet();
+
+    ws.write(0, 0, "ID").ok();
+    ws.write(0, 1, "Waktu").ok();
+    ws.write(0, 2, "User ID").ok();
+    ws.write(0, 3, "Nama").ok();
+    ws.write(0, 4, "Pesan").ok();
+    ws.write(0, 5, "Dihapus").ok();
+
+    ws.set_column_width(0, 8).ok();
+    ws.set_column_width(1, 26).ok();
+    ws.set_column_width(2, 10).ok();
+    ws.set_column_width(3, 20).ok();
+    ws.set_column_width(4, 60).ok();
+    ws.set_column_width(5, 26).ok();
+
+    let mut r: u32 = 1;
+    for m in rows {
+        ws.write_number(r, 0, m.id as f64).ok();
+        ws.write(r, 1, m.created_at.to_rfc3339()).ok();
+        if let Some(uid) = m.user_id { ws.write_number(r, 2, uid as f64).ok(); }
+        ws.write(r, 3, &m.user).ok();
+        ws.write(r, 4, &m.text).ok();
+        if let Some(d) = m.deleted_at { ws.write(r, 5, d.to_rfc3339()).ok(); }
+        r += 1;
+    }
+
+    let buf = match wb.save_to_buffer() {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("export chat XLSX error: {e:?}");
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "export error").into_response();
+        }
+    };
+
+    // nama room dipakai di nama file; buang karakter yang tidak aman untuk header
+    let safe: String = room
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        axum::http::header::CONTENT_TYPE,
+        HeaderValue::from_static("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    );
+    headers.insert(
+        axum::http::header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&format!("attachment; filename=\"chat-{safe}.xlsx\"")).unwrap(),
+    );
+    (headers, buf).into_response()
+}
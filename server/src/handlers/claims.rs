@@ -0,0 +1,97 @@
+// server/src/handlers/claims.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Feed klaim comment-to-buy per room (versi REST dari event WS `claim`, untuk
+// halaman host yang baru dibuka / di-refresh).
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+
+use crate::{claims::ClaimOut, ensure_admin, AppState};
+
+#[derive(Deserialize)]
+struct ClaimsQuery {
+    // "added" | "failed"
+    status: Option<String>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/api/admin/rooms/:room/claims", get(api_admin_room_claims))
+}
+
+/* ===================== Handlers ===================== */
+
+// Terbaru dulu; total data lewat header X-Total-Count.
+async fn api_admin_room_claims(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+    Query(q): Query<ClaimsQuery>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let status = match q.status.as_deref().filter(|s| !s.is_empty()) {
+        None => None,
+        Some(s @ ("added" | "failed")) => Some(s),
+        Some(_) => return (axum::http::StatusCode::BAD_REQUEST, "invalid status").into_response(),
+    };
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    let page = q.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * limit;
+
+    let total = sqlx::query!(
+        "SELECT COUNT(*) AS n FROM chat_claims WHERE room=? AND (? IS NULL OR status = ?)",
+        room,
+        status,
+        status
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap()
+    .n;
+
+    let rows = sqlx::query_as!(
+        ClaimOut,
+        r#"
+        SELECT c.id, c.chat_id, c.user_id, u.name AS user, c.code, c.qty,
+               c.product_id, c.variant_id, p.name AS `product_name?`,
+               c.status, c.error, c.created_at
+        FROM chat_claims c
+        JOIN users u ON u.id = c.user_id
+        LEFT JOIN products p ON p.id = c.product_id
+        WHERE c.room=? AND (? IS NULL OR c.status = ?)
+        ORDER BY c.id DESC
+        LIMIT ? OFFSET ?
+        "#,
+        room,
+        status,
+        status,
+        limit,
+        offset
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+    (headers, Json(rows)).into_response()
+}
@@ -0,0 +1,230 @@
+// server/src/handlers/flash_sales.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// CRUD admin jadwal flash sale (penilaian harga & event ada di flash_sales.rs).
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{ensure_admin, flash_sales, AppState};
+
+#[derive(Deserialize)]
+struct FlashSaleIn {
+    product_id: i64,
+    sale_price: i32,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    // total qty yang boleh terjual dengan harga sale (None = tanpa batas)
+    qty_cap: Option<i32>,
+    // hanya untuk viewer room live tertentu
+    room: Option<String>,
+    is_active: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct FlashSaleOut {
+    id: i64,
+    product_id: i64,
+    product_name: String,
+    price_idr: i32,
+    sale_price: i32,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    qty_cap: Option<i32>,
+    room: Option<String>,
+    is_active: bool,
+    // qty di keranjang open + order yang tidak batal
+    used: i64,
+    created_at: DateTime<Utc>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/flash-sales", get(api_admin_flash_sales_list))
+        .route("/api/admin/flash-sales", post(api_admin_flash_sale_create))
+        .route("/api/admin/flash-sales/:id", put(api_admin_flash_sale_update))
+        .route("/api/admin/flash-sales/:id", delete(api_admin_flash_sale_delete))
+}
+
+/* ===================== Handlers ===================== */
+
+async fn api_admin_flash_sales_list(State(state): State<AppState>, jar: CookieJar) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT f.id, f.product_id, p.name AS product_name, p.price_idr, f.sale_price,
+               f.starts_at, f.ends_at, f.qty_cap, f.room, f.is_active, f.created_at,
+               CAST((SELECT COALESCE(SUM(ci.qty), 0)
+                     FROM cart_items ci
+                     JOIN carts c ON c.id = ci.cart_id
+                     LEFT JOIN orders o ON o.cart_id = c.id
+                     WHERE ci.flash_sale_id = f.id
+                       AND (c.status = 'open' OR o.status NOT IN ('cancelled','deleted'))) AS SIGNED)
+               AS `used!: i64`
+        FROM flash_sales f JOIN products p ON p.id = f.product_id
+        ORDER BY f.starts_at DESC, f.id DESC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let out: Vec<FlashSaleOut> = rows
+        .into_iter()
+        .map(|r| FlashSaleOut {
+            id: r.id,
+            product_id: r.product_id,
+            product_name: r.product_name,
+            price_idr: r.price_idr,
+            sale_price: r.sale_price,
+            starts_at: r.starts_at,
+            ends_at: r.ends_at,
+            qty_cap: r.qty_cap,
+            room: r.room,
+            is_active: r.is_active != 0,
+            used: r.used,
+            created_at: r.created_at,
+        })
+        .collect();
+
+    Json(out).into_response()
+}
+
+async fn api_admin_flash_sale_create(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(inp): Json<FlashSaleIn>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    if let Some(resp) = validate_flash_sale(&state, &inp).await {
+        return resp;
+    }
+
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO flash_sales(product_id, sale_price, starts_at, ends_at, qty_cap, room, is_active)
+        VALUES(?,?,?,?,?,?,?)
+        "#,
+        inp.product_id,
+        inp.sale_price,
+        inp.starts_at,
+        inp.ends_at,
+        inp.qty_cap,
+        inp.room.as_deref().map(str::trim).filter(|r| !r.is_empty()),
+        if inp.is_active.unwrap_or(true) { 1 } else { 0 }
+    )
+    .execute(&state.db)
+    .await
+    .unwrap()
+    .last_insert_id() as i64;
+
+    Json(json!({"ok": true, "id": id})).into_response()
+}
+
+// Jadwal diubah: bila masih ada sisa waktu, event mulai dikirim ulang (dengan ends_at baru).
+async fn api_admin_flash_sale_update(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Json(inp): Json<FlashSaleIn>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    if let Some(resp) = validate_flash_sale(&state, &inp).await {
+        return resp;
+    }
+    let is_active = inp.is_active.unwrap_or(true);
+    if !is_active {
+        flash_sales::announce_ended(&state, id).await;
+    }
+
+    let res = sqlx::query!(
+        r#"
+        UPDATE flash_sales
+        SET product_id=?, sale_price=?, starts_at=?, ends_at=?, qty_cap=?, room=?, is_active=?,
+            notified = IF(? AND ? > NOW(), 'none', notified)
+        WHERE id=?
+        "#,
+        inp.product_id,
+        inp.sale_price,
+        inp.starts_at,
+        inp.ends_at,
+        inp.qty_cap,
+        inp.room.as_deref().map(str::trim).filter(|r| !r.is_empty()),
+        if is_active { 1 } else { 0 },
+        is_active,
+        inp.ends_at,
+        id
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    if res.rows_affected() == 0 {
+        return (axum::http::StatusCode::NOT_FOUND, "flash sale not found").into_response();
+    }
+    Json(json!({"ok": true})).into_response()
+}
+
+// Soft delete: baris keranjang / order lama tetap menunjuk sale-nya.
+async fn api_admin_flash_sale_delete(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    flash_sales::announce_ended(&state, id).await;
+    let _ = sqlx::query!("UPDATE flash_sales SET is_active=0 WHERE id=?", id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    Json(json!({"ok": true})).into_response()
+}
+
+/* ===================== helpers lokal ===================== */
+
+async fn validate_flash_sale(state: &AppState, inp: &FlashSaleIn) -> Option<Response> {
+    let bad = |msg: &'static str| Some((axum::http::StatusCode::BAD_REQUEST, msg).into_response());
+
+    if inp.sale_price <= 0 {
+        return bad("sale_price must be > 0");
+    }
+    if inp.starts_at >= inp.ends_at {
+        return bad("starts_at must be before ends_at");
+    }
+    if inp.qty_cap.is_some_and(|c| c <= 0) {
+        return bad("qty_cap must be > 0");
+    }
+    let prod = sqlx::query!("SELECT price_idr FROM products WHERE id=?", inp.product_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap();
+    match prod {
+        None => bad("product not found"),
+        Some(p) if inp.sale_price >= p.price_idr => bad("sale_price must be below price_idr"),
+        Some(_) => None,
+    }
+}
@@ -15,3 +15,16 @@ pub mod users;
 pub mod admin;
 pub mod products;
 pub mod cart;      // <— tambahkan baris ini
+pub mod payments;
+pub mod reconcile;
+pub mod shipping;
+pub mod vouchers;
+pub mod rooms;
+pub mod chat;
+pub mod moderation;
+pub mod claims;
+pub mod showcase;
+pub mod auctions;
+pub mod flash_sales;
+pub mod streams;
+pub mod analytics;
@@ -0,0 +1,220 @@
+// server/src/handlers/moderation.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// API admin untuk moderasi room (aturan & aksi sama dengan perintah WS `{"t":"mod"}`,
+// lihat moderation.rs). Admin bertindak sebagai host.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    ensure_admin,
+    moderation::{self, SanctionKind, Settings},
+    room_roles::RoomRole,
+    AppState,
+};
+
+#[derive(Deserialize)]
+struct SanctionIn {
+    user_id: i64,
+    // "mute" | "ban"
+    kind: SanctionKind,
+    seconds: u32,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LogQuery {
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct SanctionOut {
+    id: i64,
+    user_id: i64,
+    name: String,
+    kind: String,
+    expires_at: DateTime<Utc>,
+    reason: Option<String>,
+    created_by: Option<i64>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct LogOut {
+    id: i64,
+    actor_id: Option<i64>,
+    action: String,
+    target_user_id: Option<i64>,
+    chat_id: Option<i64>,
+    detail: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/rooms/:room/moderation", get(api_admin_moderation_get))
+        .route("/api/admin/rooms/:room/moderation", put(api_admin_moderation_put))
+        .route("/api/admin/rooms/:room/moderation/log", get(api_admin_moderation_log))
+        .route("/api/admin/rooms/:room/sanctions", post(api_admin_sanction_create))
+        .route("/api/admin/rooms/:room/sanctions/:user_id/:kind", delete(api_admin_sanction_lift))
+        .route("/api/admin/rooms/:room/chat/:chat_id", delete(api_admin_chat_delete))
+}
+
+/* ===================== Handlers ===================== */
+
+// Pengaturan filter + sanksi yang masih aktif.
+async fn api_admin_moderation_get(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let settings = moderation::load_settings(&state.db, &room).await;
+    let sanctions = sqlx::query_as!(
+        SanctionOut,
+        r#"
+        SELECT s.id, s.user_id, u.name, s.kind, s.expires_at AS `expires_at!: DateTime<Utc>`,
+               s.reason, s.created_by, s.created_at
+        FROM room_sanctions s JOIN users u ON u.id = s.user_id
+        WHERE s.room=? AND s.lifted_at IS NULL AND s.expires_at > NOW()
+        ORDER BY s.id DESC
+        "#,
+        room
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+    Json(json!({ "settings": settings, "sanctions": sanctions })).into_response()
+}
+
+async fn api_admin_moderation_put(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+    Json(inp): Json<Settings>,
+) -> Response {
+    let Some(admin) = ensure_admin(&jar, &state).await else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    };
+    match moderation::save_settings(&state, &room, admin.id, &inp).await {
+        Ok(()) => Json(json!({"ok": true})).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+// Terbaru dulu; total data lewat header X-Total-Count.
+async fn api_admin_moderation_log(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+    Query(q): Query<LogQuery>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    let page = q.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * limit;
+
+    let total = sqlx::query!("SELECT COUNT(*) AS n FROM moderation_log WHERE room=?", room)
+        .fetch_one(&state.db)
+        .await
+        .unwrap()
+        .n;
+    let rows = sqlx::query_as!(
+        LogOut,
+        r#"
+        SELECT id, actor_id, action, target_user_id, chat_id, detail, created_at
+        FROM moderation_log WHERE room=?
+        ORDER BY id DESC
+        LIMIT ? OFFSET ?
+        "#,
+        room,
+        limit,
+        offset
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+    (headers, Json(rows)).into_response()
+}
+
+async fn api_admin_sanction_create(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+    Json(inp): Json<SanctionIn>,
+) -> Response {
+    let Some(admin) = ensure_admin(&jar, &state).await else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    };
+    let reason = inp.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    match moderation::sanction(
+        &state,
+        &room,
+        admin.id,
+        RoomRole::Host,
+        inp.user_id,
+        inp.kind,
+        inp.seconds,
+        reason,
+    )
+    .await
+    {
+        Ok(until) => Json(json!({"ok": true, "expires_at": until})).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn api_admin_sanction_lift(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path((room, user_id, kind)): Path<(String, i64, SanctionKind)>,
+) -> Response {
+    let Some(admin) = ensure_admin(&jar, &state).await else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    };
+    match moderation::lift(&state, &room, admin.id, user_id, kind).await {
+        Ok(()) => Json(json!({"ok": true})).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+// Hapus pesan + siarkan retraksi ke room.
+async fn api_admin_chat_delete(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path((room, chat_id)): Path<(String, i64)>,
+) -> Response {
+    let Some(admin) = ensure_admin(&jar, &state).await else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    };
+    match moderation::delete_message(&state, &room, admin.id, chat_id).await {
+        Ok(()) => Json(json!({"ok": true})).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -11,20 +11,34 @@ LinkedIn: https://id.linkedin.com/in/kukuhtw
 */
 
 
+use std::collections::HashMap;
+
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, HeaderValue},
     response::{IntoResponse, Response},
     routing::{delete, get, patch, post},
     Json, Router,
 };
 use axum_extra::extract::cookie::CookieJar;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use rand::{seq::SliceRandom, Rng};
 use rust_xlsxwriter::Workbook;
 use serde::{Deserialize, Serialize};
-use sqlx::{MySql, Pool};
-
-use crate::{ensure_admin, get_user_from_cookie, AppState};
+use sqlx::{MySql, Pool, QueryBuilder};
+
+use crate::{
+    ensure_admin, events,
+    flash_sales::{self, QuotaError},
+    get_user_from_cookie,
+    handlers::cart::variant_label,
+    is_unique_violation,
+    order_status::{OrderStatus, StatusChangeError},
+    shipping::{self, Destination},
+    vouchers,
+    ws_protocol::Body,
+    AppState,
+};
 
 /* ===================== Input/Output types ===================== */
 
@@ -33,37 +47,100 @@ pub struct CheckoutReq {
     pub shipping_name: String,
     pub shipping_phone: String,
     pub shipping_address: String,
+    // dipakai untuk memilih zona tarif ongkir (lihat shipping.rs)
+    pub shipping_province: Option<String>,
+    pub shipping_city: Option<String>,
+    pub shipping_postcode: Option<String>,
     pub note: Option<String>,
-    // tetap ada di request, tapi AKAN DIABAIKAN: viewer tidak boleh set ongkir
-    pub delivery_fee: Option<i32>,
 }
 
 #[derive(Deserialize)]
 pub struct AdminOrderPatch {
     pub delivery_fee: Option<i32>,
     pub total: Option<i32>,
+    // None = status tidak diubah
+    pub status: Option<String>,
+    // catatan untuk riwayat status
+    pub note: Option<String>,
+}
+
+// Query viewer: GET /api/orders?status=&page=&limit=
+#[derive(Deserialize)]
+pub struct MyOrdersQuery {
     pub status: Option<String>,
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+// Query admin: GET /api/admin/orders
+//   status, from/to (YYYY-MM-DD, inklusif), q (nama/no. HP), min_total/max_total,
+//   sort (id|created_at|total|status|customer) + dir (asc|desc), page/limit
+#[derive(Deserialize)]
+pub struct AdminOrdersQuery {
+    pub status: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub q: Option<String>,
+    pub min_total: Option<i32>,
+    pub max_total: Option<i32>,
+    pub sort: Option<String>,
+    pub dir: Option<String>,
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
 }
 
 #[derive(Serialize, Debug, Clone)]
 struct ItemOut {
     product_name: String,
+    variant_id: Option<i64>,
+    sku: Option<String>,
+    variant_label: Option<String>,
     qty: i32,
     price_at_add: i32,
     line_total: i32,
 }
 
+#[derive(sqlx::FromRow)]
+struct AdminOrderRow {
+    id: i64,
+    user_id: i64,
+    cart_id: i64,
+    subtotal: i32,
+    // potongan voucher
+    discount: i32,
+    delivery_fee: i32,
+    // kode unik transfer yang sudah termasuk di total (0 = tidak dipakai)
+    unique_code: i32,
+    total: i32,
+    shipping_name: String,
+    shipping_phone: String,
+    shipping_address: String,
+    shipping_province: Option<String>,
+    shipping_city: Option<String>,
+    shipping_postcode: Option<String>,
+    weight_grams: i32,
+    note: Option<String>,
+    status: String,
+    created_at: DateTime<Utc>,
+}
+
 #[derive(Serialize)]
 struct AdminOrderOut {
     id: i64,
     user_id: i64,
     cart_id: i64,
     subtotal: i32,
+    discount: i32,
     delivery_fee: i32,
+    unique_code: i32,
     total: i32,
     shipping_name: String,
     shipping_phone: String,
     shipping_address: String,
+    shipping_province: Option<String>,
+    shipping_city: Option<String>,
+    shipping_postcode: Option<String>,
+    weight_grams: i32,
     note: Option<String>,
     status: String,
     created_at: DateTime<Utc>,
@@ -76,15 +153,33 @@ struct OrderDetailOut {
     id: i64,
     cart_id: i64,
     subtotal: i32,
+    discount: i32,
     delivery_fee: i32,
+    unique_code: i32,
     total: i32,
     shipping_name: String,
     shipping_phone: String,
     shipping_address: String,
+    shipping_province: Option<String>,
+    shipping_city: Option<String>,
+    shipping_postcode: Option<String>,
+    weight_grams: i32,
     note: Option<String>,
     status: String,
     created_at: DateTime<Utc>,
     items: Vec<ItemOut>,
+    timeline: Vec<StatusEventOut>,
+}
+
+// Satu baris riwayat perubahan status order
+#[derive(Serialize)]
+struct StatusEventOut {
+    from: Option<String>,
+    to: String,
+    by_user_id: Option<i64>,
+    by_name: Option<String>,
+    note: Option<String>,
+    at: DateTime<Utc>,
 }
 
 /* ===================== Routes ===================== */
@@ -93,6 +188,7 @@ pub fn routes() -> Router<AppState> {
     Router::new()
         // Viewer
         .route("/api/orders", post(api_order_checkout))
+        .route("/api/orders", get(api_my_orders))
         .route("/api/orders/:id", get(api_order_detail))
         .route("/api/orders/:id", delete(api_order_delete)) // NEW: delete (soft)
         .route("/api/orders/:id/export", get(api_order_export_xlsx)) // NEW: export xlsx
@@ -108,6 +204,7 @@ pub fn routes() -> Router<AppState> {
 pub async fn api_order_checkout(
     State(state): State<AppState>,
     jar: CookieJar,
+    headers: HeaderMap,
     Json(req): Json<CheckoutReq>,
 ) -> Response {
     // pastikan ada user dari cookie
@@ -117,62 +214,263 @@ pub async fn api_order_checkout(
     }
     let u = user.unwrap();
 
-    // ambil/atau buat cart open
-    let cart_id = ensure_viewer_cart(&state.db, u.id).await;
+    // Idempotency-Key opsional: retry dengan key yang sama mengembalikan order awal
+    let idem_key = match headers.get("idempotency-key").map(|v| v.to_str()) {
+        None => None,
+        Some(Ok(k)) if !k.trim().is_empty() && k.len() <= 64 => Some(k.trim().to_string()),
+        Some(_) => {
+            return (axum::http::StatusCode::BAD_REQUEST, "invalid Idempotency-Key").into_response()
+        }
+    };
+
+    // Semua langkah checkout dalam satu transaksi; return lebih awal = rollback.
+    let mut tx = state.db.begin().await.unwrap();
+
+    // kunci cart open milik user: checkout paralel (double click / 2 tab) antre di sini
+    let cart = sqlx::query!(
+        "SELECT id, voucher_id FROM carts WHERE user_id=? AND status='open' LIMIT 1 FOR UPDATE",
+        u.id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
 
-    // hitung subtotal dari item pada cart tsb
-    let rows = sqlx::query!("SELECT qty, price_at_add FROM cart_items WHERE cart_id=?", cart_id)
-        .fetch_all(&state.db)
+    // dibaca setelah lock cart agar request yang menunggu melihat order yang baru di-commit
+    if let Some(key) = &idem_key {
+        if let Some(prev) = sqlx::query!(
+            "SELECT id, total FROM orders WHERE user_id=? AND idempotency_key=? LIMIT 1 LOCK IN SHARE MODE",
+            u.id,
+            key
+        )
+        .fetch_optional(&mut *tx)
         .await
-        .unwrap();
+        .unwrap()
+        {
+            return checkout_replay(prev.id, prev.total as i64);
+        }
+    }
+
+    let Some(cart) = cart else {
+        return (axum::http::StatusCode::BAD_REQUEST, "cart empty").into_response();
+    };
+    let cart_id = cart.id;
+    let voucher_id = cart.voucher_id;
+
+    // ambil item + stok produk, kunci baris produk sampai commit
+    let rows = sqlx::query!(
+        r#"
+        SELECT ci.product_id, ci.variant_id, ci.qty, ci.price_at_add, ci.stream_id, ci.watch_room,
+               ci.flash_sale_id, p.name,
+               p.weight_grams, v.sku, COALESCE(v.stock, p.stock) AS `stock!: i32`
+        FROM cart_items ci
+        JOIN products p ON p.id = ci.product_id
+        LEFT JOIN product_variants v ON v.id = ci.variant_id
+        WHERE ci.cart_id=?
+        FOR UPDATE
+        "#,
+        cart_id
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .unwrap();
 
     if rows.is_empty() {
         return (axum::http::StatusCode::BAD_REQUEST, "cart empty").into_response();
     }
 
+    // cek stok per item; tolak seluruh checkout jika ada yang kurang
+    let short: Vec<serde_json::Value> = rows
+        .iter()
+        .filter(|r| r.qty > r.stock)
+        .map(|r| {
+            serde_json::json!({
+                "product_id": r.product_id,
+                "variant_id": r.variant_id,
+                "sku": r.sku,
+                "name": r.name,
+                "requested": r.qty,
+                "available": r.stock.max(0),
+            })
+        })
+        .collect();
+    if !short.is_empty() {
+        return (
+            axum::http::StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": "out of stock", "items": short })),
+        )
+            .into_response();
+    }
+
     let mut subtotal: i64 = 0;
-    for r in rows {
+    let mut weight: i64 = 0;
+    for r in &rows {
         subtotal += (r.qty as i64) * (r.price_at_add as i64);
+        weight += (r.qty as i64) * (r.weight_grams as i64);
+
+        // potong stok secara atomik (stok varian jika item punya varian)
+        let res = if let Some(variant_id) = r.variant_id {
+            sqlx::query!(
+                "UPDATE product_variants SET stock = stock - ? WHERE id=? AND stock >= ?",
+                r.qty,
+                variant_id,
+                r.qty
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap()
+        } else {
+            sqlx::query!(
+                "UPDATE products SET stock = stock - ? WHERE id=? AND stock >= ?",
+                r.qty,
+                r.product_id,
+                r.qty
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap()
+        };
+        if res.rows_affected() == 0 {
+            return (
+                axum::http::StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": "out of stock",
+                    "items": [{
+                        "product_id": r.product_id,
+                        "variant_id": r.variant_id,
+                        "name": r.name,
+                        "requested": r.qty
+                    }]
+                })),
+            )
+                .into_response();
+        }
     }
 
-    // Viewer TIDAK BOLEH mengatur ongkir → set 0 saat order dibuat.
-    let delivery: i64 = 0;
-    let total = subtotal + delivery;
+    // kuota flash sale dinilai ulang (reservasi keranjang bisa sudah lewat)
+    let mut by_sale: HashMap<i64, i64> = HashMap::new();
+    for r in &rows {
+        if let Some(sid) = r.flash_sale_id {
+            *by_sale.entry(sid).or_default() += r.qty as i64;
+        }
+    }
+    let mut sales: Vec<(i64, i64)> = by_sale.into_iter().collect();
+    sales.sort_unstable();
+    for (sale_id, qty) in sales {
+        let res = flash_sales::confirm(&mut tx, sale_id, cart_id, qty).await;
+        if let Err(QuotaError::Limit { remaining }) = res {
+            return (
+                axum::http::StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": "flash sale quota exceeded",
+                    "flash_sale_id": sale_id,
+                    "remaining": remaining,
+                })),
+            )
+                .into_response();
+        }
+    }
+
+    // voucher divalidasi ulang dengan baris voucher terkunci (kuota pemakaian)
+    let (discount, voucher_id) = match voucher_id {
+        None => (0, None),
+        Some(vid) => {
+            let lines: Vec<vouchers::Line> = rows
+                .iter()
+                .map(|r| vouchers::Line {
+                    product_id: r.product_id,
+                    line_total: r.qty as i64 * r.price_at_add as i64,
+                    room: r.watch_room.clone(),
+                })
+                .collect();
+            match vouchers::evaluate(&mut tx, vid, u.id, &lines, true).await {
+                Ok(a) => (a.discount, Some(a.voucher_id)),
+                Err(e) => return e.into_response(),
+            }
+        }
+    };
+
+    // Ongkir dihitung server dari tabel tarif (viewer tidak bisa set ongkir)
+    let dest = Destination {
+        province: req.shipping_province.clone(),
+        city: req.shipping_city.clone(),
+        postcode: req.shipping_postcode.clone(),
+    };
+    let delivery = match shipping::quote(&mut tx, &dest, weight, subtotal - discount).await {
+        Ok(q) => q.fee,
+        Err(e) => return e.into_response(),
+    };
+
+    // mode kode unik: total dibuat unik di antara order 'new' agar transfer bisa dicocokkan otomatis
+    let unique_code: i64 = if state.unique_payment_code {
+        pick_unique_code(&mut tx, subtotal - discount + delivery).await
+    } else {
+        0
+    };
+    let total = subtotal - discount + delivery + unique_code;
+
+    // order diatribusikan ke stream dengan nilai item terbesar di keranjang
+    let mut by_stream: HashMap<i64, i64> = HashMap::new();
+    for r in &rows {
+        if let Some(sid) = r.stream_id {
+            *by_stream.entry(sid).or_default() += r.qty as i64 * r.price_at_add as i64;
+        }
+    }
+    let stream_id = by_stream.into_iter().max_by_key(|&(sid, v)| (v, sid)).map(|(sid, _)| sid);
 
     // lock cart → ordered, lalu buat order
     let _ = sqlx::query!("UPDATE carts SET status='ordered' WHERE id=?", cart_id)
-        .execute(&state.db)
+        .execute(&mut *tx)
         .await
         .unwrap();
 
     let res = sqlx::query!(
         r#"
         INSERT INTO orders(
-            user_id, cart_id, subtotal, delivery_fee, total,
-            shipping_name, shipping_phone, shipping_address, note, status
+            user_id, cart_id, subtotal, discount, voucher_id, delivery_fee, unique_code, total,
+            shipping_name, shipping_phone, shipping_address,
+            shipping_province, shipping_city, shipping_postcode, weight_grams,
+            note, status, idempotency_key, stream_id
         )
-        VALUES(?,?,?,?,?,?,?,?,?,'new')
+        VALUES(?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,'new',?,?)
         "#,
         u.id,
         cart_id,
         subtotal as i32,
+        discount as i32,
+        voucher_id,
         delivery as i32,
+        unique_code as i32,
         total as i32,
         req.shipping_name,
         req.shipping_phone,
         req.shipping_address,
-        req.note
+        req.shipping_province,
+        req.shipping_city,
+        req.shipping_postcode,
+        weight as i32,
+        req.note,
+        idem_key,
+        stream_id
     )
-    .execute(&state.db)
-    .await
-    .unwrap();
+    .execute(&mut *tx)
+    .await;
+
+    // UNIQUE(cart_id) / UNIQUE(user_id, idempotency_key): cart sudah jadi order
+    let res = match res {
+        Ok(r) => r,
+        Err(e) if is_unique_violation(&e) => {
+            return (axum::http::StatusCode::CONFLICT, "cart already ordered").into_response()
+        }
+        Err(e) => panic!("insert order: {e:?}"),
+    };
 
     let order_id = res.last_insert_id() as i64;
+    record_status(&mut tx, order_id, None, OrderStatus::New, Some(u.id), None).await;
+
+    tx.commit().await.unwrap();
 
     // Broadcast event order baru (dipakai admin/viewer untuk auto-refresh)
-    let _ = state
-        .notify_tx
-        .send(serde_json::json!({ "t": "order", "order_id": order_id }).to_string());
+    events::order(&state, order_id, Body::Order { order_id }).await;
 
     Json(serde_json::json!({ "ok": true, "order_id": order_id, "total": total }))
         .into_response()
@@ -190,8 +488,10 @@ pub async fn api_order_detail(
     // ambil order
     let row = sqlx::query!(
         r#"
-        SELECT id, user_id, cart_id, subtotal, delivery_fee, total,
-               shipping_name, shipping_phone, shipping_address, note, status, created_at
+        SELECT id, user_id, cart_id, subtotal, discount, delivery_fee, unique_code, total,
+               shipping_name, shipping_phone, shipping_address,
+               shipping_province, shipping_city, shipping_postcode, weight_grams,
+               note, status, created_at
         FROM orders WHERE id=? LIMIT 1
         "#,
         order_id
@@ -213,119 +513,220 @@ pub async fn api_order_detail(
     }
 
     // ambil items
-    let item_rows = sqlx::query!(
-        r#"
-        SELECT p.name AS product_name, ci.qty, ci.price_at_add
-        FROM cart_items ci
-        JOIN products p ON p.id = ci.product_id
-        WHERE ci.cart_id = ?
-        ORDER BY ci.id ASC
-        "#,
-        row.cart_id
-    )
-    .fetch_all(&state.db)
-    .await
-    .unwrap();
+    let items = load_items(&state.db, row.cart_id).await;
 
-    let items: Vec<ItemOut> = item_rows
-        .into_iter()
-        .map(|it| {
-            let qty = it.qty;
-            let price = it.price_at_add;
-            ItemOut {
-                product_name: it.product_name, // kolom NOT NULL
-                qty,
-                price_at_add: price,
-                line_total: qty * price,
-            }
-        })
-        .collect();
+    // riwayat status (lama → baru)
+    let timeline = load_timelines(&state.db, &[row.id])
+        .await
+        .remove(&row.id)
+        .unwrap_or_default();
 
     let out = OrderDetailOut {
         id: row.id,
         cart_id: row.cart_id,
         subtotal: row.subtotal,
+        discount: row.discount,
         delivery_fee: row.delivery_fee,
+        unique_code: row.unique_code,
         total: row.total,
         shipping_name: row.shipping_name,
         shipping_phone: row.shipping_phone,
         shipping_address: row.shipping_address,
+        shipping_province: row.shipping_province,
+        shipping_city: row.shipping_city,
+        shipping_postcode: row.shipping_postcode,
+        weight_grams: row.weight_grams,
         note: row.note,
         status: row.status,
         created_at: row.created_at,
         items,
+        timeline,
     };
 
     Json(out).into_response()
 }
 
-// Admin: list seluruh order + items
-pub async fn api_admin_orders_list(
+// Viewer: daftar order milik user sendiri (paginasi, filter status), tanpa yang dihapus.
+// Total data dikirim lewat header X-Total-Count.
+pub async fn api_my_orders(
     State(state): State<AppState>,
     jar: CookieJar,
+    Query(q): Query<MyOrdersQuery>,
 ) -> Response {
-    if ensure_admin(&jar, &state).await.is_none() {
-        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
-    }
+    let (_, user) = get_user_from_cookie(&jar, &state).await;
+    let Some(u) = user else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "login first").into_response();
+    };
+
+    let status = match q.status.as_deref().filter(|s| !s.is_empty()) {
+        None => None,
+        Some(s) => match OrderStatus::parse(s) {
+            Some(st) if st != OrderStatus::Deleted => Some(st.as_str()),
+            _ => return (axum::http::StatusCode::BAD_REQUEST, "invalid status").into_response(),
+        },
+    };
+    let limit = q.limit.unwrap_or(20).clamp(1, 100);
+    let page = q.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * limit;
+
+    let total = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS n FROM orders
+        WHERE user_id=? AND status <> 'deleted' AND (? IS NULL OR status = ?)
+        "#,
+        u.id,
+        status,
+        status
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap()
+    .n;
 
     let rows = sqlx::query!(
         r#"
-        SELECT id, user_id, cart_id, subtotal, delivery_fee, total,
-               shipping_name, shipping_phone, shipping_address, note, status, created_at
+        SELECT id, cart_id, subtotal, discount, delivery_fee, unique_code, total,
+               shipping_name, shipping_phone, shipping_address,
+               shipping_province, shipping_city, shipping_postcode, weight_grams,
+               note, status, created_at
         FROM orders
-         WHERE status <> 'deleted'
+        WHERE user_id=? AND status <> 'deleted' AND (? IS NULL OR status = ?)
         ORDER BY id DESC
-        "#
+        LIMIT ? OFFSET ?
+        "#,
+        u.id,
+        status,
+        status,
+        limit,
+        offset
     )
     .fetch_all(&state.db)
     .await
     .unwrap();
 
-    let mut out: Vec<AdminOrderOut> = Vec::with_capacity(rows.len());
-
-    for r in rows {
-        let item_rows = sqlx::query!(
-            r#"
-            SELECT p.name AS product_name, ci.qty, ci.price_at_add
-            FROM cart_items ci
-            JOIN products p ON p.id = ci.product_id
-            WHERE ci.cart_id = ?
-            ORDER BY ci.id ASC
-            "#,
-            r.cart_id
-        )
-        .fetch_all(&state.db)
-        .await
-        .unwrap();
+    let cart_ids: Vec<i64> = rows.iter().map(|r| r.cart_id).collect();
+    let order_ids: Vec<i64> = rows.iter().map(|r| r.id).collect();
+    let mut items = load_items_many(&state.db, &cart_ids).await;
+    let mut timelines = load_timelines(&state.db, &order_ids).await;
 
-        let items: Vec<ItemOut> = item_rows
-            .into_iter()
-            .map(|it| ItemOut {
-                product_name: it.product_name,
-                qty: it.qty,
-                price_at_add: it.price_at_add,
-                line_total: it.qty * it.price_at_add,
-            })
-            .collect();
+    let out: Vec<OrderDetailOut> = rows
+        .into_iter()
+        .map(|r| OrderDetailOut {
+            id: r.id,
+            cart_id: r.cart_id,
+            subtotal: r.subtotal,
+            discount: r.discount,
+            delivery_fee: r.delivery_fee,
+            unique_code: r.unique_code,
+            total: r.total,
+            shipping_name: r.shipping_name,
+            shipping_phone: r.shipping_phone,
+            shipping_address: r.shipping_address,
+            shipping_province: r.shipping_province,
+            shipping_city: r.shipping_city,
+            shipping_postcode: r.shipping_postcode,
+            weight_grams: r.weight_grams,
+            note: r.note,
+            status: r.status,
+            created_at: r.created_at,
+            items: items.remove(&r.cart_id).unwrap_or_default(),
+            timeline: timelines.remove(&r.id).unwrap_or_default(),
+        })
+        .collect();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+    (headers, Json(out)).into_response()
+}
 
-        out.push(AdminOrderOut {
+// Admin: list seluruh order + items
+pub async fn api_admin_orders_list(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(q): Query<AdminOrdersQuery>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+
+    let status = match q.status.as_deref().filter(|s| !s.is_empty()) {
+        None => None,
+        Some(s) => match OrderStatus::parse(s) {
+            Some(st) => Some(st),
+            None => return (axum::http::StatusCode::BAD_REQUEST, "invalid status").into_response(),
+        },
+    };
+    let sort_col = match q.sort.as_deref().unwrap_or("id") {
+        "id" => "id",
+        "created_at" => "created_at",
+        "total" => "total",
+        "status" => "status",
+        "customer" => "shipping_name",
+        _ => return (axum::http::StatusCode::BAD_REQUEST, "invalid sort").into_response(),
+    };
+    let sort_dir = match q.dir.as_deref().unwrap_or("desc") {
+        "asc" => "ASC",
+        "desc" => "DESC",
+        _ => return (axum::http::StatusCode::BAD_REQUEST, "invalid dir").into_response(),
+    };
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+    let page = q.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * limit;
+
+    // total (untuk header X-Total-Count)
+    let mut qb = QueryBuilder::<MySql>::new("SELECT COUNT(*) FROM orders");
+    push_admin_filters(&mut qb, &q, status);
+    let (total,): (i64,) = qb.build_query_as().fetch_one(&state.db).await.unwrap();
+
+    let mut qb = QueryBuilder::<MySql>::new(
+        r#"
+        SELECT id, user_id, cart_id, subtotal, discount, delivery_fee, unique_code, total,
+               shipping_name, shipping_phone, shipping_address,
+               shipping_province, shipping_city, shipping_postcode, weight_grams,
+               note, status, created_at
+        FROM orders
+        "#,
+    );
+    push_admin_filters(&mut qb, &q, status);
+    // kolom & arah sort dari whitelist di atas; id sebagai tie-breaker
+    qb.push(format!(" ORDER BY {sort_col} {sort_dir}, id {sort_dir} LIMIT "));
+    qb.push_bind(limit);
+    qb.push(" OFFSET ");
+    qb.push_bind(offset);
+    let rows: Vec<AdminOrderRow> = qb.build_query_as().fetch_all(&state.db).await.unwrap();
+
+    // items semua order di halaman ini dalam satu query
+    let cart_ids: Vec<i64> = rows.iter().map(|r| r.cart_id).collect();
+    let mut items = load_items_many(&state.db, &cart_ids).await;
+
+    let out: Vec<AdminOrderOut> = rows
+        .into_iter()
+        .map(|r| AdminOrderOut {
             id: r.id,
             user_id: r.user_id,
             cart_id: r.cart_id,
             subtotal: r.subtotal,
+            discount: r.discount,
             delivery_fee: r.delivery_fee,
+            unique_code: r.unique_code,
             total: r.total,
             shipping_name: r.shipping_name,
             shipping_phone: r.shipping_phone,
             shipping_address: r.shipping_address,
+            shipping_province: r.shipping_province,
+            shipping_city: r.shipping_city,
+            shipping_postcode: r.shipping_postcode,
+            weight_grams: r.weight_grams,
             note: r.note,
             status: r.status,
             created_at: r.created_at,
-            items,
-        });
-    }
+            items: items.remove(&r.cart_id).unwrap_or_default(),
+        })
+        .collect();
 
-    Json(out).into_response()
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+    (headers, Json(out)).into_response()
 }
 
 // Admin: patch (update ongkir/total/status)
@@ -335,43 +736,62 @@ pub async fn api_admin_orders_patch(
     Path(order_id): Path<i64>,
     Json(inp): Json<AdminOrderPatch>,
 ) -> Response {
-    if ensure_admin(&jar, &state).await.is_none() {
+    let Some(admin) = ensure_admin(&jar, &state).await else {
         return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
-    }
+    };
 
-    let ord = sqlx::query!("SELECT id, subtotal, delivery_fee, total FROM orders WHERE id=?", order_id)
-        .fetch_optional(&state.db)
-        .await
-        .unwrap();
+    // validasi status lebih dulu; status tidak dikirim = tidak diubah
+    let next = match inp.status.as_deref().map(OrderStatus::parse) {
+        None => None,
+        Some(Some(st)) => Some(st),
+        Some(None) => {
+            return (axum::http::StatusCode::BAD_REQUEST, "invalid status").into_response()
+        }
+    };
+
+    let mut tx = state.db.begin().await.unwrap();
+
+    let ord = sqlx::query!(
+        "SELECT id, subtotal, discount, delivery_fee, unique_code, total FROM orders WHERE id=? FOR UPDATE",
+        order_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
 
     if ord.is_none() {
         return (axum::http::StatusCode::NOT_FOUND, "not found").into_response();
     }
     let ord = ord.unwrap();
 
+    if let Some(next) = next {
+        if let Err(e) =
+            change_status(&mut tx, order_id, next, Some(admin.id), inp.note.as_deref()).await
+        {
+            return e.into_response();
+        }
+    }
+
     // update delivery dulu (pakai nilai baru jika ada)
     let delivery = inp.delivery_fee.unwrap_or(ord.delivery_fee);
 
-    // jika total diinput, pakai; jika tidak, hitung dari subtotal + delivery
-    let total = inp.total.unwrap_or(ord.subtotal + delivery);
-
-    let status = inp.status.unwrap_or_else(|| "new".into());
+    // jika total diinput, pakai; jika tidak, hitung dari subtotal - diskon + delivery
+    let total = inp.total.unwrap_or(ord.subtotal - ord.discount + delivery + ord.unique_code);
 
     let _ = sqlx::query!(
-        "UPDATE orders SET delivery_fee=?, total=?, status=? WHERE id=?",
+        "UPDATE orders SET delivery_fee=?, total=? WHERE id=?",
         delivery,
         total,
-        status,
         order_id
     )
-    .execute(&state.db)
+    .execute(&mut *tx)
     .await
     .unwrap();
 
+    tx.commit().await.unwrap();
+
     // Broadcast event update order (agar viewer reload detail)
-    let _ = state
-        .notify_tx
-        .send(serde_json::json!({ "t": "order_update", "order_id": order_id }).to_string());
+    events::order(&state, order_id, Body::OrderUpdate { order_id }).await;
 
     Json(serde_json::json!({ "ok": true })).into_response()
 }
@@ -390,8 +810,10 @@ pub async fn api_order_export_xlsx(
 
     let row = sqlx::query!(
         r#"
-        SELECT id, user_id, cart_id, subtotal, delivery_fee, total,
-               shipping_name, shipping_phone, shipping_address, note, status, created_at
+        SELECT id, user_id, cart_id, subtotal, discount, delivery_fee, unique_code, total,
+               shipping_name, shipping_phone, shipping_address,
+               shipping_province, shipping_city, shipping_postcode, weight_grams,
+               note, status, created_at
         FROM orders WHERE id=? LIMIT 1
         "#,
         order_id
@@ -411,19 +833,7 @@ pub async fn api_order_export_xlsx(
         return (axum::http::StatusCode::FORBIDDEN, "forbidden").into_response();
     }
 
-    let items = sqlx::query!(
-        r#"
-        SELECT p.name AS product_name, ci.qty, ci.price_at_add
-        FROM cart_items ci
-        JOIN products p ON p.id = ci.product_id
-        WHERE ci.cart_id = ?
-        ORDER BY ci.id ASC
-        "#,
-        row.cart_id
-    )
-    .fetch_all(&state.db)
-    .await
-    .unwrap();
+    let items = load_items(&state.db, row.cart_id).await;
 
     // Build workbook (in-memory)
     let mut wb = Workbook::new();
@@ -452,18 +862,21 @@ ws.set_column_width(3, 16).ok();
 
     let mut r = start + 1;
     for it in items {
-        let line_total = it.qty * it.price_at_add;
-        ws.write(r, 0, it.product_name).ok();
+        ws.write(r, 0, item_title(&it)).ok();
         ws.write_number(r, 1, it.qty as f64).ok();
         ws.write_number(r, 2, it.price_at_add as f64).ok();
-        ws.write_number(r, 3, line_total as f64).ok();
+        ws.write_number(r, 3, it.line_total as f64).ok();
         r += 1;
     }
 
     // Ringkasan
     r += 1;
     ws.write(r, 2, "Subtotal").ok();     ws.write_number(r, 3, row.subtotal as f64).ok(); r += 1;
+    if row.discount > 0 {
+        ws.write(r, 2, "Diskon").ok();   ws.write_number(r, 3, -(row.discount as f64)).ok(); r += 1;
+    }
     ws.write(r, 2, "Ongkir").ok();       ws.write_number(r, 3, row.delivery_fee as f64).ok(); r += 1;
+    ws.write(r, 2, "Kode Unik").ok();    ws.write_number(r, 3, row.unique_code as f64).ok(); r += 1;
     ws.write(r, 2, "Grand Total").ok();  ws.write_number(r, 3, row.total as f64).ok();
 
 
@@ -507,8 +920,10 @@ pub async fn api_admin_orders_export_xlsx(
     // Ambil semua order (terbaru dulu)
     let rows = sqlx::query!(
         r#"
-        SELECT id, user_id, cart_id, subtotal, delivery_fee, total,
-               shipping_name, shipping_phone, shipping_address, note, status, created_at
+        SELECT id, user_id, cart_id, subtotal, discount, delivery_fee, unique_code, total,
+               shipping_name, shipping_phone, shipping_address,
+               shipping_province, shipping_city, shipping_postcode, weight_grams,
+               note, status, created_at
         FROM orders
          WHERE status <> 'deleted'
         ORDER BY id DESC
@@ -530,10 +945,11 @@ pub async fn api_admin_orders_export_xlsx(
     ws.write(0, 4, "Alamat").ok();
     ws.write(0, 5, "Status").ok();
     ws.write(0, 6, "Subtotal").ok();
-    ws.write(0, 7, "Ongkir").ok();
-    ws.write(0, 8, "Total").ok();
-    ws.write(0, 9, "Items").ok();
-    ws.write(0, 10, "Catatan").ok();
+    ws.write(0, 7, "Diskon").ok();
+    ws.write(0, 8, "Ongkir").ok();
+    ws.write(0, 9, "Total").ok();
+    ws.write(0, 10, "Items").ok();
+    ws.write(0, 11, "Catatan").ok();
 
     // Lebar kolom biar rapi
     ws.set_column_width(0, 8).ok();   // ID
@@ -543,28 +959,17 @@ pub async fn api_admin_orders_export_xlsx(
     ws.set_column_width(4, 36).ok();  // Alamat
     ws.set_column_width(5, 12).ok();  // Status
     ws.set_column_width(6, 14).ok();  // Subtotal
-    ws.set_column_width(7, 12).ok();  // Ongkir
-    ws.set_column_width(8, 14).ok();  // Total
-    ws.set_column_width(9, 48).ok();  // Items
-    ws.set_column_width(10, 28).ok(); // Catatan
+    ws.set_column_width(7, 12).ok();  // Diskon
+    ws.set_column_width(8, 12).ok();  // Ongkir
+    ws.set_column_width(9, 14).ok();  // Total
+    ws.set_column_width(10, 48).ok(); // Items
+    ws.set_column_width(11, 28).ok(); // Catatan
 
     // Isi baris
     let mut r: u32 = 1;
     for o in rows {
         // Ambil items per order
-        let item_rows = sqlx::query!(
-            r#"
-            SELECT p.name AS product_name, ci.qty, ci.price_at_add
-            FROM cart_items ci
-            JOIN products p ON p.id = ci.product_id
-            WHERE ci.cart_id = ?
-            ORDER BY ci.id ASC
-            "#,
-            o.cart_id
-        )
-        .fetch_all(&state.db)
-        .await
-        .unwrap();
+        let item_rows = load_items(&state.db, o.cart_id).await;
 
         // Gabungkan items menjadi satu string
         let items_joined = if item_rows.is_empty() {
@@ -573,13 +978,12 @@ pub async fn api_admin_orders_export_xlsx(
             item_rows
                 .into_iter()
                 .map(|it| {
-                    let line_total = (it.qty * it.price_at_add) as i64;
                     format!(
                         "{} x {} @ {} = {}",
-                        it.product_name,
+                        item_title(&it),
                         it.qty,
                         it.price_at_add,
-                        line_total
+                        it.line_total
                     )
                 })
                 .collect::<Vec<_>>()
@@ -596,10 +1000,11 @@ pub async fn api_admin_orders_export_xlsx(
         ws.write(r, 4, &o.shipping_address).ok();
         ws.write(r, 5, &o.status).ok();
         ws.write_number(r, 6, o.subtotal as f64).ok();
-        ws.write_number(r, 7, o.delivery_fee as f64).ok();
-        ws.write_number(r, 8, o.total as f64).ok();
-        ws.write(r, 9, items_joined).ok();
-        if let Some(note) = &o.note { ws.write(r, 10, note).ok(); }
+        ws.write_number(r, 7, o.discount as f64).ok();
+        ws.write_number(r, 8, o.delivery_fee as f64).ok();
+        ws.write_number(r, 9, o.total as f64).ok();
+        ws.write(r, 10, items_joined).ok();
+        if let Some(note) = &o.note { ws.write(r, 11, note).ok(); }
         r += 1;
     }
 
@@ -634,7 +1039,7 @@ pub async fn api_order_delete(
     // Ambil order + cek otorisasi owner/admin
     let (_, user_opt) = get_user_from_cookie(&jar, &state).await;
 
-    let row = sqlx::query!("SELECT id, user_id, status FROM orders WHERE id=? LIMIT 1", order_id)
+    let row = sqlx::query!("SELECT id, user_id FROM orders WHERE id=? LIMIT 1", order_id)
         .fetch_optional(&state.db)
         .await
         .unwrap();
@@ -644,45 +1049,292 @@ pub async fn api_order_delete(
     }
     let row = row.unwrap();
 
-    let is_admin = ensure_admin(&jar, &state).await.is_some();
+    let admin = ensure_admin(&jar, &state).await;
     let is_owner = user_opt.as_ref().map(|u| u.id == row.user_id).unwrap_or(false);
-    if !is_admin && !is_owner {
+    if admin.is_none() && !is_owner {
         return (axum::http::StatusCode::FORBIDDEN, "forbidden").into_response();
     }
 
-    // (Opsional) batasi hanya status tertentu
-    // if row.status != "new" {
-    //     return (axum::http::StatusCode::BAD_REQUEST, "only 'new' deletable").into_response();
-    // }
-
-    let _ = sqlx::query!("UPDATE orders SET status='deleted' WHERE id=?", order_id)
-        .execute(&state.db)
-        .await
-        .unwrap();
+    // hanya status yang boleh → deleted (lihat OrderStatus::can_transition_to)
+    let by = admin.or(user_opt).map(|u| u.id);
+    let mut tx = state.db.begin().await.unwrap();
+    if let Err(e) = change_status(&mut tx, order_id, OrderStatus::Deleted, by, None).await {
+        return e.into_response();
+    }
+    tx.commit().await.unwrap();
 
     // Broadcast
-    let _ = state
-        .notify_tx
-        .send(serde_json::json!({ "t": "order_deleted", "order_id": order_id }).to_string());
+    events::order(&state, order_id, Body::OrderDeleted { order_id }).await;
 
     Json(serde_json::json!({ "ok": true })).into_response()
 }
 
 /* ===================== Local helpers (khusus modul ini) ===================== */
 
-async fn ensure_viewer_cart(db: &Pool<MySql>, user_id: i64) -> i64 {
-    if let Some(r) =
-        sqlx::query!("SELECT id FROM carts WHERE user_id=? AND status='open' LIMIT 1", user_id)
-            .fetch_optional(db)
-            .await
-            .unwrap()
-    {
-        r.id
-    } else {
-        sqlx::query!("INSERT INTO carts(user_id,status) VALUES(?, 'open')", user_id)
-            .execute(db)
-            .await
-            .unwrap()
-            .last_insert_id() as i64
+/// Pindahkan order ke status `to` di dalam transaksi `tx`: validasi tabel transisi,
+/// kembalikan stok bila order dibatalkan/dihapus, lalu catat ke `order_status_history`.
+/// Status yang sama dianggap no-op. Mengembalikan status sebelumnya.
+pub(crate) async fn change_status(
+    tx: &mut sqlx::Transaction<'_, MySql>,
+    order_id: i64,
+    to: OrderStatus,
+    by: Option<i64>,
+    note: Option<&str>,
+) -> Result<OrderStatus, StatusChangeError> {
+    let row = sqlx::query!("SELECT cart_id, status FROM orders WHERE id=? FOR UPDATE", order_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .unwrap()
+        .ok_or(StatusChangeError::NotFound)?;
+
+    let from = OrderStatus::parse(&row.status).expect("orders.status enum");
+    if from == to {
+        return Ok(from);
     }
+    if !from.can_transition_to(to) {
+        return Err(StatusChangeError::Illegal { from, to });
+    }
+
+    if to.releases_stock() && !from.releases_stock() {
+        restock_cart(tx, row.cart_id).await;
+    }
+
+    let _ = sqlx::query!("UPDATE orders SET status=? WHERE id=?", to.as_str(), order_id)
+        .execute(&mut **tx)
+        .await
+        .unwrap();
+
+    record_status(tx, order_id, Some(from), to, by, note).await;
+    Ok(from)
+}
+
+async fn record_status(
+    tx: &mut sqlx::Transaction<'_, MySql>,
+    order_id: i64,
+    from: Option<OrderStatus>,
+    to: OrderStatus,
+    by: Option<i64>,
+    note: Option<&str>,
+) {
+    let _ = sqlx::query!(
+        "INSERT INTO order_status_history(order_id,from_status,to_status,changed_by,note) VALUES(?,?,?,?,?)",
+        order_id,
+        from.map(OrderStatus::as_str),
+        to.as_str(),
+        by,
+        note
+    )
+    .execute(&mut **tx)
+    .await
+    .unwrap();
+}
+
+// kembalikan stok semua item pada cart milik order
+async fn restock_cart(tx: &mut sqlx::Transaction<'_, MySql>, cart_id: i64) {
+    let _ = sqlx::query!(
+        r#"
+        UPDATE products p
+        JOIN cart_items ci ON ci.product_id = p.id
+        SET p.stock = p.stock + ci.qty
+        WHERE ci.cart_id = ? AND ci.variant_id IS NULL
+        "#,
+        cart_id
+    )
+    .execute(&mut **tx)
+    .await
+    .unwrap();
+
+    let _ = sqlx::query!(
+        r#"
+        UPDATE product_variants v
+        JOIN cart_items ci ON ci.variant_id = v.id
+        SET v.stock = v.stock + ci.qty
+        WHERE ci.cart_id = ?
+        "#,
+        cart_id
+    )
+    .execute(&mut **tx)
+    .await
+    .unwrap();
+}
+
+// WHERE untuk list admin; dipakai query COUNT dan query data
+fn push_admin_filters(
+    qb: &mut QueryBuilder<'_, MySql>,
+    q: &AdminOrdersQuery,
+    status: Option<OrderStatus>,
+) {
+    qb.push(" WHERE status <> 'deleted'");
+    if let Some(st) = status {
+        qb.push(" AND status = ").push_bind(st.as_str());
+    }
+    if let Some(from) = q.from {
+        qb.push(" AND created_at >= ").push_bind(from.and_hms_opt(0, 0, 0).unwrap());
+    }
+    if let Some(to) = q.to.and_then(|d| d.succ_opt()) {
+        qb.push(" AND created_at < ").push_bind(to.and_hms_opt(0, 0, 0).unwrap());
+    }
+    if let Some(term) = q.q.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        let like = format!(
+            "%{}%",
+            term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+        qb.push(" AND (shipping_name LIKE ")
+            .push_bind(like.clone())
+            .push(" OR shipping_phone LIKE ")
+            .push_bind(like)
+            .push(")");
+    }
+    if let Some(min) = q.min_total {
+        qb.push(" AND total >= ").push_bind(min);
+    }
+    if let Some(max) = q.max_total {
+        qb.push(" AND total <= ").push_bind(max);
+    }
+}
+
+// item sebuah cart/order, lengkap dengan varian yang dipilih
+async fn load_items(db: &Pool<MySql>, cart_id: i64) -> Vec<ItemOut> {
+    load_items_many(db, &[cart_id]).await.remove(&cart_id).unwrap_or_default()
+}
+
+#[derive(sqlx::FromRow)]
+struct ItemRow {
+    cart_id: i64,
+    product_name: String,
+    variant_id: Option<i64>,
+    qty: i32,
+    price_at_add: i32,
+    sku: Option<String>,
+    option1: Option<String>,
+    option2: Option<String>,
+    option3: Option<String>,
+}
+
+// item untuk banyak cart sekaligus (satu query), dikelompokkan per cart_id
+async fn load_items_many(db: &Pool<MySql>, cart_ids: &[i64]) -> HashMap<i64, Vec<ItemOut>> {
+    let mut out: HashMap<i64, Vec<ItemOut>> = HashMap::new();
+    if cart_ids.is_empty() {
+        return out;
+    }
+
+    let mut qb = QueryBuilder::<MySql>::new(
+        r#"
+        SELECT ci.cart_id, p.name AS product_name, ci.variant_id, ci.qty, ci.price_at_add,
+               v.sku, v.option1, v.option2, v.option3
+        FROM cart_items ci
+        JOIN products p ON p.id = ci.product_id
+        LEFT JOIN product_variants v ON v.id = ci.variant_id
+        WHERE ci.cart_id IN (
+        "#,
+    );
+    let mut sep = qb.separated(", ");
+    for id in cart_ids {
+        sep.push_bind(id);
+    }
+    qb.push(") ORDER BY ci.id ASC");
+
+    let rows: Vec<ItemRow> = qb.build_query_as().fetch_all(db).await.unwrap();
+    for it in rows {
+        out.entry(it.cart_id).or_default().push(ItemOut {
+            product_name: it.product_name, // kolom NOT NULL
+            variant_id: it.variant_id,
+            sku: it.sku,
+            variant_label: variant_label(&[it.option1, it.option2, it.option3]),
+            qty: it.qty,
+            price_at_add: it.price_at_add,
+            line_total: it.qty * it.price_at_add,
+        });
+    }
+    out
+}
+
+#[derive(sqlx::FromRow)]
+struct StatusEventRow {
+    order_id: i64,
+    from_status: Option<String>,
+    to_status: String,
+    changed_by: Option<i64>,
+    by_name: Option<String>,
+    note: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+// riwayat status untuk banyak order sekaligus, dikelompokkan per order_id
+async fn load_timelines(db: &Pool<MySql>, order_ids: &[i64]) -> HashMap<i64, Vec<StatusEventOut>> {
+    let mut out: HashMap<i64, Vec<StatusEventOut>> = HashMap::new();
+    if order_ids.is_empty() {
+        return out;
+    }
+
+    let mut qb = QueryBuilder::<MySql>::new(
+        r#"
+        SELECT h.order_id, h.from_status, h.to_status, h.changed_by, u.name AS by_name,
+               h.note, h.created_at
+        FROM order_status_history h
+        LEFT JOIN users u ON u.id = h.changed_by
+        WHERE h.order_id IN (
+        "#,
+    );
+    let mut sep = qb.separated(", ");
+    for id in order_ids {
+        sep.push_bind(id);
+    }
+    qb.push(") ORDER BY h.id ASC");
+
+    let rows: Vec<StatusEventRow> = qb.build_query_as().fetch_all(db).await.unwrap();
+    for h in rows {
+        out.entry(h.order_id).or_default().push(StatusEventOut {
+            from: h.from_status,
+            to: h.to_status,
+            by_user_id: h.changed_by,
+            by_name: h.by_name,
+            note: h.note,
+            at: h.created_at,
+        });
+    }
+    out
+}
+
+// "Kaos Polos (M / Merah) [KP-M-RED]" untuk export / packing list
+fn item_title(it: &ItemOut) -> String {
+    let mut t = it.product_name.clone();
+    if let Some(label) = &it.variant_label {
+        t.push_str(&format!(" ({label})"));
+    }
+    if let Some(sku) = &it.sku {
+        t.push_str(&format!(" [{sku}]"));
+    }
+    t
+}
+
+// Kode unik 1..=999 sehingga `base + kode` belum dipakai order 'new' lain.
+// Jika semua kode terpakai, tetap pilih acak (baris mutasi akan dilaporkan ambigu).
+async fn pick_unique_code(tx: &mut sqlx::Transaction<'_, MySql>, base: i64) -> i64 {
+    let used: std::collections::HashSet<i64> = sqlx::query!(
+        "SELECT total FROM orders WHERE status='new' AND total BETWEEN ? AND ?",
+        base + 1,
+        base + 999
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| r.total as i64 - base)
+    .collect();
+
+    let free: Vec<i64> = (1..=999).filter(|c| !used.contains(c)).collect();
+    let mut rng = rand::thread_rng();
+    free.choose(&mut rng).copied().unwrap_or_else(|| rng.gen_range(1..=999))
+}
+
+// balasan checkout untuk retry dengan Idempotency-Key yang sudah dipakai
+fn checkout_replay(order_id: i64, total: i64) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert("idempotent-replayed", HeaderValue::from_static("true"));
+    (
+        headers,
+        Json(serde_json::json!({ "ok": true, "order_id": order_id, "total": total })),
+    )
+        .into_response()
 }
@@ -0,0 +1,359 @@
+// server/src/handlers/payments.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ensure_admin, events, get_user_from_cookie,
+    handlers::orders::change_status,
+    order_status::{OrderStatus, StatusChangeError},
+    payments::{IntentRequest, PaymentError, PaymentStatus, PaymentUpdate},
+    ws_protocol::Body,
+    AppState,
+};
+
+#[derive(Deserialize)]
+struct PayReq {
+    // "va" | "qris"
+    method: String,
+}
+
+#[derive(Deserialize)]
+struct SimulateReq {
+    status: PaymentStatus,
+    // default: nominal tagihan
+    amount: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct PaymentOut {
+    id: i64,
+    order_id: i64,
+    provider: String,
+    provider_ref: String,
+    method: String,
+    amount: i32,
+    status: String,
+    pay_url: Option<String>,
+    va_number: Option<String>,
+    qr_string: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    paid_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        // Viewer
+        .route("/api/orders/:id/pay", post(api_order_pay))
+        .route("/api/orders/:id/payment", get(api_order_payment))
+        // Provider → server
+        .route("/api/payments/webhook/:provider", post(api_payment_webhook))
+        // Admin (hanya provider lokal/mock)
+        .route("/api/admin/payments/:provider_ref/simulate", post(api_admin_payment_simulate))
+}
+
+/* ===================== Handlers ===================== */
+
+// Buat tagihan untuk order 'new' milik user. Tagihan pending yang masih ada dipakai ulang;
+// ganti metode / nominal = tagihan pending lama dibatalkan dulu (tidak bisa bayar dua kali).
+async fn api_order_pay(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(order_id): Path<i64>,
+    Json(req): Json<PayReq>,
+) -> Response {
+    let (_, user) = get_user_from_cookie(&jar, &state).await;
+    let Some(u) = user else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "login first").into_response();
+    };
+
+    let ord = sqlx::query!(
+        "SELECT id, user_id, total, status FROM orders WHERE id=? LIMIT 1",
+        order_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .unwrap();
+    let Some(ord) = ord else {
+        return (axum::http::StatusCode::NOT_FOUND, "not found").into_response();
+    };
+    if ord.user_id != u.id {
+        return (axum::http::StatusCode::FORBIDDEN, "forbidden").into_response();
+    }
+    if ord.status != OrderStatus::New.as_str() {
+        return (axum::http::StatusCode::CONFLICT, "order not payable").into_response();
+    }
+
+    if let Some(p) = latest_payment(&state, order_id).await {
+        if p.status == PaymentStatus::Pending.as_str() && p.method == req.method && p.amount == ord.total {
+            return Json(p).into_response();
+        }
+    }
+
+    let stale = sqlx::query!(
+        "SELECT id, provider, provider_ref FROM payments WHERE order_id=? AND status='pending'",
+        order_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+    for p in stale {
+        if p.provider == state.payments.name() {
+            // provider menolak (mis. baru saja dibayar): jangan buat tagihan kedua
+            if let Err(e) = state.payments.cancel_intent(&p.provider_ref).await {
+                eprintln!("payment cancel_intent {} error: {e}", p.provider_ref);
+                return (axum::http::StatusCode::CONFLICT, "previous payment still active").into_response();
+            }
+        } else {
+            eprintln!("payment {}: provider {} not configured, expired locally", p.provider_ref, p.provider);
+        }
+        sqlx::query!("UPDATE payments SET status='expired' WHERE id=? AND status='pending'", p.id)
+            .execute(&state.db)
+            .await
+            .unwrap();
+    }
+
+    let intent = state
+        .payments
+        .create_intent(&IntentRequest {
+            order_id,
+            amount: ord.total as i64,
+            method: req.method.clone(),
+        })
+        .await;
+    let intent = match intent {
+        Ok(i) => i,
+        Err(e @ PaymentError::Unsupported(_)) => {
+            return (axum::http::StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+        Err(e) => {
+            eprintln!("payment create_intent error: {e}");
+            return (axum::http::StatusCode::BAD_GATEWAY, "payment provider error").into_response();
+        }
+    };
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO payments(order_id, provider, provider_ref, method, amount, status,
+                             pay_url, va_number, qr_string, expires_at)
+        VALUES(?,?,?,?,?,'pending',?,?,?,?)
+        "#,
+        order_id,
+        state.payments.name(),
+        intent.provider_ref,
+        intent.method,
+        intent.amount as i32,
+        intent.pay_url,
+        intent.va_number,
+        intent.qr_string,
+        intent.expires_at
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+
+    Json(latest_payment(&state, order_id).await).into_response()
+}
+
+// Status pembayaran terakhir sebuah order (pemilik/admin). Jika masih pending,
+// status ditanyakan ulang ke provider (webhook bisa saja terlambat / hilang).
+async fn api_order_payment(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(order_id): Path<i64>,
+) -> Response {
+    let (_, user_opt) = get_user_from_cookie(&jar, &state).await;
+
+    let ord = sqlx::query!("SELECT user_id FROM orders WHERE id=? LIMIT 1", order_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap();
+    let Some(ord) = ord else {
+        return (axum::http::StatusCode::NOT_FOUND, "not found").into_response();
+    };
+    let is_admin = ensure_admin(&jar, &state).await.is_some();
+    let is_owner = user_opt.as_ref().map(|u| u.id == ord.user_id).unwrap_or(false);
+    if !is_admin && !is_owner {
+        return (axum::http::StatusCode::FORBIDDEN, "forbidden").into_response();
+    }
+
+    let Some(p) = latest_payment(&state, order_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND, "no payment").into_response();
+    };
+
+    if p.status == PaymentStatus::Pending.as_str() && p.provider == state.payments.name() {
+        match state.payments.query_status(&p.provider_ref).await {
+            Ok(st) if st != PaymentStatus::Pending => {
+                let upd = PaymentUpdate {
+                    provider_ref: p.provider_ref.clone(),
+                    status: st,
+                    amount: p.amount as i64,
+                };
+                if let Err(resp) = apply_update(&state, upd, b"").await {
+                    return resp;
+                }
+                return Json(latest_payment(&state, order_id).await).into_response();
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("payment query_status error: {e}"),
+        }
+    }
+
+    Json(p).into_response()
+}
+
+// Webhook dari provider: verifikasi signature, update payment, order → paid.
+async fn api_payment_webhook(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if provider != state.payments.name() {
+        return (axum::http::StatusCode::NOT_FOUND, "unknown provider").into_response();
+    }
+
+    let upd = match state.payments.verify_callback(&headers, &body) {
+        Ok(u) => u,
+        Err(PaymentError::BadSignature) => {
+            return (axum::http::StatusCode::UNAUTHORIZED, "bad signature").into_response()
+        }
+        Err(e) => return (axum::http::StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    match apply_update(&state, upd, &body).await {
+        Ok(()) => Json(serde_json::json!({ "ok": true })).into_response(),
+        Err(resp) => resp,
+    }
+}
+
+// Jalankan webhook bertanda tangan dari provider lokal (dev / test end-to-end offline).
+async fn api_admin_payment_simulate(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(provider_ref): Path<String>,
+    Json(req): Json<SimulateReq>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+
+    let p = sqlx::query!("SELECT amount FROM payments WHERE provider_ref=? LIMIT 1", provider_ref)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap();
+    let Some(p) = p else {
+        return (axum::http::StatusCode::NOT_FOUND, "payment not found").into_response();
+    };
+
+    let amount = req.amount.unwrap_or(p.amount as i64);
+    let Some((headers, body)) = state.payments.simulate_callback(&provider_ref, req.status, amount)
+    else {
+        return (axum::http::StatusCode::NOT_FOUND, "provider has no simulator").into_response();
+    };
+
+    // lewat jalur yang sama dengan webhook sungguhan (termasuk verifikasi signature)
+    let provider = state.payments.name().to_string();
+    api_payment_webhook(State(state), Path(provider), headers, Bytes::from(body)).await
+}
+
+/* ===================== helpers lokal ===================== */
+
+async fn latest_payment(state: &AppState, order_id: i64) -> Option<PaymentOut> {
+    sqlx::query_as!(
+        PaymentOut,
+        r#"
+        SELECT id, order_id, provider, provider_ref, method, amount, status,
+               pay_url, va_number, qr_string, expires_at, paid_at, created_at
+        FROM payments WHERE order_id=? ORDER BY id DESC LIMIT 1
+        "#,
+        order_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .unwrap()
+}
+
+// Terapkan update status pembayaran (idempotent). Payment 'paid' bersifat final;
+// saat lunas, order dipindah ke 'paid' lewat state machine order.
+async fn apply_update(state: &AppState, upd: PaymentUpdate, raw: &[u8]) -> Result<(), Response> {
+    let mut tx = state.db.begin().await.unwrap();
+
+    let p = sqlx::query!(
+        "SELECT id, order_id, amount, status FROM payments WHERE provider=? AND provider_ref=? FOR UPDATE",
+        state.payments.name(),
+        upd.provider_ref
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .unwrap();
+    let Some(p) = p else {
+        return Err((axum::http::StatusCode::NOT_FOUND, "payment not found").into_response());
+    };
+
+    let current = PaymentStatus::parse(&p.status).expect("payments.status enum");
+    if current == upd.status || current == PaymentStatus::Paid {
+        return Ok(());
+    }
+    if upd.status == PaymentStatus::Paid && upd.amount != p.amount as i64 {
+        eprintln!(
+            "payment {} amount mismatch: expected {}, got {}",
+            upd.provider_ref, p.amount, upd.amount
+        );
+        return Err((axum::http::StatusCode::BAD_REQUEST, "amount mismatch").into_response());
+    }
+
+    let raw = (!raw.is_empty()).then(|| String::from_utf8_lossy(raw).into_owned());
+    let _ = sqlx::query!(
+        r#"
+        UPDATE payments
+        SET status=?, paid_at=IF(?='paid', NOW(), paid_at), raw_callback=COALESCE(?, raw_callback)
+        WHERE id=?
+        "#,
+        upd.status.as_str(),
+        upd.status.as_str(),
+        raw,
+        p.id
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+
+    if upd.status == PaymentStatus::Paid {
+        let note = format!("payment {} via {}", upd.provider_ref, state.payments.name());
+        match change_status(&mut tx, p.order_id, OrderStatus::Paid, None, Some(&note)).await {
+            Ok(_) => {}
+            // uang sudah diterima tapi order sudah dibatalkan, dsb: payment tetap dicatat lunas,
+            // admin yang menindaklanjuti (refund / aktifkan manual)
+            Err(StatusChangeError::Illegal { from, .. }) => {
+                eprintln!("payment {} paid but order {} is {:?}", upd.provider_ref, p.order_id, from)
+            }
+            Err(e) => return Err(e.into_response()),
+        }
+    }
+
+    tx.commit().await.unwrap();
+
+    events::order(state, p.order_id, Body::OrderUpdate { order_id: p.order_id }).await;
+    Ok(())
+}
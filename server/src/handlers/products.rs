@@ -11,8 +11,10 @@ LinkedIn: https://id.linkedin.com/in/kukuhtw
 */
 
 
+use std::collections::HashMap;
+
 use axum::{
-    extract::{Multipart, Path, State},
+    extract::{Multipart, Path, Query, State},
     response::{IntoResponse, Response},
     routing::{delete, get, post, put},
     Json, Router,
@@ -22,23 +24,81 @@ use serde_json::json;
 use tokio::{fs, io::AsyncWriteExt};
 use uuid::Uuid;
 
-use crate::{ensure_admin, AppState};
+use crate::{ensure_admin, flash_sales::{self, ActiveSale}, is_unique_violation, AppState};
 
 #[derive(Serialize, Deserialize)]
 struct ProductOut {
     id: i64,
+    // kode singkat untuk order lewat komentar live (mis. "A12")
+    code: Option<String>,
     name: String,
     description: Option<String>,
     image_url: Option<String>,
     price_idr: i32,
+    stock: i32,
+    // berat per pcs (gram), dipakai untuk hitung ongkir
+    weight_grams: i32,
     is_active: bool,
+    options: Vec<OptionOut>,
+    variants: Vec<VariantOut>,
+    // flash sale yang sedang berjalan: sale_price berlaku untuk produk & semua varian,
+    // price_idr tetap harga normal (ditampilkan dicoret)
+    flash_sale: Option<ActiveSale>,
+}
+
+// Nama opsi (mis. "Ukuran") + nilai yang dipakai varian aktif (mis. S/M/L)
+#[derive(Serialize, Deserialize)]
+struct OptionOut {
+    name: String,
+    values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VariantOut {
+    id: i64,
+    sku: String,
+    option1: Option<String>,
+    option2: Option<String>,
+    option3: Option<String>,
+    // harga efektif (override varian atau harga produk)
+    price_idr: i32,
+    price_override: Option<i32>,
+    stock: i32,
+    image_url: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct ProductIn {
+    // None saat update = tidak diubah, "" = hapus kode
+    code: Option<String>,
     name: String,
     description: Option<String>,
     price_idr: i32,
+    // wajib saat create; None saat update = stok tidak diubah
+    stock: Option<i32>,
+    // gram; None saat update = tidak diubah
+    weight_grams: Option<i32>,
+    is_active: Option<bool>,
+    // nama opsi varian, maks. 3 (None saat update = tidak diubah)
+    options: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct ProductsQuery {
+    // room live yang sedang ditonton (flash sale khusus room)
+    room: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VariantIn {
+    sku: String,
+    option1: Option<String>,
+    option2: Option<String>,
+    option3: Option<String>,
+    // None = pakai harga produk
+    price_idr: Option<i32>,
+    // wajib saat create; None saat update = stok tidak diubah
+    stock: Option<i32>,
     is_active: Option<bool>,
 }
 
@@ -49,26 +109,77 @@ pub fn routes() -> Router<AppState> {
         .route("/api/admin/products/:id", put(api_admin_product_update))
         .route("/api/admin/products/:id", delete(api_admin_product_delete))
         .route("/api/admin/products/:id/photo", post(api_admin_product_upload_photo))
+        .route("/api/admin/products/:id/variants", post(api_admin_variant_create))
+        .route("/api/admin/variants/:id", put(api_admin_variant_update))
+        .route("/api/admin/variants/:id", delete(api_admin_variant_delete))
+        .route("/api/admin/variants/:id/photo", post(api_admin_variant_upload_photo))
 }
 
-async fn api_products_list(State(state): State<AppState>) -> Response {
+async fn api_products_list(State(state): State<AppState>, Query(q): Query<ProductsQuery>) -> Response {
     let rows = sqlx::query!(
-        "SELECT id, name, description, image_url, price_idr, is_active
+        "SELECT id, code, name, description, image_url, price_idr, stock, weight_grams, is_active,
+                option1_name, option2_name, option3_name
          FROM products WHERE is_active=1 ORDER BY id DESC"
     )
     .fetch_all(&state.db)
     .await
     .unwrap();
 
+    // semua varian aktif sekaligus, dikelompokkan per produk
+    let vrows = sqlx::query!(
+        r#"
+        SELECT v.id, v.product_id, v.sku, v.option1, v.option2, v.option3,
+               v.price_idr, v.stock, v.image_url
+        FROM product_variants v
+        JOIN products p ON p.id = v.product_id
+        WHERE p.is_active=1 AND v.is_active=1
+        ORDER BY v.id ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let base_price: HashMap<i64, i32> = rows.iter().map(|r| (r.id, r.price_idr)).collect();
+    let mut by_product: HashMap<i64, Vec<VariantOut>> = HashMap::new();
+    for v in vrows {
+        let base = base_price.get(&v.product_id).copied().unwrap_or(0);
+        by_product.entry(v.product_id).or_default().push(VariantOut {
+            id: v.id,
+            sku: v.sku,
+            option1: v.option1,
+            option2: v.option2,
+            option3: v.option3,
+            price_idr: v.price_idr.unwrap_or(base),
+            price_override: v.price_idr,
+            stock: v.stock,
+            image_url: v.image_url,
+        });
+    }
+
+    let room = q.room.as_deref().filter(|r| !r.is_empty());
+    let mut sales = flash_sales::active_by_product(&state.db, room).await;
+
     let list: Vec<ProductOut> = rows
         .into_iter()
-        .map(|r| ProductOut {
-            id: r.id,
-            name: r.name,
-            description: r.description,
-            image_url: r.image_url,
-            price_idr: r.price_idr,
-            is_active: r.is_active != 0,
+        .map(|r| {
+            let variants = by_product.remove(&r.id).unwrap_or_default();
+            let options =
+                build_options([r.option1_name, r.option2_name, r.option3_name], &variants);
+            ProductOut {
+                id: r.id,
+                code: r.code,
+                name: r.name,
+                description: r.description,
+                image_url: r.image_url,
+                price_idr: r.price_idr,
+                stock: r.stock,
+                weight_grams: r.weight_grams,
+                is_active: r.is_active != 0,
+                options,
+                variants,
+                flash_sale: sales.remove(&r.id),
+            }
         })
         .collect();
 
@@ -83,19 +194,51 @@ async fn api_admin_product_create(
     if ensure_admin(&jar, &state).await.is_none() {
         return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
     }
+    // stok tidak ditebak: produk baru tanpa stok eksplisit ditolak
+    let stock = match inp.stock {
+        Some(s) if s >= 0 => s,
+        Some(_) => return (axum::http::StatusCode::BAD_REQUEST, "stock must be >= 0").into_response(),
+        None => return (axum::http::StatusCode::UNPROCESSABLE_ENTITY, "stock required").into_response(),
+    };
+    if inp.weight_grams.is_some_and(|w| w < 0) {
+        return (axum::http::StatusCode::BAD_REQUEST, "weight_grams must be >= 0").into_response();
+    }
+    let [o1, o2, o3] = match option_names(inp.options.as_deref().unwrap_or_default()) {
+        Some(n) => n,
+        None => return (axum::http::StatusCode::BAD_REQUEST, "max 3 options").into_response(),
+    };
+    let code = match inp.code.as_deref().map(product_code).transpose() {
+        Ok(c) => c.flatten(),
+        Err(resp) => return resp,
+    };
     let is_active = inp.is_active.unwrap_or(true);
-    let _ = sqlx::query!(
-        "INSERT INTO products(name,description,price_idr,is_active) VALUES(?,?,?,?)",
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO products(code,name,description,price_idr,stock,weight_grams,is_active,
+                             option1_name,option2_name,option3_name)
+        VALUES(?,?,?,?,?,?,?,?,?,?)
+        "#,
+        code,
         inp.name,
         inp.description,
         inp.price_idr,
-        if is_active { 1 } else { 0 }
+        stock,
+        inp.weight_grams.unwrap_or(0),
+        if is_active { 1 } else { 0 },
+        o1,
+        o2,
+        o3
     )
     .execute(&state.db)
-    .await
-    .unwrap();
+    .await;
 
-    Json(json!({"ok": true})).into_response()
+    match res {
+        Ok(r) => Json(json!({"ok": true, "id": r.last_insert_id() as i64})).into_response(),
+        Err(e) if is_unique_violation(&e) => {
+            (axum::http::StatusCode::CONFLICT, "code already used").into_response()
+        }
+        Err(e) => panic!("insert product: {e:?}"),
+    }
 }
 
 async fn api_admin_product_update(
@@ -107,12 +250,31 @@ async fn api_admin_product_update(
     if ensure_admin(&jar, &state).await.is_none() {
         return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
     }
+    if inp.stock.is_some_and(|s| s < 0) {
+        return (axum::http::StatusCode::BAD_REQUEST, "stock must be >= 0").into_response();
+    }
+    if inp.weight_grams.is_some_and(|w| w < 0) {
+        return (axum::http::StatusCode::BAD_REQUEST, "weight_grams must be >= 0").into_response();
+    }
+    if inp.options.as_ref().is_some_and(|o| o.len() > 3) {
+        return (axum::http::StatusCode::BAD_REQUEST, "max 3 options").into_response();
+    }
+    let code = match inp.code.as_deref().map(product_code).transpose() {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
     let is_active = inp.is_active.unwrap_or(true);
     let _ = sqlx::query!(
-        "UPDATE products SET name=?, description=?, price_idr=?, is_active=? WHERE id=?",
+        r#"
+        UPDATE products SET name=?, description=?, price_idr=?, stock=COALESCE(?, stock),
+            weight_grams=COALESCE(?, weight_grams), is_active=?
+        WHERE id=?
+        "#,
         inp.name,
         inp.description,
         inp.price_idr,
+        inp.stock,
+        inp.weight_grams,
         if is_active { 1 } else { 0 },
         id
     )
@@ -120,6 +282,32 @@ async fn api_admin_product_update(
     .await
     .unwrap();
 
+    if let Some([o1, o2, o3]) = inp.options.as_deref().and_then(option_names) {
+        let _ = sqlx::query!(
+            "UPDATE products SET option1_name=?, option2_name=?, option3_name=? WHERE id=?",
+            o1,
+            o2,
+            o3,
+            id
+        )
+        .execute(&state.db)
+        .await
+        .unwrap();
+    }
+
+    if let Some(code) = code {
+        let res = sqlx::query!("UPDATE products SET code=? WHERE id=?", code, id)
+            .execute(&state.db)
+            .await;
+        match res {
+            Ok(_) => {}
+            Err(e) if is_unique_violation(&e) => {
+                return (axum::http::StatusCode::CONFLICT, "code already used").into_response()
+            }
+            Err(e) => panic!("update product code: {e:?}"),
+        }
+    }
+
     Json(json!({"ok": true})).into_response()
 }
 
@@ -158,8 +346,176 @@ async fn api_admin_product_upload_photo(
         return (axum::http::StatusCode::NOT_FOUND, "product not found").into_response();
     }
 
-    let mut saved_url: Option<String> = None;
+    let saved_url = match save_photo(&format!("p{}", id), &mut multipart).await {
+        Ok(url) => url,
+        Err(resp) => return resp,
+    };
+
+    if let Some(url) = saved_url {
+        let _ = sqlx::query!("UPDATE products SET image_url=? WHERE id=?", url, id)
+            .execute(&state.db)
+            .await
+            .unwrap();
+        Json(json!({ "ok": true, "image_url": url })).into_response()
+    } else {
+        (axum::http::StatusCode::BAD_REQUEST, "no photo field").into_response()
+    }
+}
+
+/* ==================== Varian (admin) ==================== */
+
+async fn api_admin_variant_create(
+    State(state): State<AppState>,
+    jar: axum_extra::extract::cookie::CookieJar,
+    Path(product_id): Path<i64>,
+    Json(inp): Json<VariantIn>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    if let Some(resp) = validate_variant(&inp) {
+        return resp;
+    }
+    // sama dengan produk: varian baru tanpa stok eksplisit ditolak
+    let Some(stock) = inp.stock else {
+        return (axum::http::StatusCode::UNPROCESSABLE_ENTITY, "stock required").into_response();
+    };
+
+    let prod = sqlx::query!("SELECT id FROM products WHERE id=? LIMIT 1", product_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap();
+    if prod.is_none() {
+        return (axum::http::StatusCode::NOT_FOUND, "product not found").into_response();
+    }
+
+    let is_active = inp.is_active.unwrap_or(true);
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO product_variants(product_id,sku,option1,option2,option3,price_idr,stock,is_active)
+        VALUES(?,?,?,?,?,?,?,?)
+        "#,
+        product_id,
+        inp.sku.trim(),
+        inp.option1,
+        inp.option2,
+        inp.option3,
+        inp.price_idr,
+        stock,
+        if is_active { 1 } else { 0 }
+    )
+    .execute(&state.db)
+    .await;
+
+    match res {
+        Ok(r) => Json(json!({"ok": true, "id": r.last_insert_id() as i64})).into_response(),
+        Err(e) if is_unique_violation(&e) => {
+            (axum::http::StatusCode::CONFLICT, "sku already used").into_response()
+        }
+        Err(e) => panic!("insert variant: {e:?}"),
+    }
+}
 
+async fn api_admin_variant_update(
+    State(state): State<AppState>,
+    jar: axum_extra::extract::cookie::CookieJar,
+    Path(id): Path<i64>,
+    Json(inp): Json<VariantIn>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    if let Some(resp) = validate_variant(&inp) {
+        return resp;
+    }
+
+    let is_active = inp.is_active.unwrap_or(true);
+    let res = sqlx::query!(
+        r#"
+        UPDATE product_variants
+        SET sku=?, option1=?, option2=?, option3=?, price_idr=?,
+            stock=COALESCE(?, stock), is_active=?
+        WHERE id=?
+        "#,
+        inp.sku.trim(),
+        inp.option1,
+        inp.option2,
+        inp.option3,
+        inp.price_idr,
+        inp.stock,
+        if is_active { 1 } else { 0 },
+        id
+    )
+    .execute(&state.db)
+    .await;
+
+    match res {
+        Ok(r) if r.rows_affected() == 0 => {
+            (axum::http::StatusCode::NOT_FOUND, "variant not found").into_response()
+        }
+        Ok(_) => Json(json!({"ok": true})).into_response(),
+        Err(e) if is_unique_violation(&e) => {
+            (axum::http::StatusCode::CONFLICT, "sku already used").into_response()
+        }
+        Err(e) => panic!("update variant: {e:?}"),
+    }
+}
+
+// varian tidak dihapus fisik: masih direferensikan cart_items/order lama
+async fn api_admin_variant_delete(
+    State(state): State<AppState>,
+    jar: axum_extra::extract::cookie::CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let _ = sqlx::query!("UPDATE product_variants SET is_active=0 WHERE id=?", id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+    Json(json!({"ok": true})).into_response()
+}
+
+async fn api_admin_variant_upload_photo(
+    State(state): State<AppState>,
+    jar: axum_extra::extract::cookie::CookieJar,
+    Path(id): Path<i64>,
+    mut multipart: Multipart,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+
+    let v = sqlx::query!("SELECT id, product_id FROM product_variants WHERE id=? LIMIT 1", id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap();
+    let Some(v) = v else {
+        return (axum::http::StatusCode::NOT_FOUND, "variant not found").into_response();
+    };
+
+    let saved_url = match save_photo(&format!("p{}v{}", v.product_id, v.id), &mut multipart).await {
+        Ok(url) => url,
+        Err(resp) => return resp,
+    };
+
+    if let Some(url) = saved_url {
+        let _ = sqlx::query!("UPDATE product_variants SET image_url=? WHERE id=?", url, id)
+            .execute(&state.db)
+            .await
+            .unwrap();
+        Json(json!({ "ok": true, "image_url": url })).into_response()
+    } else {
+        (axum::http::StatusCode::BAD_REQUEST, "no photo field").into_response()
+    }
+}
+
+/* ==================== helpers lokal ==================== */
+
+// simpan field multipart "photo" ke ../uploads, balikan URL publik (None jika field tidak ada)
+async fn save_photo(prefix: &str, multipart: &mut Multipart) -> Result<Option<String>, Response> {
     while let Some(field) = multipart.next_field().await.unwrap() {
         if field.name() != Some("photo") {
             continue;
@@ -169,12 +525,12 @@ async fn api_admin_product_upload_photo(
         let content_type = field.content_type().map(|m| m.to_string()).unwrap_or_default();
 
         if !content_type.starts_with("image/") {
-            return (axum::http::StatusCode::BAD_REQUEST, "invalid content-type").into_response();
+            return Err((axum::http::StatusCode::BAD_REQUEST, "invalid content-type").into_response());
         }
 
         let bytes = field.bytes().await.unwrap();
         if bytes.len() > 5 * 1024 * 1024 {
-            return (axum::http::StatusCode::PAYLOAD_TOO_LARGE, "file too large").into_response();
+            return Err((axum::http::StatusCode::PAYLOAD_TOO_LARGE, "file too large").into_response());
         }
 
         let ext = filename.rsplit('.').next().unwrap_or("jpg");
@@ -185,7 +541,7 @@ async fn api_admin_product_upload_photo(
             "webp" => "webp",
             _ => "jpg",
         };
-        let new_name = format!("p{}_{}.{}", id, &Uuid::new_v4().to_string()[..8], safe_ext);
+        let new_name = format!("{}_{}.{}", prefix, &Uuid::new_v4().to_string()[..8], safe_ext);
 
         // simpan ke ../uploads (pastikan folder ada)
         fs::create_dir_all("../uploads").await.ok();
@@ -193,19 +549,70 @@ async fn api_admin_product_upload_photo(
         let mut file = fs::File::create(&path).await.unwrap();
         file.write_all(&bytes).await.unwrap();
 
-        let public_url = format!("/uploads/{}", new_name);
-        let _ = sqlx::query!("UPDATE products SET image_url=? WHERE id=?", public_url, id)
-            .execute(&state.db)
-            .await
-            .unwrap();
+        return Ok(Some(format!("/uploads/{}", new_name)));
+    }
+    Ok(None)
+}
 
-        saved_url = Some(public_url);
-        break;
+// normalisasi daftar nama opsi ke 3 slot kolom; None jika lebih dari 3
+fn option_names(names: &[String]) -> Option<[Option<String>; 3]> {
+    if names.len() > 3 {
+        return None;
+    }
+    let mut out: [Option<String>; 3] = Default::default();
+    for (slot, n) in out.iter_mut().zip(names) {
+        let n = n.trim();
+        if !n.is_empty() {
+            *slot = Some(n.to_string());
+        }
     }
+    Some(out)
+}
 
-    if let Some(url) = saved_url {
-        Json(json!({ "ok": true, "image_url": url })).into_response()
-    } else {
-        (axum::http::StatusCode::BAD_REQUEST, "no photo field").into_response()
+// gabungkan nama opsi produk dengan nilai unik dari varian (urut kemunculan)
+fn build_options(names: [Option<String>; 3], variants: &[VariantOut]) -> Vec<OptionOut> {
+    let mut out = vec![];
+    for (i, name) in names.into_iter().enumerate() {
+        let Some(name) = name else { continue };
+        let mut values: Vec<String> = vec![];
+        for v in variants {
+            let val = match i {
+                0 => &v.option1,
+                1 => &v.option2,
+                _ => &v.option3,
+            };
+            if let Some(val) = val {
+                if !values.contains(val) {
+                    values.push(val.clone());
+                }
+            }
+        }
+        out.push(OptionOut { name, values });
+    }
+    out
+}
+
+// "a12 " → Some("A12"), "" → None (hapus kode). Kode dipakai sebagai satu kata di chat.
+fn product_code(raw: &str) -> Result<Option<String>, Response> {
+    let code = raw.trim().to_uppercase();
+    if code.chars().any(char::is_whitespace) {
+        return Err((axum::http::StatusCode::BAD_REQUEST, "code must not contain spaces").into_response());
+    }
+    if code.chars().count() > 32 {
+        return Err((axum::http::StatusCode::BAD_REQUEST, "code too long").into_response());
+    }
+    Ok(Some(code).filter(|c| !c.is_empty()))
+}
+
+fn validate_variant(inp: &VariantIn) -> Option<Response> {
+    if inp.sku.trim().is_empty() {
+        return Some((axum::http::StatusCode::BAD_REQUEST, "sku required").into_response());
+    }
+    if inp.stock.is_some_and(|s| s < 0) {
+        return Some((axum::http::StatusCode::BAD_REQUEST, "stock must be >= 0").into_response());
+    }
+    if inp.price_idr.is_some_and(|p| p < 0) {
+        return Some((axum::http::StatusCode::BAD_REQUEST, "price must be >= 0").into_response());
     }
+    None
 }
@@ -0,0 +1,320 @@
+// server/src/handlers/reconcile.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Rekonsiliasi transfer bank manual: admin upload mutasi rekening (CSV/XLSX),
+// setiap baris kredit dicocokkan ke order 'new' dengan total yang sama persis
+// (total sudah mengandung kode unik, lihat PAYMENT_UNIQUE_CODE).
+
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+
+use axum::{
+    extract::{Multipart, Query, State},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use calamine::{open_workbook_auto_from_rs, Data, Reader};
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, QueryBuilder};
+
+use crate::{
+    ensure_admin, events,
+    handlers::orders::change_status,
+    order_status::{OrderStatus, StatusChangeError},
+    ws_protocol::Body,
+    AppState,
+};
+
+const PROVIDER: &str = "bank_transfer";
+
+// nama kolom nominal yang dikenali (urutan = prioritas; "kredit" menang atas "jumlah")
+const AMOUNT_HEADERS: &[&str] = &["kredit", "credit", "amount", "jumlah", "nominal", "mutasi"];
+const DESC_HEADERS: &[&str] = &["keterangan", "description", "deskripsi", "berita", "remark"];
+
+#[derive(Deserialize)]
+struct ReconcileQuery {
+    // 1 = hanya laporan, tidak mengubah order
+    dry_run: Option<u8>,
+}
+
+#[derive(Serialize)]
+struct RowOut {
+    row: usize, // nomor baris di file (1-based, termasuk header)
+    amount: i64,
+    description: Option<String>,
+    order_ids: Vec<i64>,
+    reason: Option<&'static str>,
+}
+
+#[derive(Serialize, Default)]
+struct ReconcileOut {
+    dry_run: bool,
+    rows: usize,
+    skipped: usize, // baris debit / kosong / bukan angka
+    matched: Vec<RowOut>,
+    unmatched: Vec<RowOut>,
+    ambiguous: Vec<RowOut>,
+}
+
+#[derive(sqlx::FromRow)]
+struct NewOrderRow {
+    id: i64,
+    total: i32,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/api/admin/payments/reconcile", post(api_admin_reconcile))
+}
+
+/* ===================== Handler ===================== */
+
+async fn api_admin_reconcile(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(q): Query<ReconcileQuery>,
+    mut multipart: Multipart,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let dry_run = q.dry_run.unwrap_or(0) == 1;
+
+    // ambil field "file"
+    let mut file: Option<(String, Vec<u8>)> = None;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let fname = field.file_name().unwrap_or("").to_lowercase();
+        match field.bytes().await {
+            Ok(b) => file = Some((fname, b.to_vec())),
+            Err(_) => {
+                return (axum::http::StatusCode::BAD_REQUEST, "upload failed").into_response()
+            }
+        }
+        break;
+    }
+    let Some((fname, bytes)) = file else {
+        return (axum::http::StatusCode::BAD_REQUEST, "file required").into_response();
+    };
+
+    let rows = if fname.ends_with(".xlsx") || fname.ends_with(".xls") {
+        read_xlsx(bytes)
+    } else {
+        read_csv(&bytes)
+    };
+    let rows = match rows {
+        Ok(r) => r,
+        Err(e) => return (axum::http::StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let Some((hdr_idx, amount_col, desc_col)) = find_header(&rows) else {
+        return (axum::http::StatusCode::BAD_REQUEST, "amount column not found").into_response();
+    };
+
+    // baris kredit: (nomor baris, nominal, keterangan)
+    let mut out = ReconcileOut { dry_run, ..Default::default() };
+    let mut credits: Vec<(usize, i64, Option<String>)> = Vec::new();
+    for (i, r) in rows.iter().enumerate().skip(hdr_idx + 1) {
+        out.rows += 1;
+        let cell = r.get(amount_col).map(String::as_str).unwrap_or("");
+        match parse_amount(cell) {
+            Some(a) if a > 0 => {
+                let desc = desc_col
+                    .and_then(|c| r.get(c))
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty());
+                credits.push((i + 1, a, desc));
+            }
+            _ => out.skipped += 1,
+        }
+    }
+
+    // order 'new' yang totalnya muncul di mutasi
+    let mut by_total: HashMap<i64, Vec<i64>> = HashMap::new();
+    let amounts: HashSet<i64> = credits.iter().map(|c| c.1).collect();
+    if !amounts.is_empty() {
+        let mut qb = QueryBuilder::<MySql>::new(
+            "SELECT id, total FROM orders WHERE status='new' AND total IN (",
+        );
+        let mut sep = qb.separated(", ");
+        for a in &amounts {
+            sep.push_bind(*a);
+        }
+        sep.push_unseparated(") ORDER BY id");
+        let found: Vec<NewOrderRow> = qb.build_query_as().fetch_all(&state.db).await.unwrap();
+        for o in found {
+            by_total.entry(o.total as i64).or_default().push(o.id);
+        }
+    }
+
+    // nominal yang muncul lebih dari sekali di file tidak bisa dipastikan milik siapa
+    let mut seen: HashMap<i64, usize> = HashMap::new();
+    for c in &credits {
+        *seen.entry(c.1).or_default() += 1;
+    }
+
+    for (row, amount, description) in credits {
+        let order_ids = by_total.get(&amount).cloned().unwrap_or_default();
+        let mut r = RowOut { row, amount, description, order_ids, reason: None };
+
+        if r.order_ids.is_empty() {
+            r.reason = Some("no new order with this total");
+            out.unmatched.push(r);
+            continue;
+        }
+        if r.order_ids.len() > 1 {
+            r.reason = Some("several new orders share this total");
+            out.ambiguous.push(r);
+            continue;
+        }
+        if seen[&amount] > 1 {
+            r.reason = Some("amount appears more than once in statement");
+            out.ambiguous.push(r);
+            continue;
+        }
+
+        if !dry_run {
+            if let Err(reason) = mark_paid(&state, r.order_ids[0], amount, row).await {
+                r.reason = Some(reason);
+                out.unmatched.push(r);
+                continue;
+            }
+        }
+        out.matched.push(r);
+    }
+
+    Json(out).into_response()
+}
+
+/* ===================== helpers lokal ===================== */
+
+// Catat payment transfer + order → paid. Status order dicek ulang di dalam transaksi
+// (bisa saja sudah dibayar / dibatalkan sejak laporan dibuat).
+async fn mark_paid(
+    state: &AppState,
+    order_id: i64,
+    amount: i64,
+    row: usize,
+) -> Result<(), &'static str> {
+    let mut tx = state.db.begin().await.unwrap();
+
+    let ord = sqlx::query!("SELECT total FROM orders WHERE id=? FOR UPDATE", order_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .unwrap();
+    if ord.map(|o| o.total as i64) != Some(amount) {
+        return Err("order total changed");
+    }
+
+    let note = format!("bank transfer reconciled (statement row {row})");
+    match change_status(&mut tx, order_id, OrderStatus::Paid, None, Some(&note)).await {
+        Ok(_) => {}
+        Err(StatusChangeError::Illegal { .. }) => return Err("order is no longer new"),
+        Err(StatusChangeError::NotFound) => return Err("order not found"),
+    }
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO payments(order_id, provider, provider_ref, method, amount, status, paid_at)
+        VALUES(?,?,?,'transfer',?,'paid',NOW())
+        "#,
+        order_id,
+        PROVIDER,
+        format!("TRF-{order_id}"),
+        amount as i32
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+
+    tx.commit().await.unwrap();
+
+    events::order(state, order_id, Body::OrderUpdate { order_id }).await;
+    Ok(())
+}
+
+fn read_csv(bytes: &[u8]) -> Result<Vec<Vec<String>>, String> {
+    // bank lokal sering ekspor pakai ';'
+    let first = bytes.split(|b| *b == b'\n').next().unwrap_or(&[]);
+    let semi = first.iter().filter(|b| **b == b';').count();
+    let comma = first.iter().filter(|b| **b == b',').count();
+    let delim = if semi > comma { b';' } else { b',' };
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delim)
+        .from_reader(bytes);
+    let mut rows = Vec::new();
+    for rec in rdr.records() {
+        let rec = rec.map_err(|e| format!("invalid csv: {e}"))?;
+        rows.push(rec.iter().map(|s| s.to_string()).collect());
+    }
+    Ok(rows)
+}
+
+fn read_xlsx(bytes: Vec<u8>) -> Result<Vec<Vec<String>>, String> {
+    let mut wb =
+        open_workbook_auto_from_rs(Cursor::new(bytes)).map_err(|e| format!("invalid xlsx: {e}"))?;
+    let range = wb
+        .worksheet_range_at(0)
+        .ok_or("xlsx has no sheet")?
+        .map_err(|e| format!("invalid xlsx: {e}"))?;
+    Ok(range
+        .rows()
+        .map(|r| {
+            r.iter()
+                .map(|c| match c {
+                    // angka tanpa desimal ".0" agar sama dengan parsing CSV
+                    Data::Float(f) if f.fract() == 0.0 => format!("{}", *f as i64),
+                    other => other.to_string(),
+                })
+                .collect()
+        })
+        .collect())
+}
+
+// Cari baris header pertama yang punya kolom nominal → (index baris, kolom nominal, kolom keterangan)
+fn find_header(rows: &[Vec<String>]) -> Option<(usize, usize, Option<usize>)> {
+    let find = |r: &[String], names: &[&str]| {
+        names.iter().find_map(|n| {
+            r.iter().position(|c| {
+                let c = c.trim().to_lowercase();
+                c.contains(n) && !c.contains("debit") && !c.contains("saldo")
+            })
+        })
+    };
+    rows.iter().enumerate().take(20).find_map(|(i, r)| {
+        find(r, AMOUNT_HEADERS).map(|col| (i, col, find(r, DESC_HEADERS)))
+    })
+}
+
+// "Rp 1.250.123,00" / "1,250,123.00 CR" / "150123" → 1250123. Debit ("DB" / minus) → negatif.
+fn parse_amount(s: &str) -> Option<i64> {
+    let up = s.trim().to_uppercase();
+    if up.is_empty() {
+        return None;
+    }
+    let debit = up.ends_with("DB") || up.starts_with('-') || up.starts_with('(');
+
+    let num: String = up.chars().filter(|c| c.is_ascii_digit() || *c == '.' || *c == ',').collect();
+    // pemisah terakhir diikuti 1–2 digit = desimal (sen), buang
+    let int_part = match num.rfind(['.', ',']) {
+        Some(p) if (1..=2).contains(&(num.len() - p - 1)) => &num[..p],
+        _ => num.as_str(),
+    };
+    let digits: String = int_part.chars().filter(|c| c.is_ascii_digit()).collect();
+    let v: i64 = digits.parse().ok()?;
+    Some(if debit { -v } else { v })
+}
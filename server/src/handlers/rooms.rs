@@ -0,0 +1,208 @@
+// server/src/handlers/rooms.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::{delete, get, put},
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{MySql, Pool};
+
+use crate::{ensure_admin, room_roles::RoomRole, AppState};
+
+#[derive(Deserialize)]
+struct RoleIn {
+    // "host" | "moderator"
+    role: RoomRole,
+}
+
+#[derive(Serialize)]
+struct RoleOut {
+    user_id: i64,
+    name: String,
+    role: String,
+    created_at: DateTime<Utc>,
+}
+
+// viewer unik yang sedang menonton (presence dari bus, seluruh instance)
+#[derive(Serialize)]
+struct ViewerOut {
+    // None = belum login (sesi cookie saja)
+    user_id: Option<i64>,
+    name: Option<String>,
+    joined_at: DateTime<Utc>,
+    // tab / koneksi yang terbuka
+    connections: usize,
+    // keranjang terbuka berisi item dari room ini
+    has_cart: bool,
+    // order aktif berisi item dari room ini
+    has_order: bool,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/rooms/:room/viewers", get(api_admin_room_viewers))
+        .route("/api/admin/rooms/:room/roles", get(api_admin_room_roles))
+        .route("/api/admin/rooms/:room/roles/:user_id", put(api_admin_room_role_set))
+        .route("/api/admin/rooms/:room/roles/:user_id", delete(api_admin_room_role_delete))
+}
+
+/* ===================== Handlers ===================== */
+
+async fn api_admin_room_viewers(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let mut viewers = state.bus.viewers(&room).await;
+    viewers.sort_by_key(|v| v.joined_at);
+
+    let ids: Vec<i64> = viewers.iter().filter_map(|v| v.user_id).collect();
+    let (mut names, mut carts, mut orders) = (HashMap::new(), HashSet::new(), HashSet::new());
+    if !ids.is_empty() {
+        let marks = vec!["?"; ids.len()].join(",");
+        let sql = format!("SELECT id, name FROM users WHERE id IN ({marks})");
+        let mut q = sqlx::query_as::<_, (i64, String)>(&sql);
+        for id in &ids {
+            q = q.bind(id);
+        }
+        names = q.fetch_all(&state.db).await.unwrap().into_iter().collect();
+        carts = user_ids_in(
+            &state.db,
+            &format!(
+                "SELECT DISTINCT c.user_id FROM carts c JOIN cart_items ci ON ci.cart_id=c.id \
+                 WHERE ci.room=? AND c.status='open' AND c.user_id IN ({marks})"
+            ),
+            &room,
+            &ids,
+        )
+        .await;
+        orders = user_ids_in(
+            &state.db,
+            &format!(
+                "SELECT DISTINCT o.user_id FROM orders o JOIN cart_items ci ON ci.cart_id=o.cart_id \
+                 WHERE ci.room=? AND o.status NOT IN ('cancelled','deleted') AND o.user_id IN ({marks})"
+            ),
+            &room,
+            &ids,
+        )
+        .await;
+    }
+
+    let rows: Vec<ViewerOut> = viewers
+        .into_iter()
+        .map(|v| ViewerOut {
+            name: v.user_id.and_then(|id| names.get(&id).cloned()),
+            has_cart: v.user_id.is_some_and(|id| carts.contains(&id)),
+            has_order: v.user_id.is_some_and(|id| orders.contains(&id)),
+            user_id: v.user_id,
+            joined_at: v.joined_at,
+            connections: v.connections,
+        })
+        .collect();
+    Json(rows).into_response()
+}
+
+async fn api_admin_room_roles(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let rows = sqlx::query_as!(
+        RoleOut,
+        r#"
+        SELECT rr.user_id, u.name, rr.role, rr.created_at
+        FROM room_roles rr JOIN users u ON u.id = rr.user_id
+        WHERE rr.room=?
+        ORDER BY rr.role, u.name
+        "#,
+        room
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+    Json(rows).into_response()
+}
+
+// Berlaku untuk koneksi WS berikutnya (peran dibaca saat upgrade).
+async fn api_admin_room_role_set(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path((room, user_id)): Path<(String, i64)>,
+    Json(inp): Json<RoleIn>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    if !matches!(inp.role, RoomRole::Host | RoomRole::Moderator) {
+        return (axum::http::StatusCode::BAD_REQUEST, "role must be host|moderator").into_response();
+    }
+    let exists = sqlx::query!("SELECT id FROM users WHERE id=?", user_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap();
+    if exists.is_none() {
+        return (axum::http::StatusCode::NOT_FOUND, "user not found").into_response();
+    }
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO room_roles(room, user_id, role) VALUES(?,?,?)
+        ON DUPLICATE KEY UPDATE role=VALUES(role)
+        "#,
+        room,
+        user_id,
+        inp.role.as_str()
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    Json(json!({"ok": true})).into_response()
+}
+
+async fn api_admin_room_role_delete(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path((room, user_id)): Path<(String, i64)>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let _ = sqlx::query!("DELETE FROM room_roles WHERE room=? AND user_id=?", room, user_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    Json(json!({"ok": true})).into_response()
+}
+
+/* ===================== helpers lokal ===================== */
+
+// `sql` berisi satu `?` untuk room lalu placeholder IN sebanyak `ids`
+async fn user_ids_in(db: &Pool<MySql>, sql: &str, room: &str, ids: &[i64]) -> HashSet<i64> {
+    let mut q = sqlx::query_scalar::<_, i64>(sql).bind(room);
+    for id in ids {
+        q = q.bind(id);
+    }
+    q.fetch_all(db).await.unwrap().into_iter().collect()
+}
@@ -0,0 +1,345 @@
+// server/src/handlers/shipping.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    ensure_admin, get_user_from_cookie,
+    shipping::{self, Destination},
+    vouchers, AppState,
+};
+
+#[derive(Deserialize)]
+struct ZoneIn {
+    name: String,
+    // NULL/kosong = berlaku untuk semua
+    province: Option<String>,
+    city: Option<String>,
+    postcode_prefix: Option<String>,
+    // diisi = ongkir tetap, braket berat diabaikan
+    flat_fee: Option<i32>,
+    // subtotal minimal untuk gratis ongkir
+    free_min_subtotal: Option<i32>,
+    is_active: Option<bool>,
+    #[serde(default)]
+    rates: Vec<RateIn>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct RateIn {
+    // batas atas braket (gram), None = tanpa batas
+    max_weight_grams: Option<i32>,
+    fee: i32,
+}
+
+#[derive(Serialize)]
+struct ZoneOut {
+    id: i64,
+    name: String,
+    province: Option<String>,
+    city: Option<String>,
+    postcode_prefix: Option<String>,
+    flat_fee: Option<i32>,
+    free_min_subtotal: Option<i32>,
+    is_active: bool,
+    rates: Vec<RateIn>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        // Viewer: estimasi ongkir keranjang sebelum checkout
+        .route("/api/shipping/quote", post(api_shipping_quote))
+        // Admin: tabel tarif
+        .route("/api/admin/shipping/zones", get(api_admin_zones_list))
+        .route("/api/admin/shipping/zones", post(api_admin_zone_create))
+        .route("/api/admin/shipping/zones/:id", put(api_admin_zone_update))
+        .route("/api/admin/shipping/zones/:id", delete(api_admin_zone_delete))
+}
+
+/* ===================== Handlers ===================== */
+
+async fn api_shipping_quote(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(dest): Json<Destination>,
+) -> Response {
+    let (_, user) = get_user_from_cookie(&jar, &state).await;
+    let Some(u) = user else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "login first").into_response();
+    };
+
+    let mut conn = state.db.acquire().await.unwrap();
+    let Some(cart) = sqlx::query!(
+        "SELECT id, voucher_id FROM carts WHERE user_id=? AND status='open' LIMIT 1",
+        u.id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .unwrap() else {
+        return (axum::http::StatusCode::BAD_REQUEST, "cart empty").into_response();
+    };
+    let rows = sqlx::query!(
+        r#"
+        SELECT ci.product_id, ci.qty, ci.price_at_add, ci.watch_room, p.weight_grams
+        FROM cart_items ci
+        JOIN products p ON p.id = ci.product_id
+        WHERE ci.cart_id=?
+        "#,
+        cart.id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+    if rows.is_empty() {
+        return (axum::http::StatusCode::BAD_REQUEST, "cart empty").into_response();
+    }
+
+    let mut subtotal: i64 = 0;
+    let mut weight: i64 = 0;
+    let mut lines = vec![];
+    for r in rows {
+        let line = r.qty as i64 * r.price_at_add as i64;
+        subtotal += line;
+        weight += r.qty as i64 * r.weight_grams as i64;
+        lines.push(vouchers::Line { product_id: r.product_id, line_total: line, room: r.watch_room });
+    }
+
+    // potongan voucher dihitung sama seperti checkout (gratis ongkir dari subtotal - diskon)
+    let discount = match cart.voucher_id {
+        None => 0,
+        Some(vid) => match vouchers::evaluate(&mut conn, vid, u.id, &lines, false).await {
+            Ok(a) => a.discount,
+            Err(e) => return e.into_response(),
+        },
+    };
+
+    match shipping::quote(&mut conn, &dest, weight, subtotal - discount).await {
+        Ok(q) => Json(json!({
+            "subtotal": subtotal,
+            "discount": discount,
+            "delivery_fee": q.fee,
+            "total": subtotal - discount + q.fee,
+            "quote": q,
+        }))
+        .into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn api_admin_zones_list(State(state): State<AppState>, jar: CookieJar) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+
+    let zones = sqlx::query!(
+        r#"
+        SELECT id, name, province, city, postcode_prefix, flat_fee, free_min_subtotal, is_active
+        FROM shipping_zones ORDER BY id ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+    let rates = sqlx::query!(
+        r#"
+        SELECT zone_id, max_weight_grams, fee FROM shipping_rates
+        ORDER BY zone_id, max_weight_grams IS NULL, max_weight_grams ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let mut by_zone: HashMap<i64, Vec<RateIn>> = HashMap::new();
+    for r in rates {
+        by_zone.entry(r.zone_id).or_default().push(RateIn {
+            max_weight_grams: r.max_weight_grams,
+            fee: r.fee,
+        });
+    }
+
+    let out: Vec<ZoneOut> = zones
+        .into_iter()
+        .map(|z| ZoneOut {
+            rates: by_zone.remove(&z.id).unwrap_or_default(),
+            id: z.id,
+            name: z.name,
+            province: z.province,
+            city: z.city,
+            postcode_prefix: z.postcode_prefix,
+            flat_fee: z.flat_fee,
+            free_min_subtotal: z.free_min_subtotal,
+            is_active: z.is_active != 0,
+        })
+        .collect();
+
+    Json(out).into_response()
+}
+
+async fn api_admin_zone_create(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(inp): Json<ZoneIn>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    if let Some(resp) = validate_zone(&inp) {
+        return resp;
+    }
+
+    let mut tx = state.db.begin().await.unwrap();
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO shipping_zones(name, province, city, postcode_prefix, flat_fee, free_min_subtotal, is_active)
+        VALUES(?,?,?,?,?,?,?)
+        "#,
+        inp.name.trim(),
+        blank_to_none(&inp.province),
+        blank_to_none(&inp.city),
+        blank_to_none(&inp.postcode_prefix),
+        inp.flat_fee,
+        inp.free_min_subtotal,
+        if inp.is_active.unwrap_or(true) { 1 } else { 0 }
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+    let id = res.last_insert_id() as i64;
+
+    replace_rates(&mut tx, id, &inp.rates).await;
+    tx.commit().await.unwrap();
+
+    Json(json!({"ok": true, "id": id})).into_response()
+}
+
+// Ubah zona; braket berat diganti seluruhnya dengan `rates` dari request.
+async fn api_admin_zone_update(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Json(inp): Json<ZoneIn>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    if let Some(resp) = validate_zone(&inp) {
+        return resp;
+    }
+
+    let mut tx = state.db.begin().await.unwrap();
+    let res = sqlx::query!(
+        r#"
+        UPDATE shipping_zones
+        SET name=?, province=?, city=?, postcode_prefix=?, flat_fee=?, free_min_subtotal=?, is_active=?
+        WHERE id=?
+        "#,
+        inp.name.trim(),
+        blank_to_none(&inp.province),
+        blank_to_none(&inp.city),
+        blank_to_none(&inp.postcode_prefix),
+        inp.flat_fee,
+        inp.free_min_subtotal,
+        if inp.is_active.unwrap_or(true) { 1 } else { 0 },
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+    if res.rows_affected() == 0 {
+        let exists = sqlx::query!("SELECT id FROM shipping_zones WHERE id=?", id)
+            .fetch_optional(&mut *tx)
+            .await
+            .unwrap();
+        if exists.is_none() {
+            return (axum::http::StatusCode::NOT_FOUND, "not found").into_response();
+        }
+    }
+
+    replace_rates(&mut tx, id, &inp.rates).await;
+    tx.commit().await.unwrap();
+
+    Json(json!({"ok": true})).into_response()
+}
+
+async fn api_admin_zone_delete(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    // shipping_rates ikut terhapus (ON DELETE CASCADE)
+    let res = sqlx::query!("DELETE FROM shipping_zones WHERE id=?", id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    if res.rows_affected() == 0 {
+        return (axum::http::StatusCode::NOT_FOUND, "not found").into_response();
+    }
+    Json(json!({"ok": true})).into_response()
+}
+
+/* ===================== helpers lokal ===================== */
+
+fn validate_zone(inp: &ZoneIn) -> Option<Response> {
+    let bad = |msg: &'static str| Some((axum::http::StatusCode::BAD_REQUEST, msg).into_response());
+
+    if inp.name.trim().is_empty() {
+        return bad("name required");
+    }
+    if inp.flat_fee.is_some_and(|f| f < 0) || inp.free_min_subtotal.is_some_and(|m| m < 0) {
+        return bad("fees must be >= 0");
+    }
+    if inp.flat_fee.is_none() && inp.rates.is_empty() {
+        return bad("flat_fee or rates required");
+    }
+    if inp.rates.iter().any(|r| r.fee < 0 || r.max_weight_grams.is_some_and(|w| w <= 0)) {
+        return bad("rate fee must be >= 0 and max_weight_grams > 0");
+    }
+    if inp.rates.iter().filter(|r| r.max_weight_grams.is_none()).count() > 1 {
+        return bad("only one open-ended rate allowed");
+    }
+    None
+}
+
+fn blank_to_none(s: &Option<String>) -> Option<&str> {
+    s.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+async fn replace_rates(tx: &mut sqlx::Transaction<'_, sqlx::MySql>, zone_id: i64, rates: &[RateIn]) {
+    let _ = sqlx::query!("DELETE FROM shipping_rates WHERE zone_id=?", zone_id)
+        .execute(&mut **tx)
+        .await
+        .unwrap();
+    for r in rates {
+        let _ = sqlx::query!(
+            "INSERT INTO shipping_rates(zone_id, max_weight_grams, fee) VALUES(?,?,?)",
+            zone_id,
+            r.max_weight_grams,
+            r.fee
+        )
+        .execute(&mut **tx)
+        .await
+        .unwrap();
+    }
+}
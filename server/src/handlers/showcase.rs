@@ -0,0 +1,75 @@
+// server/src/handlers/showcase.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Etalase live lewat REST (sama dengan perintah WS `pin` / `unpin` dari host).
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{ensure_admin, showcase, AppState};
+
+#[derive(Deserialize)]
+struct PinIn {
+    product_id: i64,
+    live_price: Option<i32>,
+    // hitung mundur (detik)
+    seconds: Option<u32>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/rooms/:room/pin", get(api_room_pin))
+        .route("/api/admin/rooms/:room/pin", post(api_admin_room_pin))
+        .route("/api/admin/rooms/:room/pin", delete(api_admin_room_unpin))
+}
+
+/* ===================== Handlers ===================== */
+
+// Publik: produk yang sedang di-pin (null bila tidak ada).
+async fn api_room_pin(State(state): State<AppState>, Path(room): Path<String>) -> Response {
+    Json(showcase::current(&state.db, &room).await).into_response()
+}
+
+async fn api_admin_room_pin(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+    Json(inp): Json<PinIn>,
+) -> Response {
+    let Some(admin) = ensure_admin(&jar, &state).await else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    };
+    match showcase::pin(&state, &room, Some(admin.id), inp.product_id, inp.live_price, inp.seconds).await {
+        Ok(pin) => Json(pin).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn api_admin_room_unpin(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(room): Path<String>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    match showcase::unpin(&state, &room).await {
+        Ok(()) => Json(json!({"ok": true})).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,316 @@
+// server/src/handlers/streams.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Jadwal & siklus sesi siaran: daftar publik "live sekarang / akan datang",
+// admin menjadwalkan, memulai, mengakhiri, dan melihat omzet per stream.
+// Lihat streams.rs untuk atribusi keranjang / order.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    ensure_admin,
+    streams::{self, StreamOut},
+    AppState,
+};
+
+#[derive(Deserialize)]
+struct StreamIn {
+    room: String,
+    title: String,
+    host_user_id: Option<i64>,
+    scheduled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct StreamsQuery {
+    room: Option<String>,
+    // "scheduled" | "live" | "ended"
+    status: Option<String>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct StreamRow {
+    id: i64,
+    room: String,
+    title: String,
+    host: Option<String>,
+    scheduled_at: Option<DateTime<Utc>>,
+    started_at: Option<DateTime<Utc>>,
+    ended_at: Option<DateTime<Utc>>,
+    status: String,
+    // order yang tidak batal & omzetnya (orders.stream_id)
+    orders: i64,
+    revenue: i64,
+}
+
+#[derive(Serialize)]
+struct StreamStats {
+    // baris keranjang yang ditambahkan selama stream live
+    cart_items: i64,
+    cart_qty: i64,
+    orders: i64,
+    // total order yang tidak batal
+    revenue: i64,
+    // order yang sudah dibayar (paid / shipped / done)
+    paid_orders: i64,
+    paid_revenue: i64,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/streams", get(api_streams_public))
+        .route("/api/admin/streams", get(api_admin_streams_list))
+        .route("/api/admin/streams", post(api_admin_stream_create))
+        .route("/api/admin/streams/:id", get(api_admin_stream_detail))
+        .route("/api/admin/streams/:id/start", post(api_admin_stream_start))
+        .route("/api/admin/streams/:id/stop", post(api_admin_stream_stop))
+}
+
+/* ===================== Handlers ===================== */
+
+// Publik: stream yang sedang live + jadwal terdekat.
+async fn api_streams_public(State(state): State<AppState>) -> Response {
+    let live = sqlx::query_as!(
+        StreamOut,
+        r#"
+        SELECT s.id, s.room, s.title, s.host_user_id, u.name AS `host?`,
+               s.scheduled_at, s.started_at, s.ended_at, s.status
+        FROM streams s LEFT JOIN users u ON u.id = s.host_user_id
+        WHERE s.status='live'
+        ORDER BY s.started_at DESC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let upcoming = sqlx::query_as!(
+        StreamOut,
+        r#"
+        SELECT s.id, s.room, s.title, s.host_user_id, u.name AS `host?`,
+               s.scheduled_at, s.started_at, s.ended_at, s.status
+        FROM streams s LEFT JOIN users u ON u.id = s.host_user_id
+        WHERE s.status='scheduled' AND (s.scheduled_at IS NULL OR s.scheduled_at >= NOW() - INTERVAL 1 DAY)
+        ORDER BY s.scheduled_at IS NULL, s.scheduled_at ASC, s.id ASC
+        LIMIT 50
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    Json(json!({ "live": live, "upcoming": upcoming })).into_response()
+}
+
+// Terbaru dulu; total data lewat header X-Total-Count.
+async fn api_admin_streams_list(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(q): Query<StreamsQuery>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let room = q.room.as_deref().filter(|r| !r.is_empty());
+    let status = match q.status.as_deref().filter(|s| !s.is_empty()) {
+        None => None,
+        Some(s @ ("scheduled" | "live" | "ended")) => Some(s),
+        Some(_) => return (axum::http::StatusCode::BAD_REQUEST, "invalid status").into_response(),
+    };
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    let page = q.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * limit;
+
+    let total = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS n FROM streams
+        WHERE (? IS NULL OR room = ?) AND (? IS NULL OR status = ?)
+        "#,
+        room,
+        room,
+        status,
+        status
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap()
+    .n;
+
+    let rows = sqlx::query_as!(
+        StreamRow,
+        r#"
+        SELECT s.id, s.room, s.title, u.name AS `host?`, s.scheduled_at, s.started_at,
+               s.ended_at, s.status,
+               CAST(COUNT(o.id) AS SIGNED) AS `orders!: i64`,
+               CAST(COALESCE(SUM(o.total), 0) AS SIGNED) AS `revenue!: i64`
+        FROM streams s
+        LEFT JOIN users u ON u.id = s.host_user_id
+        LEFT JOIN orders o ON o.stream_id = s.id AND o.status NOT IN ('cancelled','deleted')
+        WHERE (? IS NULL OR s.room = ?) AND (? IS NULL OR s.status = ?)
+        GROUP BY s.id
+        ORDER BY s.id DESC
+        LIMIT ? OFFSET ?
+        "#,
+        room,
+        room,
+        status,
+        status,
+        limit,
+        offset
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+    (headers, Json(rows)).into_response()
+}
+
+async fn api_admin_stream_create(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(inp): Json<StreamIn>,
+) -> Response {
+    let Some(admin) = ensure_admin(&jar, &state).await else {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    };
+    // room dipakai di URL /live/:room & kanal WS: alnum, '_' atau '-'
+    let room = inp.room.trim();
+    if room.is_empty()
+        || room.len() > 64
+        || !room.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return (axum::http::StatusCode::BAD_REQUEST, "invalid room").into_response();
+    }
+    let title = inp.title.trim();
+    if title.is_empty() || title.chars().count() > 160 {
+        return (axum::http::StatusCode::BAD_REQUEST, "title must be 1-160 chars").into_response();
+    }
+    if let Some(host_id) = inp.host_user_id {
+        let exists = sqlx::query!("SELECT id FROM users WHERE id=?", host_id)
+            .fetch_optional(&state.db)
+            .await
+            .unwrap();
+        if exists.is_none() {
+            return (axum::http::StatusCode::BAD_REQUEST, "host user not found").into_response();
+        }
+    }
+
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO streams(room, title, host_user_id, scheduled_at, created_by)
+        VALUES(?,?,?,?,?)
+        "#,
+        room,
+        title,
+        inp.host_user_id,
+        inp.scheduled_at,
+        admin.id
+    )
+    .execute(&state.db)
+    .await
+    .unwrap()
+    .last_insert_id() as i64;
+
+    Json(streams::get(&state.db, id).await).into_response()
+}
+
+// Detail stream + ringkasan penjualan yang diatribusikan.
+async fn api_admin_stream_detail(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let Some(stream) = streams::get(&state.db, id).await else {
+        return (axum::http::StatusCode::NOT_FOUND, "stream not found").into_response();
+    };
+
+    let cart = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS `n!: i64`, CAST(COALESCE(SUM(qty), 0) AS SIGNED) AS `qty!: i64`
+        FROM cart_items WHERE stream_id=?
+        "#,
+        id
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap();
+
+    let orders = sqlx::query!(
+        r#"
+        SELECT CAST(COUNT(*) AS SIGNED) AS `orders!: i64`,
+               CAST(COALESCE(SUM(total), 0) AS SIGNED) AS `revenue!: i64`,
+               CAST(COALESCE(SUM(status IN ('paid','shipped','done')), 0) AS SIGNED) AS `paid_orders!: i64`,
+               CAST(COALESCE(SUM(IF(status IN ('paid','shipped','done'), total, 0)), 0) AS SIGNED)
+               AS `paid_revenue!: i64`
+        FROM orders
+        WHERE stream_id=? AND status NOT IN ('cancelled','deleted')
+        "#,
+        id
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap();
+
+    let stats = StreamStats {
+        cart_items: cart.n,
+        cart_qty: cart.qty,
+        orders: orders.orders,
+        revenue: orders.revenue,
+        paid_orders: orders.paid_orders,
+        paid_revenue: orders.paid_revenue,
+    };
+    Json(json!({ "stream": stream, "stats": stats })).into_response()
+}
+
+async fn api_admin_stream_start(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    match streams::start(&state, id).await {
+        Ok(s) => Json(s).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn api_admin_stream_stop(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    match streams::stop(&state, id).await {
+        Ok(s) => Json(s).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
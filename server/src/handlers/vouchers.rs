@@ -0,0 +1,258 @@
+// server/src/handlers/vouchers.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{ensure_admin, is_unique_violation, AppState};
+
+#[derive(Deserialize)]
+struct VoucherIn {
+    code: String,
+    // "percent" | "fixed"
+    kind: String,
+    // persen (1..100) atau rupiah
+    value: i32,
+    min_subtotal: Option<i32>,
+    // batas potongan untuk voucher persen
+    max_discount: Option<i32>,
+    // total pemakaian semua user / per user (None = tanpa batas)
+    usage_limit: Option<i32>,
+    per_user_limit: Option<i32>,
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+    // hanya untuk produk / room live tertentu
+    product_id: Option<i64>,
+    room: Option<String>,
+    is_active: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct VoucherOut {
+    id: i64,
+    code: String,
+    kind: String,
+    value: i32,
+    min_subtotal: i32,
+    max_discount: Option<i32>,
+    usage_limit: Option<i32>,
+    per_user_limit: Option<i32>,
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+    product_id: Option<i64>,
+    room: Option<String>,
+    is_active: bool,
+    // order aktif (bukan cancelled/deleted) yang memakai voucher ini
+    used: i64,
+    created_at: DateTime<Utc>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/vouchers", get(api_admin_vouchers_list))
+        .route("/api/admin/vouchers", post(api_admin_voucher_create))
+        .route("/api/admin/vouchers/:id", put(api_admin_voucher_update))
+        .route("/api/admin/vouchers/:id", delete(api_admin_voucher_delete))
+}
+
+/* ===================== Handlers ===================== */
+
+async fn api_admin_vouchers_list(State(state): State<AppState>, jar: CookieJar) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT v.id, v.code, v.kind, v.value, v.min_subtotal, v.max_discount,
+               v.usage_limit, v.per_user_limit, v.starts_at, v.ends_at,
+               v.product_id, v.room, v.is_active, v.created_at,
+               (SELECT COUNT(*) FROM orders o
+                 WHERE o.voucher_id = v.id AND o.status NOT IN ('cancelled','deleted')) AS `used!: i64`
+        FROM vouchers v
+        ORDER BY v.id DESC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+
+    let out: Vec<VoucherOut> = rows
+        .into_iter()
+        .map(|r| VoucherOut {
+            id: r.id,
+            code: r.code,
+            kind: r.kind,
+            value: r.value,
+            min_subtotal: r.min_subtotal,
+            max_discount: r.max_discount,
+            usage_limit: r.usage_limit,
+            per_user_limit: r.per_user_limit,
+            starts_at: r.starts_at,
+            ends_at: r.ends_at,
+            product_id: r.product_id,
+            room: r.room,
+            is_active: r.is_active != 0,
+            used: r.used,
+            created_at: r.created_at,
+        })
+        .collect();
+
+    Json(out).into_response()
+}
+
+async fn api_admin_voucher_create(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(inp): Json<VoucherIn>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    if let Some(resp) = validate_voucher(&inp) {
+        return resp;
+    }
+
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO vouchers(code, kind, value, min_subtotal, max_discount, usage_limit,
+                             per_user_limit, starts_at, ends_at, product_id, room, is_active)
+        VALUES(?,?,?,?,?,?,?,?,?,?,?,?)
+        "#,
+        inp.code.trim().to_uppercase(),
+        inp.kind,
+        inp.value,
+        inp.min_subtotal.unwrap_or(0),
+        inp.max_discount,
+        inp.usage_limit,
+        inp.per_user_limit,
+        inp.starts_at,
+        inp.ends_at,
+        inp.product_id,
+        inp.room.as_deref().map(str::trim).filter(|r| !r.is_empty()),
+        if inp.is_active.unwrap_or(true) { 1 } else { 0 }
+    )
+    .execute(&state.db)
+    .await;
+
+    match res {
+        Ok(r) => Json(json!({"ok": true, "id": r.last_insert_id() as i64})).into_response(),
+        Err(e) if is_unique_violation(&e) => {
+            (axum::http::StatusCode::CONFLICT, "code already exists").into_response()
+        }
+        Err(e) => panic!("insert voucher: {e:?}"),
+    }
+}
+
+async fn api_admin_voucher_update(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Json(inp): Json<VoucherIn>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    if let Some(resp) = validate_voucher(&inp) {
+        return resp;
+    }
+
+    let res = sqlx::query!(
+        r#"
+        UPDATE vouchers
+        SET code=?, kind=?, value=?, min_subtotal=?, max_discount=?, usage_limit=?,
+            per_user_limit=?, starts_at=?, ends_at=?, product_id=?, room=?, is_active=?
+        WHERE id=?
+        "#,
+        inp.code.trim().to_uppercase(),
+        inp.kind,
+        inp.value,
+        inp.min_subtotal.unwrap_or(0),
+        inp.max_discount,
+        inp.usage_limit,
+        inp.per_user_limit,
+        inp.starts_at,
+        inp.ends_at,
+        inp.product_id,
+        inp.room.as_deref().map(str::trim).filter(|r| !r.is_empty()),
+        if inp.is_active.unwrap_or(true) { 1 } else { 0 },
+        id
+    )
+    .execute(&state.db)
+    .await;
+
+    match res {
+        Ok(_) => Json(json!({"ok": true})).into_response(),
+        Err(e) if is_unique_violation(&e) => {
+            (axum::http::StatusCode::CONFLICT, "code already exists").into_response()
+        }
+        Err(e) => panic!("update voucher: {e:?}"),
+    }
+}
+
+// Soft delete: order lama tetap menunjuk voucher yang dipakai.
+async fn api_admin_voucher_delete(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    if ensure_admin(&jar, &state).await.is_none() {
+        return (axum::http::StatusCode::UNAUTHORIZED, "admin only").into_response();
+    }
+    let _ = sqlx::query!("UPDATE vouchers SET is_active=0 WHERE id=?", id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    // lepas dari cart yang belum checkout
+    let _ = sqlx::query!("UPDATE carts SET voucher_id=NULL WHERE voucher_id=? AND status='open'", id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    Json(json!({"ok": true})).into_response()
+}
+
+/* ===================== helpers lokal ===================== */
+
+fn validate_voucher(inp: &VoucherIn) -> Option<Response> {
+    let bad = |msg: &'static str| Some((axum::http::StatusCode::BAD_REQUEST, msg).into_response());
+
+    let code = inp.code.trim();
+    let code_ok = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if code.is_empty() || code.len() > 32 || !code.chars().all(code_ok) {
+        return bad("code must be 1-32 chars [A-Z0-9_-]");
+    }
+    match inp.kind.as_str() {
+        "percent" if !(1..=100).contains(&inp.value) => return bad("percent value must be 1..100"),
+        "fixed" if inp.value <= 0 => return bad("fixed value must be > 0"),
+        "percent" | "fixed" => {}
+        _ => return bad("kind must be percent|fixed"),
+    }
+    if inp.min_subtotal.is_some_and(|v| v < 0) || inp.max_discount.is_some_and(|v| v <= 0) {
+        return bad("min_subtotal must be >= 0, max_discount > 0");
+    }
+    if inp.usage_limit.is_some_and(|v| v <= 0) || inp.per_user_limit.is_some_and(|v| v <= 0) {
+        return bad("limits must be > 0");
+    }
+    if let (Some(a), Some(b)) = (inp.starts_at, inp.ends_at) {
+        if a >= b {
+            return bad("starts_at must be before ends_at");
+        }
+    }
+    None
+}
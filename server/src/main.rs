@@ -10,23 +10,54 @@ LinkedIn: https://id.linkedin.com/in/kukuhtw
 */
 
 
+mod analytics;
+mod auctions;
+mod bus;
+mod chat;
+mod events;
+mod claims;
+mod flash_sales;
 mod handlers; // server/src/handlers/
+mod moderation;
+mod order_status;
+mod payments;
+mod resume;
+mod room_roles;
+mod shipping;
+mod showcase;
+mod streams;
+mod vouchers;
+mod ws_conn;
+mod ws_protocol;
 use handlers::{
     admin as admin_handlers,
     orders as orders_handlers,
     products as products_handlers,
     users as users_handlers,
     cart as cart_handlers,
+    payments as payments_handlers,
+    reconcile as reconcile_handlers,
+    shipping as shipping_handlers,
+    vouchers as vouchers_handlers,
+    rooms as rooms_handlers,
+    chat as chat_handlers,
+    moderation as moderation_handlers,
+    claims as claims_handlers,
+    showcase as showcase_handlers,
+    auctions as auctions_handlers,
+    flash_sales as flash_sales_handlers,
+    streams as streams_handlers,
+    analytics as analytics_handlers,
 };
 
-use std::{collections::HashMap, env, sync::Arc};
+use std::{env, sync::Arc};
 
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        Path, State,
+        Path, Query, State,
     },
-     response::{Html, IntoResponse, Redirect},
+     response::{Html, IntoResponse, Redirect, Response},
     routing::get,
     Router,
 };
@@ -34,19 +65,35 @@ use axum_extra::extract::cookie::CookieJar;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use sqlx::{mysql::MySqlPoolOptions, MySql, Pool};
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::broadcast;
 use tower_http::services::ServeDir;
 
+use bus::{Presence, Topic};
+use room_roles::RoomRole;
+use ws_protocol::{Body, ClientMsg, Envelope, ProtoError, Sender};
+
 
 
 #[derive(Clone)]
 pub(crate) struct AppState {
-    pub rooms: Arc<RwLock<HashMap<String, broadcast::Sender<String>>>>,
     pub db: Pool<MySql>,
     pub cookie_name: String,
-    // + NEW: kanal notifikasi global (order, dsb)
-    pub notify_tx: broadcast::Sender<String>,
-     pub viewer_counts: Arc<RwLock<HashMap<String, usize>>>, // NEW
+    // fan-out room / staff / admin / user + hitungan viewer (lihat BUS, bus/)
+    pub bus: Arc<dyn bus::Bus>,
+    // payment gateway aktif (lihat PAYMENT_PROVIDER)
+    pub payments: Arc<dyn payments::PaymentProvider>,
+    // tambahkan kode unik 1..999 ke total order (transfer bank manual, lihat PAYMENT_UNIQUE_CODE)
+    pub unique_payment_code: bool,
+    // jumlah pesan chat terakhir yang dikirim ke koneksi baru (CHAT_BACKFILL)
+    pub chat_backfill: i64,
+    // mute/ban, slow mode, filter kata per room
+    pub moderation: Arc<moderation::Moderation>,
+    // keyword comment-to-buy (CLAIM_KEYWORDS); kosong = mati
+    pub claim_keywords: Vec<String>,
+    // ping, batas idle & antrian keluar koneksi WS (WS_PING_SECS, WS_IDLE_SECS, WS_QUEUE)
+    pub ws_limits: ws_conn::Limits,
+    // sesi WS room yang menunggu resume (RESUME_GRACE_SECS)
+    pub resume: Arc<resume::Resume>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -58,17 +105,6 @@ pub(crate) struct User {
     pub phone: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "t")]
-enum WsMsg {
-    #[serde(rename = "f")]
-    Frame { room: String, d: String },
-    #[serde(rename = "c")]
-    Chat { room: String, user: String, text: String },
-    #[serde(rename = "sys")]
-    Sys { room: String, text: String },
-}
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
@@ -82,18 +118,38 @@ async fn main() -> anyhow::Result<()> {
         .connect(&database_url)
         .await?;
 
-    // + NEW: buat channel global notifikasi
-    let (notify_tx, _rx) = broadcast::channel::<String>(256);
-
 
     let state = AppState {
-        rooms: Arc::new(RwLock::new(HashMap::new())),
         db,
         cookie_name: env::var("SESSION_COOKIE_NAME").unwrap_or_else(|_| "sid".into()),
-        notify_tx, // + NEW
-        viewer_counts: Arc::new(RwLock::new(HashMap::new())), // NEW
+        bus: bus::from_env().await,
+        payments: payments::provider_from_env(),
+        unique_payment_code: matches!(
+            env::var("PAYMENT_UNIQUE_CODE").as_deref(),
+            Ok("1") | Ok("true")
+        ),
+        chat_backfill: env::var("CHAT_BACKFILL")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(50),
+        moderation: Arc::new(moderation::Moderation::new()),
+        claim_keywords: claims::keywords_from_env(),
+        ws_limits: ws_conn::limits_from_env(),
+        resume: Arc::new(resume::Resume::new(resume::grace_secs_from_env())),
     };
 
+    // lelang yang masih berjalan saat server mati: pasang ulang timer penutupnya
+    auctions::resume(&state).await;
+    // event flash sale mulai / selesai
+    flash_sales::spawn_ticker(state.clone());
+    // cache moderasi + kick dari instance lain
+    moderation::spawn_sync(state.clone()).await;
+    // sesi WS yang di-resume di instance lain
+    resume::spawn_sync(state.clone()).await;
+    // analitik: cuplikan berkala (+ menutup sesi tonton milik instance yang sudah mati)
+    analytics::spawn_sampler(state.clone(), analytics::sample_secs_from_env());
+
+    let bus_name = state.bus.name();
     let app = Router::new()
         // pages & ws
         .route("/", get(index))
@@ -105,13 +161,26 @@ async fn main() -> anyhow::Result<()> {
         .merge(products_handlers::routes())
         .merge(orders_handlers::routes())
         .merge(cart_handlers::routes())
+        .merge(payments_handlers::routes())
+        .merge(reconcile_handlers::routes())
+        .merge(shipping_handlers::routes())
+        .merge(vouchers_handlers::routes())
+        .merge(rooms_handlers::routes())
+        .merge(chat_handlers::routes())
+        .merge(moderation_handlers::routes())
+        .merge(claims_handlers::routes())
+        .merge(showcase_handlers::routes())
+        .merge(auctions_handlers::routes())
+        .merge(flash_sales_handlers::routes())
+        .merge(streams_handlers::routes())
+        .merge(analytics_handlers::routes())
         // static + uploads
         .nest_service("/static", ServeDir::new("../webapp"))
         .nest_service("/uploads", ServeDir::new("../uploads"))
         .with_state(state);
 
     let (listener, pretty_addr) = bind_with_fallback().await;
-    println!("▶ serving at {pretty_addr}  (index di /static/index.html)");
+    println!("▶ serving at {pretty_addr}  (index di /static/index.html, bus {bus_name})");
     axum::serve(listener, app).await?;
     Ok(())
 }
@@ -167,6 +236,10 @@ pub(crate) async fn get_user_from_cookie(
     (jar.clone(), None)
 }
 
+pub(crate) fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error().is_some_and(|d| d.is_unique_violation())
+}
+
 pub(crate) async fn ensure_admin(jar: &CookieJar, state: &AppState) -> Option<User> {
     let (_, u) = get_user_from_cookie(jar, state).await;
     if let Some(u) = u {
@@ -177,100 +250,395 @@ pub(crate) async fn ensure_admin(jar: &CookieJar, state: &AppState) -> Option<Us
     None
 }
 
+/// Kirim event ke semua koneksi room (di instance mana pun).
+pub(crate) async fn room_send(state: &AppState, room: &str, body: Body) {
+    room_publish(state, room, Envelope::new(Some(room), None, body)).await;
+}
+
+/// Kirim ke topik room dengan nomor urut (diberi bus); tersimpan di buffer replay (resume).
+pub(crate) async fn room_publish(state: &AppState, room: &str, env: Envelope) {
+    state.bus.publish_replayable(room, env.to_json()).await;
+}
+
+/// Kirim event ke host/moderator room yang sedang terhubung.
+pub(crate) async fn staff_send(state: &AppState, room: &str, body: Body) {
+    let topic = Topic::Staff(room.to_string());
+    state.bus.publish(&topic, ws_protocol::event(Some(room), body)).await;
+}
+
 /* ===================== WebSocket Streaming ===================== */
 
+// `/ws/:room?resume=<token>&since=<seq>`: sambung ulang sesi lama (lihat resume.rs)
+#[derive(Deserialize)]
+struct ResumeQuery {
+    resume: Option<String>,
+    since: Option<u64>,
+}
+
 async fn ws_handler(
     State(state): State<AppState>,
     Path(room): Path<String>,
+    Query(rq): Query<ResumeQuery>,
+    jar: CookieJar,
     ws: WebSocketUpgrade,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state, room))
+) -> Response {
+    // user & peran ditentukan sekali saat upgrade, dipakai selama koneksi hidup
+    let (_, user) = get_user_from_cookie(&jar, &state).await;
+    if let Some(u) = &user {
+        if let Some(until) = state.moderation.banned_until(&state.db, &room, u.id).await {
+            let msg = format!("banned from this room until {}", until.to_rfc3339());
+            return (axum::http::StatusCode::FORBIDDEN, msg).into_response();
+        }
+    }
+    let role = room_roles::resolve(&state.db, user.as_ref(), &room).await;
+    let is_admin = user.as_ref().is_some_and(|u| u.role == "admin");
+    let sender = Sender {
+        user_id: user.as_ref().map(|u| u.id),
+        name: user.map(|u| u.name),
+        role,
+    };
+    let sid = jar.get(&state.cookie_name).map(|c| c.value().to_string());
+    let viewer_key = analytics::viewer_key(sender.user_id, sid.as_deref());
+    // batas ukuran ditegakkan sebelum pesan di-buffer penuh (parse_client hanya pengaman kedua)
+    ws.max_message_size(ws_protocol::MAX_MESSAGE)
+        .max_frame_size(ws_protocol::MAX_MESSAGE)
+        .on_upgrade(move |socket| handle_socket(socket, state, room, sender, is_admin, viewer_key, rq))
+        .into_response()
 }
 
-async fn handle_socket(socket: WebSocket, state: AppState, room: String) {
-    // pilih channel room / global
-    let tx = if room == "_events" {
-        state.notify_tx.clone()
+async fn handle_socket(
+    socket: WebSocket,
+    state: AppState,
+    room: String,
+    sender: Sender,
+    is_admin: bool,
+    viewer_key: String,
+    rq: ResumeQuery,
+) {
+    // === Viewer counter (untuk room selain _events)
+    let is_view_room = room != "_events";
+    let limits = state.ws_limits;
+
+    // topik tujuan kiriman koneksi ini: room / admin (kiriman host lewat _events)
+    let topic = if is_view_room { Topic::Room(room.clone()) } else { Topic::Admin };
+    let frame_topic = if is_view_room { Topic::Frames(room.clone()) } else { Topic::Admin };
+
+    // topik yang diterima: room + frame + event lintas room; _events: admin menerima
+    // topik admin, user login hanya topik order miliknya
+    let rx = if is_view_room || is_admin { Some(state.bus.subscribe(&topic).await) } else { None };
+    let frame_rx = if is_view_room { Some(state.bus.subscribe(&frame_topic).await) } else { None };
+    let all_rx = if is_view_room { Some(state.bus.subscribe(&Topic::AllRooms).await) } else { None };
+    // host/moderator juga menerima kanal staff room
+    let staff_topic = Topic::Staff(room.clone());
+    let staff_rx = if is_view_room && matches!(sender.role, RoomRole::Host | RoomRole::Moderator) {
+        Some(state.bus.subscribe(&staff_topic).await)
     } else {
-        let mut rooms = state.rooms.write().await;
-        rooms.entry(room.clone())
-            .or_insert_with(|| broadcast::channel::<String>(512).0)
-            .clone()
+        None
+    };
+    let user_topic = sender.user_id.filter(|_| !is_view_room).map(Topic::User);
+    let user_rx = match &user_topic {
+        Some(t) => Some(state.bus.subscribe(t).await),
+        None => None,
     };
 
-    let mut rx = tx.subscribe();
+    // sambung ulang sesi lama: tanpa viewer_join / sesi tonton baru
+    let resumed = match (&rq.resume, is_view_room) {
+        (Some(token), true) => resume::claim(&state, token, &room, sender.user_id).await,
+        _ => None,
+    };
 
-    // === Viewer counter (untuk room selain _events)
-    let is_view_room = room != "_events";
-    if is_view_room {
-        // increment
-        {
-            let mut vc = state.viewer_counts.write().await;
-            let c = vc.entry(room.clone()).or_default();
-            *c += 1;
+    // mulai dari sini semua jalur keluar (termasuk panic) lewat Drop guard
+    let mut guard = ws_conn::ConnGuard {
+        state: state.clone(),
+        room: room.clone(),
+        user_id: sender.user_id,
+        presence: None,
+        view_id: None,
+        resume: (is_view_room && state.resume.enabled()).then(resume::new_token),
+        topics: [Some(topic.clone()), Some(frame_topic.clone()), Some(Topic::AllRooms), Some(staff_topic)]
+            .into_iter()
+            .chain([user_topic])
+            .flatten()
+            .collect(),
+        tasks: Vec::new(),
+    };
+    if let Some(r) = &resumed {
+        guard.view_id = r.view_id;
+        if !r.held {
+            // sesi diparkir instance lain: presence & sesi tonton pindah ke sini
+            ws_conn::viewer_joined(&state, &room, &r.presence).await;
+            if let Some(id) = r.view_id {
+                analytics::view_moved(&state.db, id, state.bus.node()).await;
+            }
         }
-        // kirim notifikasi join + total
-        let total_now = {
-            let vc = state.viewer_counts.read().await;
-            vc.values().copied().sum::<usize>()
+        guard.presence = Some(r.presence.clone());
+    } else if is_view_room {
+        let node = state.bus.node();
+        let view_id = analytics::view_started(&state.db, node, &room, &viewer_key, sender.user_id).await;
+        guard.view_id = Some(view_id);
+        // presence per viewer (user / sesi): tab kedua dst. tidak dihitung lagi
+        let presence = Presence {
+            key: viewer_key.clone(),
+            user_id: sender.user_id,
+            joined_at: chrono::Utc::now(),
+            connections: 1,
         };
-        let _ = state.notify_tx.send(serde_json::json!({"t":"viewer_join","room":room}).to_string());
-        let _ = state.notify_tx.send(serde_json::json!({"t":"viewer_total","n":total_now}).to_string());
+        ws_conn::viewer_joined(&state, &room, &presence).await;
+        guard.presence = Some(presence);
     }
 
-    let _ = tx.send(
-        serde_json::to_string(&WsMsg::Sys {
-            room: room.clone(),
-            text: "Client joined".into(),
-        }).unwrap(),
-    );
+    if resumed.is_none() {
+        let joined = Envelope::new(Some(&room), None, Body::Sys { text: "Client joined".into() });
+        if is_view_room {
+            room_publish(&state, &room, joined).await;
+        } else {
+            state.bus.publish(&topic, joined.to_json()).await;
+        }
+    }
 
     let (mut writer_ws, mut reader_ws) = socket.split();
+    let outbox = ws_conn::Outbox::new(limits.queue);
 
-    let writer = tokio::spawn({
-        let mut rx2 = rx;
+    // balasan khusus koneksi ini (welcome, error), tidak ikut broadcast; antrian
+    // penuh pesan penting bahkan untuk balasan sendiri → putus
+    let reply = |body: Body| {
+        if !outbox.push(Envelope::new(Some(&room), None, body).to_json()) {
+            outbox.close();
+        }
+    };
+    reply(Body::Welcome { role: sender.role, resume: guard.resume.clone(), resumed: resumed.is_some() });
+    if is_view_room {
+        // resume: pesan room setelah `since` dari buffer replay
+        let missed = match (&resumed, rq.since) {
+            (Some(_), Some(since)) => state.bus.replay(&room, since).await,
+            _ => None,
+        };
+        match missed {
+            Some(msgs) => {
+                for m in msgs {
+                    if !outbox.push(m) {
+                        outbox.close();
+                    }
+                }
+            }
+            // sudah tidak tersimpan: kirim ulang state penuh
+            None if resumed.is_some() => resync(&state, Some(&room), &outbox, 0).await,
+            // viewer yang datang belakangan tetap melihat riwayat chat & produk yang sedang di-pin
+            None => {
+                for body in room_snapshot(&state, &room).await {
+                    reply(body);
+                }
+            }
+        }
+    }
+
+    // pump: topik bus → outbox. Frame tertinggal cukup dilompati (receiver broadcast
+    // sudah lompat ke yang terbaru); pesan lain tertinggal / outbox penuh → resync.
+    let pump = tokio::spawn({
+        let (state, room, outbox) = (state.clone(), room.clone(), outbox.clone());
+        let (mut rx, mut frame_rx, mut all_rx, mut staff_rx, mut user_rx) =
+            (rx, frame_rx, all_rx, staff_rx, user_rx);
         async move {
-            while let Ok(msg) = rx2.recv().await {
-                if writer_ws.send(Message::Text(msg)).await.is_err() {
-                    break;
+            let room = Some(room.as_str()).filter(|_| is_view_room);
+            loop {
+                let (frame, res) = tokio::select! {
+                    _ = outbox.closed() => break,
+                    m = recv_opt(&mut frame_rx) => (true, m),
+                    m = recv_opt(&mut rx) => (false, m),
+                    m = recv_opt(&mut all_rx) => (false, m),
+                    m = recv_opt(&mut staff_rx) => (false, m),
+                    m = recv_opt(&mut user_rx) => (false, m),
+                };
+                match res {
+                    Ok(m) if frame => outbox.push_frame(m),
+                    Ok(m) => {
+                        if !outbox.push(m) {
+                            let missed = outbox.clear();
+                            resync(&state, room, &outbox, missed).await;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) if frame => {}
+                    Err(broadcast::error::RecvError::Lagged(n)) => resync(&state, room, &outbox, n).await,
+                    Err(broadcast::error::RecvError::Closed) => break,
                 }
             }
+            outbox.close();
         }
     });
 
-    // ===== reader loop
-    while let Some(Ok(msg)) = reader_ws.next().await {
+    // writer: outbox → socket + ping berkala; kiriman macet lebih dari batas idle → putus
+    let writer = tokio::spawn({
+        let outbox = outbox.clone();
+        async move {
+            let mut ping = tokio::time::interval(limits.ping);
+            ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            ping.tick().await;
+            loop {
+                let msg = tokio::select! {
+                    m = outbox.pop() => match m { Some(m) => Message::Text(m), None => break },
+                    _ = ping.tick() => Message::Ping(Vec::new()),
+                };
+                match tokio::time::timeout(limits.idle, writer_ws.send(msg)).await {
+                    Ok(Ok(())) => {}
+                    _ => break,
+                }
+            }
+            outbox.close();
+        }
+    });
+    guard.tasks = vec![pump, writer];
+
+    // ===== reader loop: parse ketat, cek hak peran, cap identitas pengirim
+    let mut kick_rx = state.moderation.subscribe_kicks();
+    let mut deadline = tokio::time::Instant::now() + limits.idle;
+    loop {
+        let msg = tokio::select! {
+            m = reader_ws.next() => match m { Some(Ok(m)) => m, _ => break },
+            // tidak ada kiriman / Pong selama batas idle → koneksi mati
+            _ = tokio::time::sleep_until(deadline) => break,
+            // writer berhenti atau client terlalu lambat
+            _ = outbox.closed() => break,
+            Ok((r, uid)) = kick_rx.recv() => {
+                // kena ban saat sedang terhubung → putus
+                if r == room && sender.user_id == Some(uid) { break }
+                continue;
+            }
+        };
+        deadline = tokio::time::Instant::now() + limits.idle;
         match msg {
             Message::Text(txt) => {
-                if txt.len() > 2_000_000 { continue; }
-                if let Ok(mut parsed) = serde_json::from_str::<serde_json::Value>(&txt) {
-                    if parsed.get("room").is_none() {
-                        parsed["room"] = serde_json::Value::String(room.clone());
+                let parsed = ws_protocol::parse_client(&txt).and_then(|m| {
+                    // _events hanya menerima kiriman host
+                    let allowed = sender.role.may_send(m.kind())
+                        && (is_view_room || sender.role == RoomRole::Host);
+                    if allowed { Ok(m) } else { Err(ProtoError::Forbidden { kind: m.kind() }) }
+                });
+                match parsed {
+                    Ok(ClientMsg::Moderate { action }) => {
+                        if let Err(e) = moderation::apply(&state, &room, &sender, action).await {
+                            reply(e.into_body());
+                        }
+                    }
+                    Ok(ClientMsg::Pin { product_id, live_price, seconds }) => {
+                        if let Err(e) =
+                            showcase::pin(&state, &room, sender.user_id, product_id, live_price, seconds)
+                                .await
+                        {
+                            reply(e.into_body());
+                        }
+                    }
+                    Ok(ClientMsg::Bid { auction_id, amount }) => {
+                        // bukan Anonymous (dicek may_send) → user_id pasti ada
+                        if let Some(uid) = sender.user_id {
+                            let name = sender.name.as_deref().unwrap_or_default();
+                            if let Err(e) =
+                                auctions::bid(&state, auction_id, Some(&room), uid, name, amount).await
+                            {
+                                reply(e.into_body());
+                            }
+                        }
+                    }
+                    Ok(ClientMsg::Unpin) => {
+                        if let Err(e) = showcase::unpin(&state, &room).await {
+                            reply(e.into_body());
+                        }
                     }
-                    let _ = tx.send(parsed.to_string());
+                    Ok(m) => {
+                        // perintah server sudah ditangani di atas
+                        let Some(mut body) = m.into_body() else { continue };
+                        // chat room dicek moderasi lalu disimpan agar bisa di-backfill & diekspor
+                        if let Body::Chat { text, chat_id } = &mut body {
+                            if is_view_room {
+                                if let Err(e) =
+                                    state.moderation.check_chat(&state.db, &room, &sender, text).await
+                                {
+                                    reply(e.into_body());
+                                    continue;
+                                }
+                                *chat_id = Some(chat::save(&state.db, &room, &sender, text).await);
+                            }
+                        }
+                        // comment-to-buy: "KEEP A12 2" (diproses setelah chat disiarkan)
+                        let claim = match &body {
+                            Body::Chat { text, chat_id } if is_view_room => {
+                                claims::parse(&state.claim_keywords, text).map(|c| (c, *chat_id))
+                            }
+                            _ => None,
+                        };
+                        // frame lewat topik sendiri (boleh tertinggal / dibuang, tanpa nomor urut)
+                        let is_frame = matches!(body, Body::Frame { .. });
+                        let env = Envelope::new(Some(&room), Some(&sender), body);
+                        if is_view_room && !is_frame {
+                            room_publish(&state, &room, env).await;
+                        } else {
+                            let dest = if is_frame { &frame_topic } else { &topic };
+                            state.bus.publish(dest, env.to_json()).await;
+                        }
+
+                        if let Some((c, chat_id)) = claim {
+                            if let Some(out) = claims::process(&state, &room, &sender, chat_id, c).await {
+                                reply(Body::ClaimResult(out.clone()));
+                                staff_send(&state, &room, Body::Claim(out)).await;
+                            }
+                        }
+                    }
+                    Err(e) => reply(e.into_body()),
                 }
             }
-            Message::Binary(_) => {}
+            Message::Binary(_) => reply(
+                ProtoError::Malformed("binary frames not supported".into()).into_body(),
+            ),
             Message::Ping(_) | Message::Pong(_) => {}
             Message::Close(_) => break,
         }
     }
 
-    // on close: decrement
-    if is_view_room {
-        {
-            let mut vc = state.viewer_counts.write().await;
-            if let Some(c) = vc.get_mut(&room) { if *c > 0 { *c -= 1; } }
-        }
-        let total_now = {
-            let vc = state.viewer_counts.read().await;
-            vc.values().copied().sum::<usize>()
-        };
-        let _ = state.notify_tx.send(serde_json::json!({"t":"viewer_total","n":total_now}).to_string());
+    // on close: decrement, tutup sesi tonton & lepas topik (lihat ws_conn::ConnGuard)
+    drop(guard);
+}
+
+// Kanal opsional: None = tidak pernah siap (cabang select! tidak aktif).
+async fn recv_opt(
+    rx: &mut Option<broadcast::Receiver<String>>,
+) -> Result<String, broadcast::error::RecvError> {
+    match rx.as_mut() {
+        Some(r) => r.recv().await,
+        None => std::future::pending().await,
     }
+}
 
-    writer.abort();
+// State room untuk koneksi yang baru join / resync: jumlah viewer, riwayat chat, pin,
+// lelang, sesi.
+async fn room_snapshot(state: &AppState, room: &str) -> Vec<Body> {
+    let n = state.bus.viewer_counts().await.get(room).copied().unwrap_or(0);
+    let mut out = vec![Body::ViewerCount { n }];
+    if state.chat_backfill > 0 {
+        let messages = chat::recent(&state.db, room, state.chat_backfill).await;
+        out.push(Body::ChatHistory { messages });
+    }
+    if let Some(pin) = showcase::current(&state.db, room).await {
+        out.push(Body::ProductPinned(pin));
+    }
+    if let Some(auction) = auctions::current(&state.db, room).await {
+        out.push(Body::Auction(auction));
+    }
+    if let Some(stream) = streams::current(&state.db, room).await {
+        out.push(Body::Stream(stream));
+    }
+    out
+}
+
+// Koneksi tertinggal: beri tahu client lalu kirim ulang state room (None = _events,
+// client memuat ulang lewat REST).
+async fn resync(state: &AppState, room: Option<&str>, outbox: &ws_conn::Outbox, missed: u64) {
+    outbox.push(ws_protocol::event(room, Body::Resync { missed }));
+    if let Some(room) = room {
+        for body in room_snapshot(state, room).await {
+            outbox.push(ws_protocol::event(Some(room), body));
+        }
+    }
 }
+
 /* ===================== Bind helper ===================== */
 
 async fn bind_with_fallback() -> (tokio::net::TcpListener, String) {
@@ -0,0 +1,574 @@
+// server/src/moderation.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Moderasi chat per room: hapus pesan (retraksi), mute / ban berdurasi, slow mode,
+// daftar kata terlarang (kata biasa atau /regex/) dan blokir link.
+//
+// Sumber kebenaran ada di DB (room_moderation, room_sanctions); `Moderation` hanya
+// cache per room yang dibuang setiap kali ada perubahan, plus jejak slow mode.
+// Semua aksi moderator/admin dicatat di moderation_log.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Pool};
+use tokio::sync::broadcast;
+
+use crate::{
+    bus::Topic,
+    room_roles::{self, RoomRole},
+    room_send,
+    ws_protocol::{Body, Sender},
+    AppState, User,
+};
+
+// durasi mute/ban maksimum: 30 hari
+const MAX_SANCTION_SECS: u32 = 30 * 24 * 3600;
+const MAX_SLOW_MODE_SECS: u32 = 3600;
+const MAX_BLOCKED_WORDS: usize = 500;
+
+fn link_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?i)(https?://|www\.|\b[a-z0-9-]+\.(com|id|net|org|co|io|me|ly|link|shop|xyz)\b)")
+            .expect("link regex")
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SanctionKind {
+    // tidak boleh chat
+    Mute,
+    // diputus + tidak boleh masuk room
+    Ban,
+}
+
+impl SanctionKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SanctionKind::Mute => "mute",
+            SanctionKind::Ban => "ban",
+        }
+    }
+}
+
+/// Aksi moderator lewat WS: `{"t":"mod","op":"mute","user_id":5,"seconds":600}`.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub(crate) enum ModAction {
+    Delete { chat_id: i64 },
+    Mute { user_id: i64, seconds: u32 },
+    Ban { user_id: i64, seconds: u32 },
+    Unmute { user_id: i64 },
+    Unban { user_id: i64 },
+    // 0 = matikan
+    SlowMode { seconds: u32 },
+}
+
+/// Pengaturan filter per room (tabel room_moderation).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct Settings {
+    pub slow_mode_secs: u32,
+    pub block_links: bool,
+    // kata biasa (case-insensitive) atau "/regex/"
+    pub blocked_words: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) enum ModError {
+    Banned { until: DateTime<Utc> },
+    Muted { until: DateTime<Utc> },
+    SlowMode { retry_in: u64 },
+    BlockedWord,
+    LinkBlocked,
+    NotFound,
+    Forbidden,
+    Invalid(String),
+}
+
+impl ModError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ModError::Banned { .. } => "banned",
+            ModError::Muted { .. } => "muted",
+            ModError::SlowMode { .. } => "slow_mode",
+            ModError::BlockedWord => "blocked_word",
+            ModError::LinkBlocked => "link_blocked",
+            ModError::NotFound => "not_found",
+            ModError::Forbidden => "forbidden",
+            ModError::Invalid(_) => "invalid",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ModError::Banned { until } => format!("banned from this room until {}", until.to_rfc3339()),
+            ModError::Muted { until } => format!("muted until {}", until.to_rfc3339()),
+            ModError::SlowMode { retry_in } => format!("slow mode: wait {retry_in}s"),
+            ModError::BlockedWord => "message contains a blocked word".into(),
+            ModError::LinkBlocked => "links are not allowed in this room".into(),
+            ModError::NotFound => "not found".into(),
+            ModError::Forbidden => "not allowed to moderate this user".into(),
+            ModError::Invalid(m) => m.clone(),
+        }
+    }
+
+    /// Balasan WS ke pengirim.
+    pub fn into_body(self) -> Body {
+        Body::Error { code: self.code(), message: self.message() }
+    }
+}
+
+impl IntoResponse for ModError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ModError::NotFound => axum::http::StatusCode::NOT_FOUND,
+            ModError::Forbidden => axum::http::StatusCode::FORBIDDEN,
+            _ => axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        let body = serde_json::json!({ "error": self.code(), "message": self.message() });
+        (status, Json(body)).into_response()
+    }
+}
+
+// snapshot satu room (dibaca dari DB saat pertama dipakai)
+struct RoomMod {
+    settings: Settings,
+    filters: Vec<Regex>,
+    mutes: HashMap<i64, DateTime<Utc>>,
+    bans: HashMap<i64, DateTime<Utc>>,
+    // pesan terakhir per user (slow mode)
+    last_chat: HashMap<i64, Instant>,
+}
+
+pub(crate) struct Moderation {
+    rooms: Mutex<HashMap<String, RoomMod>>,
+    // (room, user_id) yang harus diputus sekarang (ban)
+    kick_tx: broadcast::Sender<(String, i64)>,
+}
+
+impl Moderation {
+    pub fn new() -> Self {
+        Moderation { rooms: Mutex::new(HashMap::new()), kick_tx: broadcast::channel(64).0 }
+    }
+
+    pub fn subscribe_kicks(&self) -> broadcast::Receiver<(String, i64)> {
+        self.kick_tx.subscribe()
+    }
+
+    // buang cache room; dibaca ulang dari DB pada pesan berikutnya
+    fn invalidate(&self, room: &str) {
+        self.rooms.lock().unwrap().remove(room);
+    }
+}
+
+// pesan sinkron antar instance lewat Topic::Moderation
+#[derive(Serialize, Deserialize)]
+struct SyncMsg {
+    room: String,
+    // user yang baru di-ban → putus koneksinya di instance mana pun
+    kick: Option<i64>,
+}
+
+// setting/sanksi room berubah: semua instance (termasuk ini) buang cache-nya
+async fn changed(state: &AppState, room: &str, kick: Option<i64>) {
+    let msg = serde_json::to_string(&SyncMsg { room: room.to_string(), kick }).unwrap();
+    state.moderation.invalidate(room);
+    state.bus.publish(&Topic::Moderation, msg).await;
+}
+
+/// Terima sinkron moderasi dari bus: invalidasi cache + kick koneksi lokal.
+pub(crate) async fn spawn_sync(state: AppState) {
+    let mut rx = state.bus.subscribe(&Topic::Moderation).await;
+    tokio::spawn(async move {
+        loop {
+            let msg = match rx.recv().await {
+                Ok(m) => m,
+                // tertinggal: aman-nya buang semua cache
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    state.moderation.rooms.lock().unwrap().clear();
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let Ok(m) = serde_json::from_str::<SyncMsg>(&msg) else { continue };
+            state.moderation.invalidate(&m.room);
+            if let Some(user_id) = m.kick {
+                let _ = state.moderation.kick_tx.send((m.room, user_id));
+            }
+        }
+    });
+}
+
+impl Moderation {
+    async fn ensure_loaded(&self, db: &Pool<MySql>, room: &str) {
+        if self.rooms.lock().unwrap().contains_key(room) {
+            return;
+        }
+        let settings = load_settings(db, room).await;
+        let filters = compile_filters(&settings.blocked_words).unwrap_or_default();
+        let mut mutes = HashMap::new();
+        let mut bans = HashMap::new();
+        let rows = sqlx::query!(
+            r#"
+            SELECT user_id, kind, expires_at AS `expires_at!: DateTime<Utc>`
+            FROM room_sanctions
+            WHERE room=? AND lifted_at IS NULL AND expires_at > NOW()
+            "#,
+            room
+        )
+        .fetch_all(db)
+        .await
+        .unwrap();
+        for r in rows {
+            let map = if r.kind == "ban" { &mut bans } else { &mut mutes };
+            let e = map.entry(r.user_id).or_insert(r.expires_at);
+            *e = (*e).max(r.expires_at);
+        }
+        self.rooms.lock().unwrap().entry(room.to_string()).or_insert(RoomMod {
+            settings,
+            filters,
+            mutes,
+            bans,
+            last_chat: HashMap::new(),
+        });
+    }
+
+    pub async fn banned_until(&self, db: &Pool<MySql>, room: &str, user_id: i64) -> Option<DateTime<Utc>> {
+        self.ensure_loaded(db, room).await;
+        let rooms = self.rooms.lock().unwrap();
+        rooms.get(room)?.bans.get(&user_id).copied().filter(|u| *u > Utc::now())
+    }
+
+    /// Cek satu pesan chat sebelum disimpan & disiarkan. Host/moderator tidak kena filter.
+    /// Pesan yang diblok filter dicatat di moderation_log (actor NULL).
+    pub async fn check_chat(&self, db: &Pool<MySql>, room: &str, from: &Sender, text: &str) -> Result<(), ModError> {
+        let Some(uid) = from.user_id else {
+            return Ok(());
+        };
+        if matches!(from.role, RoomRole::Host | RoomRole::Moderator) {
+            return Ok(());
+        }
+        self.ensure_loaded(db, room).await;
+
+        let verdict = {
+            let mut rooms = self.rooms.lock().unwrap();
+            let Some(rm) = rooms.get_mut(room) else {
+                return Ok(());
+            };
+            let now = Utc::now();
+            if let Some(until) = rm.bans.get(&uid).copied().filter(|u| *u > now) {
+                return Err(ModError::Banned { until });
+            }
+            if let Some(until) = rm.mutes.get(&uid).copied().filter(|u| *u > now) {
+                return Err(ModError::Muted { until });
+            }
+            if rm.filters.iter().any(|re| re.is_match(text)) {
+                Err(ModError::BlockedWord)
+            } else if rm.settings.block_links && link_re().is_match(text) {
+                Err(ModError::LinkBlocked)
+            } else {
+                let slow = rm.settings.slow_mode_secs as u64;
+                match rm.last_chat.get(&uid) {
+                    Some(t) if slow > 0 && t.elapsed().as_secs() < slow => {
+                        return Err(ModError::SlowMode { retry_in: slow - t.elapsed().as_secs() });
+                    }
+                    _ => {
+                        rm.last_chat.insert(uid, Instant::now());
+                        Ok(())
+                    }
+                }
+            }
+        };
+
+        if let Err(e) = &verdict {
+            log(db, room, None, "filter_block", Some(uid), None, Some(&format!("{}: {text}", e.code())))
+                .await;
+        }
+        verdict
+    }
+}
+
+/* ===================== Aksi (dipakai WS & API admin) ===================== */
+
+/// Aksi moderator dari WS.
+pub(crate) async fn apply(state: &AppState, room: &str, actor: &Sender, action: ModAction) -> Result<(), ModError> {
+    let Some(actor_id) = actor.user_id else {
+        return Err(ModError::Forbidden);
+    };
+    match action {
+        ModAction::Delete { chat_id } => delete_message(state, room, actor_id, chat_id).await,
+        ModAction::Mute { user_id, seconds } => {
+            sanction(state, room, actor_id, actor.role, user_id, SanctionKind::Mute, seconds, None)
+                .await
+                .map(|_| ())
+        }
+        ModAction::Ban { user_id, seconds } => {
+            sanction(state, room, actor_id, actor.role, user_id, SanctionKind::Ban, seconds, None)
+                .await
+                .map(|_| ())
+        }
+        ModAction::Unmute { user_id } => lift(state, room, actor_id, user_id, SanctionKind::Mute).await,
+        ModAction::Unban { user_id } => lift(state, room, actor_id, user_id, SanctionKind::Ban).await,
+        ModAction::SlowMode { seconds } => set_slow_mode(state, room, actor_id, seconds).await,
+    }
+}
+
+/// Tandai pesan terhapus + siarkan retraksi `chat_deleted` ke room.
+pub(crate) async fn delete_message(state: &AppState, room: &str, actor_id: i64, chat_id: i64) -> Result<(), ModError> {
+    let res = sqlx::query!(
+        "UPDATE chat_messages SET deleted_at=NOW(), deleted_by=? WHERE id=? AND room=? AND deleted_at IS NULL",
+        actor_id,
+        chat_id,
+        room
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    if res.rows_affected() == 0 {
+        return Err(ModError::NotFound);
+    }
+    log(&state.db, room, Some(actor_id), "delete", None, Some(chat_id), None).await;
+    room_send(state, room, Body::ChatDeleted { chat_id }).await;
+    Ok(())
+}
+
+/// Mute / ban `user_id` selama `seconds`. Host tidak bisa dikenai sanksi; moderator
+/// hanya bisa dikenai sanksi oleh host.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn sanction(
+    state: &AppState,
+    room: &str,
+    actor_id: i64,
+    actor_role: RoomRole,
+    user_id: i64,
+    kind: SanctionKind,
+    seconds: u32,
+    reason: Option<&str>,
+) -> Result<DateTime<Utc>, ModError> {
+    if seconds == 0 || seconds > MAX_SANCTION_SECS {
+        return Err(ModError::Invalid(format!("seconds must be 1..={MAX_SANCTION_SECS}")));
+    }
+    let Some(target) = load_user(&state.db, user_id).await else {
+        return Err(ModError::NotFound);
+    };
+    match room_roles::resolve(&state.db, Some(&target), room).await {
+        RoomRole::Host => return Err(ModError::Forbidden),
+        RoomRole::Moderator if actor_role != RoomRole::Host => return Err(ModError::Forbidden),
+        _ => {}
+    }
+
+    let until = Utc::now() + chrono::Duration::seconds(seconds as i64);
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO room_sanctions(room, user_id, kind, expires_at, reason, created_by)
+        VALUES(?,?,?,?,?,?)
+        "#,
+        room,
+        user_id,
+        kind.as_str(),
+        until,
+        reason,
+        actor_id
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    changed(state, room, (kind == SanctionKind::Ban).then_some(user_id)).await;
+
+    let detail = format!("{seconds}s{}", reason.map(|r| format!(": {r}")).unwrap_or_default());
+    log(&state.db, room, Some(actor_id), kind.as_str(), Some(user_id), None, Some(&detail)).await;
+
+    room_send(state, room, Body::Sanction { user_id, kind: kind.as_str(), expires_at: Some(until) }).await;
+    Ok(until)
+}
+
+/// Cabut mute / ban yang masih aktif.
+pub(crate) async fn lift(state: &AppState, room: &str, actor_id: i64, user_id: i64, kind: SanctionKind) -> Result<(), ModError> {
+    let res = sqlx::query!(
+        r#"
+        UPDATE room_sanctions SET lifted_at=NOW()
+        WHERE room=? AND user_id=? AND kind=? AND lifted_at IS NULL AND expires_at > NOW()
+        "#,
+        room,
+        user_id,
+        kind.as_str()
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    if res.rows_affected() == 0 {
+        return Err(ModError::NotFound);
+    }
+    changed(state, room, None).await;
+
+    let action = match kind {
+        SanctionKind::Mute => "unmute",
+        SanctionKind::Ban => "unban",
+    };
+    log(&state.db, room, Some(actor_id), action, Some(user_id), None, None).await;
+    room_send(state, room, Body::Sanction { user_id, kind: kind.as_str(), expires_at: None }).await;
+    Ok(())
+}
+
+pub(crate) async fn set_slow_mode(state: &AppState, room: &str, actor_id: i64, seconds: u32) -> Result<(), ModError> {
+    if seconds > MAX_SLOW_MODE_SECS {
+        return Err(ModError::Invalid(format!("seconds must be 0..={MAX_SLOW_MODE_SECS}")));
+    }
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO room_moderation(room, slow_mode_secs) VALUES(?,?)
+        ON DUPLICATE KEY UPDATE slow_mode_secs=VALUES(slow_mode_secs)
+        "#,
+        room,
+        seconds
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    changed(state, room, None).await;
+
+    log(&state.db, room, Some(actor_id), "slow_mode", None, None, Some(&format!("{seconds}s"))).await;
+    room_send(state, room, Body::SlowMode { seconds }).await;
+    Ok(())
+}
+
+/// Simpan seluruh pengaturan filter room (API admin).
+pub(crate) async fn save_settings(state: &AppState, room: &str, actor_id: i64, s: &Settings) -> Result<(), ModError> {
+    if s.slow_mode_secs > MAX_SLOW_MODE_SECS {
+        return Err(ModError::Invalid(format!("slow_mode_secs must be 0..={MAX_SLOW_MODE_SECS}")));
+    }
+    if s.blocked_words.len() > MAX_BLOCKED_WORDS {
+        return Err(ModError::Invalid(format!("at most {MAX_BLOCKED_WORDS} blocked words")));
+    }
+    let words: Vec<String> = s
+        .blocked_words
+        .iter()
+        .map(|w| w.trim().to_string())
+        .filter(|w| !w.is_empty())
+        .collect();
+    compile_filters(&words).map_err(ModError::Invalid)?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO room_moderation(room, slow_mode_secs, block_links, blocked_words)
+        VALUES(?,?,?,?)
+        ON DUPLICATE KEY UPDATE slow_mode_secs=VALUES(slow_mode_secs),
+                                block_links=VALUES(block_links),
+                                blocked_words=VALUES(blocked_words)
+        "#,
+        room,
+        s.slow_mode_secs,
+        s.block_links,
+        words.join("\n")
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    changed(state, room, None).await;
+
+    let detail = format!(
+        "slow_mode={}s block_links={} words={}",
+        s.slow_mode_secs,
+        s.block_links,
+        words.len()
+    );
+    log(&state.db, room, Some(actor_id), "settings", None, None, Some(&detail)).await;
+    room_send(state, room, Body::SlowMode { seconds: s.slow_mode_secs }).await;
+    Ok(())
+}
+
+pub(crate) async fn load_settings(db: &Pool<MySql>, room: &str) -> Settings {
+    let row = sqlx::query!(
+        r#"
+        SELECT slow_mode_secs, block_links AS `block_links: bool`, blocked_words
+        FROM room_moderation WHERE room=?
+        "#,
+        room
+    )
+    .fetch_optional(db)
+    .await
+    .unwrap();
+    match row {
+        Some(r) => Settings {
+            slow_mode_secs: r.slow_mode_secs,
+            block_links: r.block_links,
+            blocked_words: r
+                .blocked_words
+                .unwrap_or_default()
+                .lines()
+                .map(str::to_string)
+                .collect(),
+        },
+        None => Settings::default(),
+    }
+}
+
+/* ===================== helpers lokal ===================== */
+
+// "/pola/" = regex apa adanya, selain itu kata utuh case-insensitive
+fn compile_filters(words: &[String]) -> Result<Vec<Regex>, String> {
+    words
+        .iter()
+        .map(|w| {
+            let pat = match w.strip_prefix('/').and_then(|x| x.strip_suffix('/')) {
+                Some(re) if !re.is_empty() => format!("(?i){re}"),
+                _ => format!(r"(?i)\b{}\b", regex::escape(w)),
+            };
+            Regex::new(&pat).map_err(|e| format!("invalid pattern {w:?}: {e}"))
+        })
+        .collect()
+}
+
+async fn load_user(db: &Pool<MySql>, user_id: i64) -> Option<User> {
+    sqlx::query!("SELECT id, role, name, email, phone FROM users WHERE id=?", user_id)
+        .fetch_optional(db)
+        .await
+        .unwrap()
+        .map(|r| User { id: r.id, role: r.role, name: r.name, email: r.email, phone: r.phone })
+}
+
+async fn log(
+    db: &Pool<MySql>,
+    room: &str,
+    actor_id: Option<i64>,
+    action: &str,
+    target_user_id: Option<i64>,
+    chat_id: Option<i64>,
+    detail: Option<&str>,
+) {
+    // detail dipotong agar muat di kolom
+    let detail = detail.map(|d| d.chars().take(500).collect::<String>());
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO moderation_log(room, actor_id, action, target_user_id, chat_id, detail)
+        VALUES(?,?,?,?,?,?)
+        "#,
+        room,
+        actor_id,
+        action,
+        target_user_id,
+        chat_id,
+        detail
+    )
+    .execute(db)
+    .await
+    .unwrap();
+}
@@ -0,0 +1,155 @@
+// server/src/order_status.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Status order; nilainya sama dengan enum kolom `orders.status`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OrderStatus {
+    New,
+    Paid,
+    Shipped,
+    Done,
+    Cancelled,
+    Deleted,
+}
+
+impl OrderStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrderStatus::New => "new",
+            OrderStatus::Paid => "paid",
+            OrderStatus::Shipped => "shipped",
+            OrderStatus::Done => "done",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Deleted => "deleted",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "new" => Some(OrderStatus::New),
+            "paid" => Some(OrderStatus::Paid),
+            "shipped" => Some(OrderStatus::Shipped),
+            "done" => Some(OrderStatus::Done),
+            "cancelled" => Some(OrderStatus::Cancelled),
+            "deleted" => Some(OrderStatus::Deleted),
+            _ => None,
+        }
+    }
+
+    /// Tabel transisi yang diizinkan:
+    /// new → paid | cancelled | deleted, paid → shipped | cancelled,
+    /// shipped → done, done → deleted, cancelled → deleted.
+    pub fn can_transition_to(self, next: OrderStatus) -> bool {
+        use OrderStatus::*;
+        matches!(
+            (self, next),
+            (New, Paid)
+                | (New, Cancelled)
+                | (New, Deleted)
+                | (Paid, Shipped)
+                | (Paid, Cancelled)
+                | (Shipped, Done)
+                | (Done, Deleted)
+                | (Cancelled, Deleted)
+        )
+    }
+
+    /// Status yang stoknya sudah dikembalikan ke produk.
+    pub fn releases_stock(self) -> bool {
+        matches!(self, OrderStatus::Cancelled | OrderStatus::Deleted)
+    }
+}
+
+/// Gagal mengubah status order.
+#[derive(Debug)]
+pub(crate) enum StatusChangeError {
+    NotFound,
+    Illegal { from: OrderStatus, to: OrderStatus },
+}
+
+impl IntoResponse for StatusChangeError {
+    fn into_response(self) -> Response {
+        match self {
+            StatusChangeError::NotFound => {
+                (axum::http::StatusCode::NOT_FOUND, "not found").into_response()
+            }
+            StatusChangeError::Illegal { from, to } => (
+                axum::http::StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": "illegal status transition",
+                    "from": from,
+                    "to": to,
+                })),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OrderStatus::{self, *};
+
+    const ALL: [OrderStatus; 6] = [New, Paid, Shipped, Done, Cancelled, Deleted];
+
+    // satu-satunya transisi yang boleh; pasangan lain (termasuk ke diri sendiri) ditolak
+    const ALLOWED: [(OrderStatus, OrderStatus); 8] = [
+        (New, Paid),
+        (New, Cancelled),
+        (New, Deleted),
+        (Paid, Shipped),
+        (Paid, Cancelled),
+        (Shipped, Done),
+        (Done, Deleted),
+        (Cancelled, Deleted),
+    ];
+
+    #[test]
+    fn transition_table() {
+        for from in ALL {
+            for to in ALL {
+                let expected = ALLOWED.contains(&(from, to));
+                assert_eq!(from.can_transition_to(to), expected, "{from:?} -> {to:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn backward_transitions_rejected() {
+        assert!(!Done.can_transition_to(Paid));
+        assert!(!Shipped.can_transition_to(New));
+        assert!(!Shipped.can_transition_to(Cancelled));
+        assert!(!Deleted.can_transition_to(New));
+        assert!(!Cancelled.can_transition_to(Paid));
+    }
+
+    #[test]
+    fn releases_stock_table() {
+        for s in ALL {
+            assert_eq!(s.releases_stock(), matches!(s, Cancelled | Deleted), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        for s in ALL {
+            assert_eq!(OrderStatus::parse(s.as_str()), Some(s));
+        }
+        assert_eq!(OrderStatus::parse("refunded"), None);
+    }
+}
@@ -0,0 +1,230 @@
+// server/src/payments/mock.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+//! Provider lokal tanpa jaringan. Callback ditandatangani HMAC-SHA256
+//! (header `X-Mock-Signature`, hex) sama seperti provider sungguhan, sehingga
+//! alur webhook bisa dijalankan offline lewat `simulate_callback`.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use super::{IntentRequest, PaymentError, PaymentIntent, PaymentProvider, PaymentStatus, PaymentUpdate};
+
+const SIGNATURE_HEADER: &str = "x-mock-signature";
+
+#[derive(Serialize, Deserialize)]
+struct MockCallback {
+    provider_ref: String,
+    status: PaymentStatus,
+    amount: i64,
+}
+
+pub(crate) struct MockProvider {
+    secret: String,
+    // status terakhir per provider_ref (untuk query_status)
+    state: Mutex<HashMap<String, PaymentStatus>>,
+}
+
+impl MockProvider {
+    pub fn new(secret: String) -> Self {
+        Self { secret, state: Mutex::new(HashMap::new()) }
+    }
+
+    fn sign(&self, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).expect("hmac key");
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn create_intent(&self, req: &IntentRequest) -> Result<PaymentIntent, PaymentError> {
+        let provider_ref = format!("MOCK-{}-{}", req.order_id, &Uuid::new_v4().simple().to_string()[..10]);
+        let (va_number, qr_string) = match req.method.as_str() {
+            "va" => (Some(format!("8808{:012}", req.order_id)), None),
+            "qris" => (None, Some(format!("MOCKQRIS|{}|{}", provider_ref, req.amount))),
+            other => return Err(PaymentError::Unsupported(other.to_string())),
+        };
+        self.state.lock().unwrap().insert(provider_ref.clone(), PaymentStatus::Pending);
+
+        Ok(PaymentIntent {
+            provider_ref,
+            method: req.method.clone(),
+            amount: req.amount,
+            pay_url: None,
+            va_number,
+            qr_string,
+            expires_at: Some(Utc::now() + Duration::hours(24)),
+        })
+    }
+
+    fn verify_callback(&self, headers: &HeaderMap, body: &[u8]) -> Result<PaymentUpdate, PaymentError> {
+        let sig = headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| hex::decode(s).ok())
+            .ok_or(PaymentError::BadSignature)?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).expect("hmac key");
+        mac.update(body);
+        mac.verify_slice(&sig).map_err(|_| PaymentError::BadSignature)?;
+
+        let cb: MockCallback =
+            serde_json::from_slice(body).map_err(|e| PaymentError::BadPayload(e.to_string()))?;
+        self.state.lock().unwrap().insert(cb.provider_ref.clone(), cb.status);
+
+        Ok(PaymentUpdate {
+            provider_ref: cb.provider_ref,
+            status: cb.status,
+            amount: cb.amount,
+        })
+    }
+
+    async fn query_status(&self, provider_ref: &str) -> Result<PaymentStatus, PaymentError> {
+        self.state
+            .lock()
+            .unwrap()
+            .get(provider_ref)
+            .copied()
+            .ok_or_else(|| PaymentError::Provider(format!("unknown ref {provider_ref}")))
+    }
+
+    async fn cancel_intent(&self, provider_ref: &str) -> Result<(), PaymentError> {
+        let mut state = self.state.lock().unwrap();
+        match state.get(provider_ref).copied() {
+            Some(PaymentStatus::Paid) => Err(PaymentError::Provider(format!("{provider_ref} already paid"))),
+            // ref tidak dikenal (mis. proses sudah restart): tidak ada yang bisa dibayar lagi
+            _ => {
+                state.insert(provider_ref.to_string(), PaymentStatus::Expired);
+                Ok(())
+            }
+        }
+    }
+
+    fn simulate_callback(
+        &self,
+        provider_ref: &str,
+        status: PaymentStatus,
+        amount: i64,
+    ) -> Option<(HeaderMap, Vec<u8>)> {
+        let body = serde_json::to_vec(&MockCallback {
+            provider_ref: provider_ref.to_string(),
+            status,
+            amount,
+        })
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(SIGNATURE_HEADER, self.sign(&body).parse().unwrap());
+        Some((headers, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> MockProvider {
+        MockProvider::new("test-secret".to_string())
+    }
+
+    fn signed(p: &MockProvider, body: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SIGNATURE_HEADER, p.sign(body).parse().unwrap());
+        headers
+    }
+
+    fn callback_body(status: PaymentStatus, amount: i64) -> Vec<u8> {
+        serde_json::to_vec(&MockCallback { provider_ref: "MOCK-1-abc".to_string(), status, amount }).unwrap()
+    }
+
+    #[test]
+    fn sign_verify_round_trip() {
+        let p = provider();
+        let body = callback_body(PaymentStatus::Paid, 150_000);
+        let upd = p.verify_callback(&signed(&p, &body), &body).unwrap();
+        assert_eq!(upd.provider_ref, "MOCK-1-abc");
+        assert_eq!(upd.status, PaymentStatus::Paid);
+        assert_eq!(upd.amount, 150_000);
+    }
+
+    #[test]
+    fn wrong_signature_rejected() {
+        let p = provider();
+        let body = callback_body(PaymentStatus::Paid, 150_000);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(SIGNATURE_HEADER, "00".repeat(32).parse().unwrap());
+        assert!(matches!(p.verify_callback(&headers, &body), Err(PaymentError::BadSignature)));
+
+        // bukan hex / header tidak ada
+        headers.insert(SIGNATURE_HEADER, "not-hex".parse().unwrap());
+        assert!(matches!(p.verify_callback(&headers, &body), Err(PaymentError::BadSignature)));
+        assert!(matches!(p.verify_callback(&HeaderMap::new(), &body), Err(PaymentError::BadSignature)));
+
+        // secret lain
+        let other = MockProvider::new("other-secret".to_string());
+        assert!(matches!(
+            p.verify_callback(&signed(&other, &body), &body),
+            Err(PaymentError::BadSignature)
+        ));
+    }
+
+    #[test]
+    fn tampered_body_rejected() {
+        let p = provider();
+        let body = callback_body(PaymentStatus::Paid, 150_000);
+        let headers = signed(&p, &body);
+        let tampered = callback_body(PaymentStatus::Paid, 1);
+        assert!(matches!(p.verify_callback(&headers, &tampered), Err(PaymentError::BadSignature)));
+    }
+
+    #[tokio::test]
+    async fn simulate_callback_verifies() {
+        let p = provider();
+        let intent = p
+            .create_intent(&IntentRequest { order_id: 7, amount: 99_000, method: "va".to_string() })
+            .await
+            .unwrap();
+        assert_eq!(p.query_status(&intent.provider_ref).await.unwrap(), PaymentStatus::Pending);
+
+        let (headers, body) = p.simulate_callback(&intent.provider_ref, PaymentStatus::Paid, 99_000).unwrap();
+        let upd = p.verify_callback(&headers, &body).unwrap();
+        assert_eq!(upd.provider_ref, intent.provider_ref);
+        assert_eq!(upd.status, PaymentStatus::Paid);
+        assert_eq!(upd.amount, 99_000);
+        assert_eq!(p.query_status(&intent.provider_ref).await.unwrap(), PaymentStatus::Paid);
+    }
+
+    #[tokio::test]
+    async fn cancel_intent_expires_unpaid_only() {
+        let p = provider();
+        let req = IntentRequest { order_id: 8, amount: 50_000, method: "qris".to_string() };
+        let open = p.create_intent(&req).await.unwrap();
+        p.cancel_intent(&open.provider_ref).await.unwrap();
+        assert_eq!(p.query_status(&open.provider_ref).await.unwrap(), PaymentStatus::Expired);
+
+        let paid = p.create_intent(&req).await.unwrap();
+        let (headers, body) = p.simulate_callback(&paid.provider_ref, PaymentStatus::Paid, 50_000).unwrap();
+        p.verify_callback(&headers, &body).unwrap();
+        assert!(matches!(p.cancel_intent(&paid.provider_ref).await, Err(PaymentError::Provider(_))));
+    }
+}
@@ -0,0 +1,153 @@
+// server/src/payments/mod.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+//! Payment gateway. Setiap provider (mock, Midtrans/Xendit-style VA & QRIS, ...)
+//! mengimplementasikan `PaymentProvider`; handler di `handlers/payments.rs`
+//! hanya bicara dengan trait ini.
+
+pub(crate) mod mock;
+
+use std::{env, sync::Arc};
+
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Status pembayaran; nilainya sama dengan enum kolom `payments.status`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PaymentStatus {
+    Pending,
+    Paid,
+    Failed,
+    Expired,
+}
+
+impl PaymentStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PaymentStatus::Pending => "pending",
+            PaymentStatus::Paid => "paid",
+            PaymentStatus::Failed => "failed",
+            PaymentStatus::Expired => "expired",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(PaymentStatus::Pending),
+            "paid" => Some(PaymentStatus::Paid),
+            "failed" => Some(PaymentStatus::Failed),
+            "expired" => Some(PaymentStatus::Expired),
+            _ => None,
+        }
+    }
+}
+
+/// Permintaan membuat tagihan untuk satu order.
+#[derive(Debug, Clone)]
+pub(crate) struct IntentRequest {
+    pub order_id: i64,
+    pub amount: i64,
+    // "va" | "qris" | ... (provider boleh menolak metode yang tidak didukung)
+    pub method: String,
+}
+
+/// Tagihan yang dibuat provider; ditampilkan ke pembeli.
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PaymentIntent {
+    pub provider_ref: String,
+    pub method: String,
+    pub amount: i64,
+    pub pay_url: Option<String>,
+    pub va_number: Option<String>,
+    pub qr_string: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Hasil callback/webhook yang sudah diverifikasi.
+#[derive(Debug, Clone)]
+pub(crate) struct PaymentUpdate {
+    pub provider_ref: String,
+    pub status: PaymentStatus,
+    pub amount: i64,
+}
+
+#[derive(Debug)]
+pub(crate) enum PaymentError {
+    /// Signature callback tidak valid / tidak ada.
+    BadSignature,
+    /// Body callback tidak bisa dibaca.
+    BadPayload(String),
+    /// Metode bayar tidak didukung provider.
+    Unsupported(String),
+    /// Provider tidak bisa dihubungi / membalas error.
+    Provider(String),
+}
+
+impl std::fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaymentError::BadSignature => write!(f, "bad signature"),
+            PaymentError::BadPayload(m) => write!(f, "bad payload: {m}"),
+            PaymentError::Unsupported(m) => write!(f, "unsupported method: {m}"),
+            PaymentError::Provider(m) => write!(f, "provider error: {m}"),
+        }
+    }
+}
+
+#[async_trait]
+pub(crate) trait PaymentProvider: Send + Sync {
+    /// Nama provider, disimpan di `payments.provider` dan dipakai di URL webhook.
+    fn name(&self) -> &'static str;
+
+    /// Buat tagihan (VA, QRIS, ...) untuk order.
+    async fn create_intent(&self, req: &IntentRequest) -> Result<PaymentIntent, PaymentError>;
+
+    /// Verifikasi signature lalu parse body webhook.
+    fn verify_callback(&self, headers: &HeaderMap, body: &[u8]) -> Result<PaymentUpdate, PaymentError>;
+
+    /// Tanya status terbaru ke provider (untuk polling / rekonsiliasi).
+    async fn query_status(&self, provider_ref: &str) -> Result<PaymentStatus, PaymentError>;
+
+    /// Batalkan tagihan yang belum dibayar agar tidak bisa dibayar lagi.
+    /// Err bila sudah lunas / provider menolak (tagihan dianggap masih hidup).
+    async fn cancel_intent(&self, provider_ref: &str) -> Result<(), PaymentError>;
+
+    /// Khusus provider lokal: buat webhook bertanda tangan (headers + body) seolah
+    /// dikirim provider, agar alur bayar bisa dijalankan tanpa jaringan.
+    /// Provider sungguhan membiarkan default (None).
+    fn simulate_callback(
+        &self,
+        _provider_ref: &str,
+        _status: PaymentStatus,
+        _amount: i64,
+    ) -> Option<(HeaderMap, Vec<u8>)> {
+        None
+    }
+}
+
+/// Pilih provider dari env `PAYMENT_PROVIDER` (wajib; provider lokal hanya lewat
+/// `PAYMENT_PROVIDER=mock`). `PAYMENT_WEBHOOK_SECRET` wajib diisi: webhook bisa
+/// dipanggil siapa pun, secret yang bisa ditebak = order bisa ditandai lunas palsu.
+pub(crate) fn provider_from_env() -> Arc<dyn PaymentProvider> {
+    let provider = env::var("PAYMENT_PROVIDER")
+        .unwrap_or_else(|_| panic!("❌ PAYMENT_PROVIDER belum diisi (mis. `mock` untuk provider lokal)"));
+    let secret = env::var("PAYMENT_WEBHOOK_SECRET")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| panic!("❌ PAYMENT_WEBHOOK_SECRET belum diisi"));
+    match provider.as_str() {
+        "mock" => Arc::new(mock::MockProvider::new(secret)),
+        other => panic!("❌ PAYMENT_PROVIDER tidak dikenal: {other}"),
+    }
+}
@@ -0,0 +1,166 @@
+// server/src/resume.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Resume koneksi WS room (viewer di jaringan seluler yang sering putus):
+// - tiap koneksi room mendapat token sesi di `welcome`; saat putus, sesinya
+//   "diparkir" selama RESUME_GRACE_SECS — presence viewer & sesi tonton tetap jalan
+// - client menyambung ulang ke `/ws/:room?resume=<token>&since=<seq>` → tidak ada
+//   viewer_join / room_views baru, pesan room setelah `since` dikirim dari buffer replay
+// - lewat grace tanpa resume → dihitung keluar seperti biasa
+// Resume bisa mendarat di instance lain: data sesi disimpan di bus, instance asal
+// melepas presence-nya lewat Topic::Resumed.
+
+use std::{collections::HashMap, env, sync::Mutex, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{sync::broadcast, task::JoinHandle};
+
+use crate::{
+    analytics,
+    bus::{Presence, Topic},
+    ws_conn, AppState,
+};
+
+/// Baca RESUME_GRACE_SECS (default 30, 0 = resume mati).
+pub(crate) fn grace_secs_from_env() -> u64 {
+    env::var("RESUME_GRACE_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(30).min(600)
+}
+
+// data sesi di bus (key = token)
+#[derive(Serialize, Deserialize)]
+struct Session {
+    room: String,
+    user_id: Option<i64>,
+    view_id: Option<i64>,
+    presence: Presence,
+}
+
+// sesi yang diparkir instance ini (masih memegang presence viewer)
+struct Parked {
+    room: String,
+    key: String,
+    view_id: Option<i64>,
+    timer: JoinHandle<()>,
+}
+
+pub(crate) struct Resume {
+    grace_secs: u64,
+    parked: Mutex<HashMap<String, Parked>>,
+}
+
+/// Sesi lama yang berhasil diambil alih.
+pub(crate) struct Resumed {
+    pub view_id: Option<i64>,
+    // presence lama (joined_at tetap)
+    pub presence: Presence,
+    // presence masih dipegang instance ini (tidak perlu bus.join)
+    pub held: bool,
+}
+
+impl Resume {
+    pub fn new(grace_secs: u64) -> Self {
+        Resume { grace_secs, parked: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.grace_secs > 0
+    }
+
+    fn unpark(&self, token: &str) -> Option<Parked> {
+        self.parked.lock().unwrap().remove(token)
+    }
+}
+
+pub(crate) fn new_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Ambil alih sesi `token`; None = kedaluwarsa / bukan milik room & user ini.
+pub(crate) async fn claim(
+    state: &AppState,
+    token: &str,
+    room: &str,
+    user_id: Option<i64>,
+) -> Option<Resumed> {
+    if !state.resume.enabled() {
+        return None;
+    }
+    let raw = state.bus.claim(token).await?;
+    let s: Session = serde_json::from_str(&raw).ok()?;
+    if s.room != room || s.user_id != user_id {
+        // bukan pemiliknya: kembalikan agar pemilik asli masih bisa resume
+        state.bus.park(token, raw, state.resume.grace_secs).await;
+        return None;
+    }
+    let held = match state.resume.unpark(token) {
+        Some(p) => {
+            p.timer.abort();
+            true
+        }
+        None => {
+            // diparkir instance lain → instance itu melepas hitungannya
+            state.bus.publish(&Topic::Resumed, token.to_string()).await;
+            false
+        }
+    };
+    Some(Resumed { view_id: s.view_id, presence: s.presence, held })
+}
+
+/// Koneksi room putus: tahan hitungan viewer & sesi tonton selama masa grace.
+pub(crate) async fn park(
+    state: &AppState,
+    token: String,
+    room: String,
+    user_id: Option<i64>,
+    view_id: Option<i64>,
+    presence: Presence,
+) {
+    let grace = state.resume.grace_secs;
+    let key = presence.key.clone();
+    let data = serde_json::to_string(&Session { room: room.clone(), user_id, view_id, presence }).unwrap();
+    state.bus.park(&token, data, grace).await;
+
+    let timer = tokio::spawn({
+        let (state, token) = (state.clone(), token.clone());
+        async move {
+            tokio::time::sleep(Duration::from_secs(grace)).await;
+            let Some(p) = state.resume.unpark(&token) else { return };
+            // masih ada di bus = tidak ada yang resume → keluar betulan; sudah diambil
+            // instance lain (Topic::Resumed belum sampai) → cukup lepas hitungan
+            let expired = state.bus.claim(&token).await.is_some();
+            ws_conn::viewer_left(&state, &p.room, &p.key).await;
+            if let (true, Some(id)) = (expired, p.view_id) {
+                analytics::view_ended(&state.db, id).await;
+            }
+        }
+    });
+    state.resume.parked.lock().unwrap().insert(token, Parked { room, key, view_id, timer });
+}
+
+/// Sesi yang diparkir di sini di-resume di instance lain: lepas hitungan viewer
+/// (sesi tonton berlanjut di sana).
+pub(crate) async fn spawn_sync(state: AppState) {
+    let mut rx = state.bus.subscribe(&Topic::Resumed).await;
+    tokio::spawn(async move {
+        loop {
+            let token = match rx.recv().await {
+                Ok(t) => t,
+                // yang terlewat tetap dilepas oleh timer grace
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if let Some(p) = state.resume.unpark(&token) {
+                p.timer.abort();
+                ws_conn::viewer_left(&state, &p.room, &p.key).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,74 @@
+// server/src/room_roles.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Pool};
+
+use crate::User;
+
+/// Peran satu koneksi WebSocket di sebuah room; ditentukan saat upgrade.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RoomRole {
+    Host,
+    Moderator,
+    Viewer,
+    Anonymous,
+}
+
+impl RoomRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RoomRole::Host => "host",
+            RoomRole::Moderator => "moderator",
+            RoomRole::Viewer => "viewer",
+            RoomRole::Anonymous => "anonymous",
+        }
+    }
+
+    /// Tipe pesan (`t`) yang boleh dikirim peran ini; selain itu dibuang server.
+    /// Anonymous hanya menerima.
+    pub fn may_send(self, t: &str) -> bool {
+        use RoomRole::*;
+        match t {
+            // media, offer WebRTC & pin etalase hanya dari host
+            "f" | "offer" | "pin" | "unpin" => self == Host,
+            // pesan sistem & aksi moderasi hanya host / moderator
+            "sys" | "mod" => matches!(self, Host | Moderator),
+            "c" | "answer" | "ice" | "viewer_enter" | "bid" => self != Anonymous,
+            _ => false,
+        }
+    }
+}
+
+/// Admin = host di semua room; selain itu pakai penugasan `room_roles`
+/// (host / moderator per room), default viewer bila login.
+pub(crate) async fn resolve(db: &Pool<MySql>, user: Option<&User>, room: &str) -> RoomRole {
+    let Some(u) = user else {
+        return RoomRole::Anonymous;
+    };
+    if u.role == "admin" {
+        return RoomRole::Host;
+    }
+    let assigned = sqlx::query!(
+        "SELECT role FROM room_roles WHERE room=? AND user_id=? LIMIT 1",
+        room,
+        u.id
+    )
+    .fetch_optional(db)
+    .await
+    .unwrap();
+    match assigned.as_ref().map(|r| r.role.as_str()) {
+        Some("host") => RoomRole::Host,
+        Some("moderator") => RoomRole::Moderator,
+        _ => RoomRole::Viewer,
+    }
+}
@@ -0,0 +1,148 @@
+// server/src/shipping.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlConnection;
+
+/// Tujuan pengiriman; field kosong tidak ikut dicocokkan.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub(crate) struct Destination {
+    pub province: Option<String>,
+    pub city: Option<String>,
+    pub postcode: Option<String>,
+}
+
+/// Hasil perhitungan ongkir untuk satu tujuan + isi keranjang.
+#[derive(Serialize, Debug)]
+pub(crate) struct Quote {
+    pub zone_id: i64,
+    pub zone_name: String,
+    pub weight_grams: i64,
+    // ongkir sebelum gratis ongkir
+    pub base_fee: i64,
+    pub fee: i64,
+    pub free_shipping: bool,
+}
+
+/// Ongkir tidak bisa dihitung.
+#[derive(Debug)]
+pub(crate) enum ShippingError {
+    // tidak ada zona aktif yang mencakup tujuan
+    NotCovered,
+    // berat melebihi braket terakhir zona
+    TooHeavy { zone_id: i64, weight_grams: i64 },
+}
+
+impl IntoResponse for ShippingError {
+    fn into_response(self) -> Response {
+        let body = match self {
+            ShippingError::NotCovered => serde_json::json!({
+                "error": "destination not covered by shipping rates",
+            }),
+            ShippingError::TooHeavy { zone_id, weight_grams } => serde_json::json!({
+                "error": "weight exceeds shipping rate brackets",
+                "zone_id": zone_id,
+                "weight_grams": weight_grams,
+            }),
+        };
+        (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response()
+    }
+}
+
+/// Hitung ongkir. Zona dipilih yang paling spesifik (kode pos > kota > provinsi > semua);
+/// `flat_fee` mengabaikan braket berat, `free_min_subtotal` membuat ongkir 0.
+pub(crate) async fn quote(
+    conn: &mut MySqlConnection,
+    dest: &Destination,
+    weight_grams: i64,
+    subtotal: i64,
+) -> Result<Quote, ShippingError> {
+    let zones = sqlx::query!(
+        r#"
+        SELECT id, name, province, city, postcode_prefix, flat_fee, free_min_subtotal
+        FROM shipping_zones WHERE is_active=1 ORDER BY id ASC
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+
+    let norm = |s: &Option<String>| s.as_deref().map(|v| v.trim().to_lowercase()).unwrap_or_default();
+    let (d_prov, d_city, d_post) = (norm(&dest.province), norm(&dest.city), norm(&dest.postcode));
+
+    let mut best: Option<(usize, _)> = None;
+    for z in zones {
+        let mut score = 0;
+        if let Some(p) = z.postcode_prefix.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            if !d_post.starts_with(&p.to_lowercase()) {
+                continue;
+            }
+            score += 1000 + p.len();
+        }
+        if let Some(c) = z.city.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+            if d_city != c.to_lowercase() {
+                continue;
+            }
+            score += 100;
+        }
+        if let Some(p) = z.province.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            if d_prov != p.to_lowercase() {
+                continue;
+            }
+            score += 10;
+        }
+        // seri → zona dengan id terkecil (urutan query)
+        if best.as_ref().map_or(true, |(s, _)| score > *s) {
+            best = Some((score, z));
+        }
+    }
+    let Some((_, zone)) = best else {
+        return Err(ShippingError::NotCovered);
+    };
+
+    let base_fee = match zone.flat_fee {
+        Some(f) => f as i64,
+        None => {
+            // braket: max_weight_grams terkecil yang >= berat; NULL = tanpa batas atas
+            let rate = sqlx::query!(
+                r#"
+                SELECT fee FROM shipping_rates
+                WHERE zone_id=? AND (max_weight_grams IS NULL OR max_weight_grams >= ?)
+                ORDER BY max_weight_grams IS NULL, max_weight_grams ASC
+                LIMIT 1
+                "#,
+                zone.id,
+                weight_grams
+            )
+            .fetch_optional(&mut *conn)
+            .await
+            .unwrap();
+            match rate {
+                Some(r) => r.fee as i64,
+                None => return Err(ShippingError::TooHeavy { zone_id: zone.id, weight_grams }),
+            }
+        }
+    };
+
+    let free_shipping = zone.free_min_subtotal.is_some_and(|m| subtotal >= m as i64);
+    Ok(Quote {
+        zone_id: zone.id,
+        zone_name: zone.name,
+        weight_grams,
+        base_fee,
+        fee: if free_shipping { 0 } else { base_fee },
+        free_shipping,
+    })
+}
@@ -0,0 +1,207 @@
+// server/src/showcase.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Etalase live: host mem-pin satu produk per room (opsional harga khusus live +
+// hitung mundur). Pin baru menggantikan pin lama. Item yang masuk keranjang
+// selama produk di-pin dicatat dengan room-nya (cart_items.room) dan memakai
+// harga live bila ada.
+
+use std::time::Duration;
+
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{MySql, Pool};
+
+use crate::{room_send, ws_protocol::Body, AppState};
+
+// hitung mundur maksimum: 24 jam
+const MAX_PIN_SECS: u32 = 24 * 3600;
+
+/// Produk yang sedang di-pin di room.
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PinOut {
+    pub pin_id: i64,
+    pub product_id: i64,
+    pub code: Option<String>,
+    pub name: String,
+    pub image_url: Option<String>,
+    pub price_idr: i32,
+    // harga khusus selama pin aktif
+    pub live_price: Option<i32>,
+    // None = tanpa hitung mundur
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub(crate) enum PinError {
+    ProductMissing,
+    BadPrice,
+    BadDuration,
+    NotPinned,
+}
+
+impl PinError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PinError::ProductMissing => "product missing",
+            PinError::BadPrice => "live_price must be > 0",
+            PinError::BadDuration => "seconds must be 1..=86400",
+            PinError::NotPinned => "nothing pinned",
+        }
+    }
+
+    /// Balasan WS ke host.
+    pub fn into_body(self) -> Body {
+        Body::Error { code: "invalid", message: self.as_str().into() }
+    }
+}
+
+impl IntoResponse for PinError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            PinError::NotPinned => axum::http::StatusCode::NOT_FOUND,
+            _ => axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        (status, Json(serde_json::json!({ "error": self.as_str() }))).into_response()
+    }
+}
+
+/// Pin yang masih aktif di room (dikirim juga ke koneksi yang baru join).
+pub(crate) async fn current(db: &Pool<MySql>, room: &str) -> Option<PinOut> {
+    sqlx::query_as!(
+        PinOut,
+        r#"
+        SELECT rp.id AS pin_id, rp.product_id, p.code, p.name, p.image_url, p.price_idr,
+               rp.live_price, rp.ends_at
+        FROM room_pins rp JOIN products p ON p.id = rp.product_id
+        WHERE rp.room=? AND rp.unpinned_at IS NULL
+          AND (rp.ends_at IS NULL OR rp.ends_at > NOW())
+        ORDER BY rp.id DESC
+        LIMIT 1
+        "#,
+        room
+    )
+    .fetch_optional(db)
+    .await
+    .unwrap()
+}
+
+/// Pin aktif produk ini di `room` (room tempat viewer menonton) → Some(harga live,
+/// None = harga normal). Pin di room lain tidak berlaku.
+pub(crate) async fn active_for_product(
+    db: &Pool<MySql>,
+    product_id: i64,
+    room: &str,
+) -> Option<Option<i32>> {
+    sqlx::query!(
+        r#"
+        SELECT live_price FROM room_pins
+        WHERE product_id=? AND room=? AND unpinned_at IS NULL
+          AND (ends_at IS NULL OR ends_at > NOW())
+        ORDER BY id DESC
+        LIMIT 1
+        "#,
+        product_id,
+        room
+    )
+    .fetch_optional(db)
+    .await
+    .unwrap()
+    .map(|r| r.live_price)
+}
+
+/// Pin produk di room + siarkan `product_pinned`. Dengan `seconds`, pin otomatis
+/// dilepas (dan `product_unpinned` disiarkan) saat hitung mundur habis.
+pub(crate) async fn pin(
+    state: &AppState,
+    room: &str,
+    pinned_by: Option<i64>,
+    product_id: i64,
+    live_price: Option<i32>,
+    seconds: Option<u32>,
+) -> Result<PinOut, PinError> {
+    if live_price.is_some_and(|p| p <= 0) {
+        return Err(PinError::BadPrice);
+    }
+    if seconds.is_some_and(|s| s == 0 || s > MAX_PIN_SECS) {
+        return Err(PinError::BadDuration);
+    }
+    let exists = sqlx::query!("SELECT id FROM products WHERE id=? AND is_active=1", product_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap();
+    if exists.is_none() {
+        return Err(PinError::ProductMissing);
+    }
+
+    let ends_at = seconds.map(|s| Utc::now() + chrono::Duration::seconds(s as i64));
+    let mut tx = state.db.begin().await.unwrap();
+    let _ = sqlx::query!(
+        "UPDATE room_pins SET unpinned_at=NOW() WHERE room=? AND unpinned_at IS NULL",
+        room
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+    let pin_id = sqlx::query!(
+        "INSERT INTO room_pins(room, product_id, live_price, ends_at, pinned_by) VALUES(?,?,?,?,?)",
+        room,
+        product_id,
+        live_price,
+        ends_at,
+        pinned_by
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap()
+    .last_insert_id() as i64;
+    tx.commit().await.unwrap();
+
+    let pin = current(&state.db, room).await.ok_or(PinError::ProductMissing)?;
+    room_send(state, room, Body::ProductPinned(pin.clone())).await;
+
+    if let Some(secs) = seconds {
+        let state = state.clone();
+        let room = room.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(secs as u64)).await;
+            // hanya bila pin ini belum diganti / dilepas manual
+            release(&state, &room, pin_id, product_id).await;
+        });
+    }
+    Ok(pin)
+}
+
+/// Lepas pin aktif room.
+pub(crate) async fn unpin(state: &AppState, room: &str) -> Result<(), PinError> {
+    let Some(pin) = current(&state.db, room).await else {
+        return Err(PinError::NotPinned);
+    };
+    release(state, room, pin.pin_id, pin.product_id).await;
+    Ok(())
+}
+
+async fn release(state: &AppState, room: &str, pin_id: i64, product_id: i64) {
+    let res = sqlx::query!(
+        "UPDATE room_pins SET unpinned_at=NOW() WHERE id=? AND unpinned_at IS NULL",
+        pin_id
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    if res.rows_affected() > 0 {
+        room_send(state, room, Body::ProductUnpinned { pin_id, product_id }).await;
+    }
+}
@@ -0,0 +1,152 @@
+// server/src/streams.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Sesi siaran: satu baris `streams` per siaran di sebuah room (jadwal, mulai,
+// selesai). Room tetap string bebas; stream yang sedang live di room dipakai
+// untuk atribusi item keranjang (cart_items.stream_id) dan order (orders.stream_id)
+// sehingga omzet per siaran bisa dihitung.
+
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{MySql, MySqlConnection, Pool};
+
+use crate::{room_send, ws_protocol::Body, AppState};
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct StreamOut {
+    pub id: i64,
+    pub room: String,
+    pub title: String,
+    pub host_user_id: Option<i64>,
+    pub host: Option<String>,
+    pub scheduled_at: Option<DateTime<Utc>>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    // "scheduled" | "live" | "ended"
+    pub status: String,
+}
+
+#[derive(Debug)]
+pub(crate) enum StreamError {
+    NotFound,
+    // room ini sudah punya stream live
+    RoomLive,
+    NotLive,
+    Ended,
+}
+
+impl StreamError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StreamError::NotFound => "stream not found",
+            StreamError::RoomLive => "another stream is live in this room",
+            StreamError::NotLive => "stream is not live",
+            StreamError::Ended => "stream already ended",
+        }
+    }
+}
+
+impl IntoResponse for StreamError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            StreamError::NotFound => axum::http::StatusCode::NOT_FOUND,
+            _ => axum::http::StatusCode::CONFLICT,
+        };
+        (status, Json(serde_json::json!({ "error": self.as_str() }))).into_response()
+    }
+}
+
+pub(crate) async fn get(db: &Pool<MySql>, id: i64) -> Option<StreamOut> {
+    sqlx::query_as!(
+        StreamOut,
+        r#"
+        SELECT s.id, s.room, s.title, s.host_user_id, u.name AS `host?`,
+               s.scheduled_at, s.started_at, s.ended_at, s.status
+        FROM streams s LEFT JOIN users u ON u.id = s.host_user_id
+        WHERE s.id=?
+        "#,
+        id
+    )
+    .fetch_optional(db)
+    .await
+    .unwrap()
+}
+
+/// Stream yang sedang live di room (lengkap, untuk viewer yang baru join).
+pub(crate) async fn current(db: &Pool<MySql>, room: &str) -> Option<StreamOut> {
+    let mut conn = db.acquire().await.unwrap();
+    let id = live_in_room(&mut conn, room).await?;
+    drop(conn);
+    get(db, id).await
+}
+
+/// Stream yang sedang live di room (untuk atribusi penjualan).
+pub(crate) async fn live_in_room(conn: &mut MySqlConnection, room: &str) -> Option<i64> {
+    sqlx::query!(
+        "SELECT id FROM streams WHERE room=? AND status='live' ORDER BY id DESC LIMIT 1",
+        room
+    )
+    .fetch_optional(conn)
+    .await
+    .unwrap()
+    .map(|r| r.id)
+}
+
+/// Mulai stream terjadwal (scheduled → live) + siarkan `stream` ke room.
+pub(crate) async fn start(state: &AppState, id: i64) -> Result<StreamOut, StreamError> {
+    let Some(s) = get(&state.db, id).await else {
+        return Err(StreamError::NotFound);
+    };
+    match s.status.as_str() {
+        "live" => return Ok(s),
+        "ended" => return Err(StreamError::Ended),
+        _ => {}
+    }
+    let mut conn = state.db.acquire().await.unwrap();
+    if live_in_room(&mut conn, &s.room).await.is_some() {
+        return Err(StreamError::RoomLive);
+    }
+    let _ = sqlx::query!(
+        "UPDATE streams SET status='live', started_at=NOW() WHERE id=? AND status='scheduled'",
+        id
+    )
+    .execute(&mut *conn)
+    .await
+    .unwrap();
+    drop(conn);
+
+    let s = get(&state.db, id).await.ok_or(StreamError::NotFound)?;
+    room_send(state, &s.room, Body::Stream(s.clone())).await;
+    Ok(s)
+}
+
+/// Akhiri stream live (live → ended) + siarkan `stream` ke room.
+pub(crate) async fn stop(state: &AppState, id: i64) -> Result<StreamOut, StreamError> {
+    let res = sqlx::query!(
+        "UPDATE streams SET status='ended', ended_at=NOW() WHERE id=? AND status='live'",
+        id
+    )
+    .execute(&state.db)
+    .await
+    .unwrap();
+    let Some(s) = get(&state.db, id).await else {
+        return Err(StreamError::NotFound);
+    };
+    if res.rows_affected() == 0 {
+        return Err(if s.status == "ended" { StreamError::Ended } else { StreamError::NotLive });
+    }
+    room_send(state, &s.room, Body::Stream(s.clone())).await;
+    Ok(s)
+}
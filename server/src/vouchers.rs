@@ -0,0 +1,205 @@
+// server/src/vouchers.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::MySqlConnection;
+
+/// Satu baris keranjang yang dinilai voucher.
+pub(crate) struct Line {
+    pub product_id: i64,
+    pub line_total: i64,
+    // room live yang ditonton saat item dimasukkan (`cart_items.watch_room`)
+    pub room: Option<String>,
+}
+
+/// Voucher yang lolos validasi + potongan untuk keranjang saat ini.
+#[derive(Serialize, Debug)]
+pub(crate) struct Applied {
+    pub voucher_id: i64,
+    pub code: String,
+    pub discount: i64,
+}
+
+/// Alasan voucher tidak bisa dipakai.
+#[derive(Debug)]
+pub(crate) enum VoucherError {
+    NotFound,
+    Inactive,
+    NotStarted,
+    Expired,
+    MinSubtotal { min: i64 },
+    UsageLimit,
+    UserLimit,
+    // voucher khusus produk tertentu, produk itu tidak ada di keranjang
+    NoEligibleItem,
+    // voucher khusus room live tertentu, tidak ada item dari room itu
+    WrongRoom,
+}
+
+impl VoucherError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VoucherError::NotFound => "voucher not found",
+            VoucherError::Inactive => "voucher inactive",
+            VoucherError::NotStarted => "voucher not started yet",
+            VoucherError::Expired => "voucher expired",
+            VoucherError::MinSubtotal { .. } => "subtotal below voucher minimum",
+            VoucherError::UsageLimit => "voucher usage limit reached",
+            VoucherError::UserLimit => "voucher already used",
+            VoucherError::NoEligibleItem => "no eligible item for voucher",
+            VoucherError::WrongRoom => "voucher not valid for this live room",
+        }
+    }
+}
+
+impl IntoResponse for VoucherError {
+    fn into_response(self) -> Response {
+        let mut body = serde_json::json!({ "error": self.as_str() });
+        if let VoucherError::MinSubtotal { min } = self {
+            body["min_subtotal"] = min.into();
+        }
+        (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response()
+    }
+}
+
+/// Cari voucher aktif berdasarkan kode (case-insensitive).
+pub(crate) async fn find_by_code(conn: &mut MySqlConnection, code: &str) -> Option<i64> {
+    sqlx::query!(
+        "SELECT id FROM vouchers WHERE code=? AND is_active=1 LIMIT 1",
+        code.trim().to_uppercase()
+    )
+    .fetch_optional(conn)
+    .await
+    .unwrap()
+    .map(|v| v.id)
+}
+
+/// Validasi voucher untuk user + isi keranjang dan hitung potongannya.
+/// `lock` = kunci baris voucher (FOR UPDATE) saat checkout agar batas pemakaian
+/// tidak terlewati oleh checkout paralel. Pemakaian dihitung dari order yang
+/// tidak dibatalkan/dihapus, jadi order batal otomatis mengembalikan kuota.
+/// Voucher khusus room hanya berlaku untuk item yang dimasukkan saat menonton room itu
+/// (`cart_items.watch_room`, dicatat server saat add dengan room / klaim / lelang).
+pub(crate) async fn evaluate(
+    conn: &mut MySqlConnection,
+    voucher_id: i64,
+    user_id: i64,
+    lines: &[Line],
+    lock: bool,
+) -> Result<Applied, VoucherError> {
+    let sql = if lock {
+        "SELECT id, code, kind, value, min_subtotal, max_discount, usage_limit, per_user_limit,
+                starts_at, ends_at, product_id, room, is_active
+         FROM vouchers WHERE id=? FOR UPDATE"
+    } else {
+        "SELECT id, code, kind, value, min_subtotal, max_discount, usage_limit, per_user_limit,
+                starts_at, ends_at, product_id, room, is_active
+         FROM vouchers WHERE id=?"
+    };
+    let v: Option<VoucherRow> = sqlx::query_as(sql)
+        .bind(voucher_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .unwrap();
+    let Some(v) = v else {
+        return Err(VoucherError::NotFound);
+    };
+
+    if !v.is_active {
+        return Err(VoucherError::Inactive);
+    }
+    let now = Utc::now();
+    if v.starts_at.is_some_and(|t| now < t) {
+        return Err(VoucherError::NotStarted);
+    }
+    if v.ends_at.is_some_and(|t| now > t) {
+        return Err(VoucherError::Expired);
+    }
+
+    // voucher room: hanya item yang masuk keranjang dari room itu
+    let lines: Vec<&Line> = match &v.room {
+        None => lines.iter().collect(),
+        Some(room) => lines.iter().filter(|l| l.room.as_ref() == Some(room)).collect(),
+    };
+    if lines.is_empty() {
+        return Err(VoucherError::WrongRoom);
+    }
+
+    // subtotal yang berhak potongan (semua item, atau hanya produk tertentu)
+    let eligible: i64 = lines
+        .iter()
+        .filter(|l| v.product_id.map_or(true, |p| p == l.product_id))
+        .map(|l| l.line_total)
+        .sum();
+    if v.product_id.is_some() && eligible == 0 {
+        return Err(VoucherError::NoEligibleItem);
+    }
+    if eligible < v.min_subtotal as i64 {
+        return Err(VoucherError::MinSubtotal { min: v.min_subtotal as i64 });
+    }
+
+    if v.usage_limit.is_some() || v.per_user_limit.is_some() {
+        let used = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS `total!: i64`,
+                   COALESCE(SUM(user_id = ?), 0) AS `mine!: i64`
+            FROM orders
+            WHERE voucher_id=? AND status NOT IN ('cancelled','deleted')
+            "#,
+            user_id,
+            v.id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        if v.usage_limit.is_some_and(|lim| used.total >= lim as i64) {
+            return Err(VoucherError::UsageLimit);
+        }
+        if v.per_user_limit.is_some_and(|lim| used.mine >= lim as i64) {
+            return Err(VoucherError::UserLimit);
+        }
+    }
+
+    let mut discount = if v.kind == "percent" {
+        eligible * v.value as i64 / 100
+    } else {
+        v.value as i64
+    };
+    if let Some(max) = v.max_discount {
+        discount = discount.min(max as i64);
+    }
+    // tidak pernah melebihi nilai barang yang berhak
+    discount = discount.clamp(0, eligible);
+
+    Ok(Applied { voucher_id: v.id, code: v.code, discount })
+}
+
+#[derive(sqlx::FromRow)]
+struct VoucherRow {
+    id: i64,
+    code: String,
+    kind: String,
+    value: i32,
+    min_subtotal: i32,
+    max_discount: Option<i32>,
+    usage_limit: Option<i32>,
+    per_user_limit: Option<i32>,
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+    product_id: Option<i64>,
+    room: Option<String>,
+    is_active: bool,
+}
@@ -0,0 +1,260 @@
+// server/src/ws_conn.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Perlengkapan koneksi WS (dipakai handle_socket):
+// - Outbox: antrian keluar per koneksi yang dibatasi; frame video (`f`) dibuang lebih
+//   dulu, pesan penting (chat, state, signaling) tidak pernah diam-diam hilang —
+//   antrian penuh pesan penting = client terlalu lambat → resync.
+// - Limits: interval ping & batas idle (WS_PING_SECS, WS_IDLE_SECS, WS_QUEUE).
+// - ConnGuard: hitungan viewer, sesi tonton & topik bus dibereskan di jalur keluar
+//   mana pun (break, error kirim, timeout, panic); koneksi room yang bisa di-resume
+//   diparkir dulu (lihat resume.rs).
+
+use std::{
+    collections::VecDeque,
+    env,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{sync::Notify, task::JoinHandle};
+
+use crate::{
+    analytics,
+    bus::{Presence, Topic},
+    events, resume, room_send,
+    ws_protocol::Body,
+    AppState,
+};
+
+// frame yang ditahan per koneksi; frame lebih lama tidak berguna bagi viewer
+const FRAME_SLOTS: usize = 2;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    // server mengirim Ping tiap selang ini
+    pub ping: Duration,
+    // tidak ada pesan masuk (termasuk Pong) selama ini → koneksi dianggap mati
+    pub idle: Duration,
+    // panjang maksimum antrian keluar per koneksi
+    pub queue: usize,
+}
+
+/// Baca WS_PING_SECS (default 20), WS_IDLE_SECS (default 60, minimal 2× ping) dan
+/// WS_QUEUE (default 256).
+pub(crate) fn limits_from_env() -> Limits {
+    let num = |key: &str, default: u64| {
+        env::var(key).ok().and_then(|v| v.parse::<u64>().ok()).filter(|n| *n > 0).unwrap_or(default)
+    };
+    let ping = num("WS_PING_SECS", 20);
+    let idle = num("WS_IDLE_SECS", 60).max(ping * 2);
+    Limits {
+        ping: Duration::from_secs(ping),
+        idle: Duration::from_secs(idle),
+        queue: num("WS_QUEUE", 256).max(16) as usize,
+    }
+}
+
+/* ===================== Outbox ===================== */
+
+pub(crate) struct Outbox {
+    cap: usize,
+    inner: Mutex<Inner>,
+    // ada pesan baru / ditutup (untuk pop)
+    ready: Notify,
+    // ditutup (untuk reader & pump)
+    closed_n: Notify,
+}
+
+#[derive(Default)]
+struct Inner {
+    // (frame?, json)
+    queue: VecDeque<(bool, String)>,
+    frames: usize,
+    closed: bool,
+}
+
+impl Inner {
+    fn evict_frame(&mut self) -> bool {
+        match self.queue.iter().position(|(f, _)| *f) {
+            Some(i) => {
+                self.queue.remove(i);
+                self.frames -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Outbox {
+    pub fn new(cap: usize) -> Arc<Self> {
+        Arc::new(Outbox {
+            cap,
+            inner: Mutex::new(Inner::default()),
+            ready: Notify::new(),
+            closed_n: Notify::new(),
+        })
+    }
+
+    /// Pesan penting. false = antrian penuh pesan penting (pesan tidak masuk).
+    pub fn push(&self, msg: String) -> bool {
+        let mut q = self.inner.lock().unwrap();
+        if q.closed {
+            return true;
+        }
+        if q.queue.len() >= self.cap && !q.evict_frame() {
+            return false;
+        }
+        q.queue.push_back((false, msg));
+        drop(q);
+        self.ready.notify_one();
+        true
+    }
+
+    /// Frame video: hanya FRAME_SLOTS terbaru yang ditahan, dibuang bila antrian penuh.
+    pub fn push_frame(&self, msg: String) {
+        let mut q = self.inner.lock().unwrap();
+        if q.closed {
+            return;
+        }
+        if (q.frames >= FRAME_SLOTS || q.queue.len() >= self.cap) && !q.evict_frame() {
+            // penuh pesan penting: frame ini yang dibuang
+            return;
+        }
+        q.queue.push_back((true, msg));
+        q.frames += 1;
+        drop(q);
+        self.ready.notify_one();
+    }
+
+    /// Kosongkan antrian (sebelum resync); jumlah pesan penting yang dibuang.
+    pub fn clear(&self) -> u64 {
+        let mut q = self.inner.lock().unwrap();
+        let important = q.queue.len() - q.frames;
+        q.queue.clear();
+        q.frames = 0;
+        important as u64
+    }
+
+    /// Pesan berikutnya; None setelah ditutup.
+    pub async fn pop(&self) -> Option<String> {
+        loop {
+            {
+                let mut q = self.inner.lock().unwrap();
+                if let Some((frame, msg)) = q.queue.pop_front() {
+                    if frame {
+                        q.frames -= 1;
+                    }
+                    return Some(msg);
+                }
+                if q.closed {
+                    return None;
+                }
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    pub fn close(&self) {
+        self.inner.lock().unwrap().closed = true;
+        self.ready.notify_one();
+        self.closed_n.notify_waiters();
+    }
+
+    /// Selesai saat outbox ditutup (writer berhenti / client terlalu lambat).
+    pub async fn closed(&self) {
+        let n = self.closed_n.notified();
+        if self.inner.lock().unwrap().closed {
+            return;
+        }
+        n.await;
+    }
+}
+
+/* ===================== ConnGuard ===================== */
+
+/// Pembersihan koneksi saat di-drop: hentikan task, kurangi hitungan viewer (atau
+/// parkir sesi untuk resume), tutup sesi tonton, lepas topik bus. Drop tidak bisa
+/// await → dikerjakan di task baru.
+pub(crate) struct ConnGuard {
+    pub state: AppState,
+    pub room: String,
+    pub user_id: Option<i64>,
+    // presence viewer room dipegang koneksi ini (bus.join / hasil resume)
+    pub presence: Option<Presence>,
+    pub view_id: Option<i64>,
+    // token resume koneksi ini (None = tidak bisa di-resume)
+    pub resume: Option<String>,
+    pub topics: Vec<Topic>,
+    pub tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for ConnGuard {
+    fn drop(&mut self) {
+        let tasks = std::mem::take(&mut self.tasks);
+        for t in &tasks {
+            t.abort();
+        }
+        let state = self.state.clone();
+        let room = std::mem::take(&mut self.room);
+        let (user_id, view_id) = (self.user_id, self.view_id);
+        let presence = self.presence.take();
+        let token = self.resume.take();
+        let topics = std::mem::take(&mut self.topics);
+        tokio::spawn(async move {
+            // tunggu task benar-benar berhenti agar receiver topik sudah di-drop
+            for t in tasks {
+                let _ = t.await;
+            }
+            for t in &topics {
+                state.bus.release(t).await;
+            }
+            match (token, presence) {
+                (Some(token), Some(p)) => resume::park(&state, token, room, user_id, view_id, p).await,
+                (_, presence) => {
+                    if let Some(p) = presence {
+                        viewer_left(&state, &room, &p.key).await;
+                    }
+                    if let Some(id) = view_id {
+                        analytics::view_ended(&state.db, id).await;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Satu koneksi viewer masuk room. Join, jumlah room & total hanya dikabarkan pada
+/// koneksi pertama viewer itu (tab lain tidak dihitung lagi).
+pub(crate) async fn viewer_joined(state: &AppState, room: &str, viewer: &Presence) {
+    if state.bus.join(room, viewer).await {
+        events::to_admin(state, Some(room), Body::ViewerJoin).await;
+        counts_changed(state, room).await;
+    }
+}
+
+/// Satu koneksi viewer keluar room; leave dikabarkan pada koneksi terakhirnya.
+pub(crate) async fn viewer_left(state: &AppState, room: &str, key: &str) {
+    if state.bus.leave(room, key).await {
+        events::to_admin(state, Some(room), Body::ViewerLeave).await;
+        counts_changed(state, room).await;
+    }
+}
+
+// jumlah viewer unik room → room & admin, total semua room → admin
+async fn counts_changed(state: &AppState, room: &str) {
+    let counts = state.bus.viewer_counts().await;
+    let n = counts.get(room).copied().unwrap_or(0);
+    room_send(state, room, Body::ViewerCount { n }).await;
+    events::to_admin(state, Some(room), Body::ViewerCount { n }).await;
+    events::to_admin(state, None, Body::ViewerTotal { n: counts.values().sum() }).await;
+}
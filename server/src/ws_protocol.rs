@@ -0,0 +1,367 @@
+// server/src/ws_protocol.rs
+/*
+=============================================================================
+Project : LiveStreamShop Rust — sell via live stream, chat & checkout on your site.
+Author  : Kukuh Tripamungkas Wicaksono (Kukuh TW)
+Email   : kukuhtw@gmail.com
+WhatsApp: https://wa.me/628129893706
+LinkedIn: https://id.linkedin.com/in/kukuhtw
+=============================================================================
+*/
+
+// Protokol WebSocket (versi 1). Semua pesan JSON dengan field `t` sebagai tipe.
+//
+// Client → server: `ClientMsg` (boleh menyertakan `"v":1`). Field identitas seperti
+// `user` / `room` dari client diabaikan.
+// Server → client: `Envelope` = `{v, id, ts, room?, user?, user_id?, role?, t, ...}`;
+// `id`, `ts`, dan identitas pengirim selalu dicap server.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    auctions::AuctionOut, chat::ChatOut, claims::ClaimOut, flash_sales::ActiveSale,
+    moderation::ModAction, room_roles::RoomRole, showcase::PinOut, streams::StreamOut,
+};
+
+pub(crate) const PROTOCOL_VERSION: u8 = 1;
+
+// batas ukuran per tipe (byte, setelah di-decode)
+const MAX_FRAME: usize = 2_000_000; // data URL JPEG
+const MAX_TEXT: usize = 500;
+const MAX_SDP: usize = 64 * 1024;
+const MAX_ICE: usize = 4 * 1024;
+// batas satu pesan mentah sebelum di-parse
+pub(crate) const MAX_MESSAGE: usize = MAX_FRAME + 1024;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Pesan yang boleh dikirim client.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "t")]
+pub(crate) enum ClientMsg {
+    #[serde(rename = "f")]
+    Frame { d: String },
+    #[serde(rename = "c")]
+    Chat { text: String },
+    #[serde(rename = "sys")]
+    Sys { text: String },
+    #[serde(rename = "offer")]
+    Offer { sdp: String },
+    #[serde(rename = "answer")]
+    Answer { sdp: String },
+    #[serde(rename = "ice")]
+    Ice { candidate: serde_json::Value },
+    #[serde(rename = "viewer_enter")]
+    ViewerEnter,
+    // aksi moderator (host / moderator), diproses server dan tidak diteruskan
+    #[serde(rename = "mod")]
+    Moderate {
+        #[serde(flatten)]
+        action: ModAction,
+    },
+    // etalase live (host): pin produk, opsional harga live + hitung mundur (detik)
+    #[serde(rename = "pin")]
+    Pin { product_id: i64, live_price: Option<i32>, seconds: Option<u32> },
+    #[serde(rename = "unpin")]
+    Unpin,
+    // tawaran lelang (viewer login); urutan & validasi oleh server
+    #[serde(rename = "bid")]
+    Bid { auction_id: i64, amount: i32 },
+}
+
+#[derive(Deserialize)]
+struct ClientIn {
+    v: Option<u8>,
+    #[serde(flatten)]
+    msg: ClientMsg,
+}
+
+impl ClientMsg {
+    /// Nilai `t` (dipakai untuk cek hak peran).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ClientMsg::Frame { .. } => "f",
+            ClientMsg::Chat { .. } => "c",
+            ClientMsg::Sys { .. } => "sys",
+            ClientMsg::Offer { .. } => "offer",
+            ClientMsg::Answer { .. } => "answer",
+            ClientMsg::Ice { .. } => "ice",
+            ClientMsg::ViewerEnter => "viewer_enter",
+            ClientMsg::Moderate { .. } => "mod",
+            ClientMsg::Pin { .. } => "pin",
+            ClientMsg::Unpin => "unpin",
+            ClientMsg::Bid { .. } => "bid",
+        }
+    }
+
+    fn validate(&self) -> Result<(), ProtoError> {
+        let too_large = |len: usize, max: usize| {
+            if len > max {
+                Err(ProtoError::TooLarge { kind: self.kind(), max })
+            } else {
+                Ok(())
+            }
+        };
+        match self {
+            ClientMsg::Frame { d } => too_large(d.len(), MAX_FRAME),
+            ClientMsg::Chat { text } | ClientMsg::Sys { text } => {
+                if text.trim().is_empty() {
+                    return Err(ProtoError::Invalid("text required".into()));
+                }
+                too_large(text.chars().count(), MAX_TEXT)
+            }
+            ClientMsg::Offer { sdp } | ClientMsg::Answer { sdp } => {
+                if sdp.is_empty() {
+                    return Err(ProtoError::Invalid("sdp required".into()));
+                }
+                too_large(sdp.len(), MAX_SDP)
+            }
+            ClientMsg::Ice { candidate } => {
+                if !candidate.is_object() {
+                    return Err(ProtoError::Invalid("candidate must be an object".into()));
+                }
+                too_large(candidate.to_string().len(), MAX_ICE)
+            }
+            ClientMsg::Bid { amount, .. } => {
+                if *amount <= 0 {
+                    return Err(ProtoError::Invalid("amount must be > 0".into()));
+                }
+                Ok(())
+            }
+            ClientMsg::ViewerEnter
+            | ClientMsg::Moderate { .. }
+            | ClientMsg::Pin { .. }
+            | ClientMsg::Unpin => Ok(()),
+        }
+    }
+
+    /// Ubah menjadi body siaran (isi dari client, identitas ditambah server).
+    /// None = perintah yang diproses server (moderasi, pin, lelang), tidak diteruskan.
+    pub fn into_body(self) -> Option<Body> {
+        let body = match self {
+            ClientMsg::Frame { d } => Body::Frame { d },
+            ClientMsg::Chat { text } => Body::Chat { text: text.trim().to_string(), chat_id: None },
+            ClientMsg::Sys { text } => Body::Sys { text },
+            ClientMsg::Offer { sdp } => Body::Offer { sdp },
+            ClientMsg::Answer { sdp } => Body::Answer { sdp },
+            ClientMsg::Ice { candidate } => Body::Ice { candidate },
+            ClientMsg::ViewerEnter => Body::ViewerEnter,
+            ClientMsg::Moderate { .. } | ClientMsg::Pin { .. } | ClientMsg::Unpin | ClientMsg::Bid { .. } => {
+                return None;
+            }
+        };
+        Some(body)
+    }
+}
+
+/// Parse + validasi satu pesan teks dari client.
+pub(crate) fn parse_client(txt: &str) -> Result<ClientMsg, ProtoError> {
+    if txt.len() > MAX_MESSAGE {
+        return Err(ProtoError::TooLarge { kind: "message", max: MAX_MESSAGE });
+    }
+    let inp: ClientIn =
+        serde_json::from_str(txt).map_err(|e| ProtoError::Malformed(e.to_string()))?;
+    if inp.v.is_some_and(|v| v != PROTOCOL_VERSION) {
+        return Err(ProtoError::UnsupportedVersion);
+    }
+    inp.msg.validate()?;
+    Ok(inp.msg)
+}
+
+/// Isi pesan server → client.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "t")]
+pub(crate) enum Body {
+    // diteruskan dari client
+    #[serde(rename = "f")]
+    Frame { d: String },
+    #[serde(rename = "c")]
+    Chat {
+        text: String,
+        // id baris chat_messages (setelah disimpan)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        chat_id: Option<i64>,
+    },
+    #[serde(rename = "sys")]
+    Sys { text: String },
+    #[serde(rename = "offer")]
+    Offer { sdp: String },
+    #[serde(rename = "answer")]
+    Answer { sdp: String },
+    #[serde(rename = "ice")]
+    Ice { candidate: serde_json::Value },
+    #[serde(rename = "viewer_enter")]
+    ViewerEnter,
+
+    // dari server
+    // riwayat chat terakhir, dikirim sekali ke koneksi yang baru join
+    #[serde(rename = "chat_history")]
+    ChatHistory { messages: Vec<ChatOut> },
+    // retraksi pesan yang dihapus moderator
+    #[serde(rename = "chat_deleted")]
+    ChatDeleted { chat_id: i64 },
+    // mute / ban baru; expires_at None = sanksi dicabut
+    #[serde(rename = "sanction")]
+    Sanction { user_id: i64, kind: &'static str, expires_at: Option<DateTime<Utc>> },
+    // 0 = slow mode mati
+    #[serde(rename = "slow_mode")]
+    SlowMode { seconds: u32 },
+    // hasil "KEEP A12 2", hanya ke pengirim
+    #[serde(rename = "claim_result")]
+    ClaimResult(ClaimOut),
+    // feed klaim untuk host/moderator
+    #[serde(rename = "claim")]
+    Claim(ClaimOut),
+    // etalase live: produk yang sedang di-pin (juga dikirim ke koneksi yang baru join)
+    #[serde(rename = "product_pinned")]
+    ProductPinned(PinOut),
+    #[serde(rename = "product_unpinned")]
+    ProductUnpinned { pin_id: i64, product_id: i64 },
+    // lelang dibuka (juga dikirim ke koneksi yang baru join)
+    #[serde(rename = "auction")]
+    Auction(AuctionOut),
+    // tawaran tertinggi baru; extended = waktu diperpanjang (anti-sniping)
+    #[serde(rename = "auction_bid")]
+    AuctionBid {
+        auction_id: i64,
+        amount: i32,
+        user_id: i64,
+        user: String,
+        bid_count: i32,
+        min_next: i64,
+        ends_at: DateTime<Utc>,
+        extended: bool,
+    },
+    // status "closed" (pemenang, bila ada, sudah dapat barang di keranjang) | "cancelled"
+    #[serde(rename = "auction_closed")]
+    AuctionClosed {
+        auction_id: i64,
+        product_id: i64,
+        status: String,
+        winner_user_id: Option<i64>,
+        winner: Option<String>,
+        amount: Option<i32>,
+    },
+    // flash sale mulai / selesai (sale khusus room hanya ke room itu)
+    #[serde(rename = "flash_sale_started")]
+    FlashSaleStarted(ActiveSale),
+    #[serde(rename = "flash_sale_ended")]
+    FlashSaleEnded { id: i64, product_id: i64 },
+    // sesi siaran mulai / selesai (juga dikirim ke koneksi yang baru join bila live)
+    #[serde(rename = "stream")]
+    Stream(StreamOut),
+    // resume: token sesi untuk menyambung ulang (`?resume=&since=`); resumed = koneksi
+    // ini melanjutkan sesi lama (pesan terlewat menyusul)
+    #[serde(rename = "welcome")]
+    Welcome {
+        role: RoomRole,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        resume: Option<String>,
+        resumed: bool,
+    },
+    // koneksi tertinggal: `missed` pesan terlewat (0 = tidak diketahui, mis. resume
+    // dari buffer yang sudah terpangkas); state (riwayat chat, pin, lelang, sesi)
+    // dikirim ulang setelah ini → client buang tampilan lama
+    #[serde(rename = "resync")]
+    Resync { missed: u64 },
+    #[serde(rename = "error")]
+    Error { code: &'static str, message: String },
+    // viewer unik pertama kali / terakhir kali terhubung ke room (bukan per tab)
+    #[serde(rename = "viewer_join")]
+    ViewerJoin,
+    #[serde(rename = "viewer_leave")]
+    ViewerLeave,
+    // jumlah viewer unik room (ke room itu + admin)
+    #[serde(rename = "viewer_count")]
+    ViewerCount { n: usize },
+    #[serde(rename = "viewer_total")]
+    ViewerTotal { n: usize },
+    #[serde(rename = "order")]
+    Order { order_id: i64 },
+    #[serde(rename = "order_update")]
+    OrderUpdate { order_id: i64 },
+    #[serde(rename = "order_deleted")]
+    OrderDeleted { order_id: i64 },
+}
+
+/// Identitas pengirim yang dicap server.
+#[derive(Debug, Clone)]
+pub(crate) struct Sender {
+    pub user_id: Option<i64>,
+    pub name: Option<String>,
+    pub role: RoomRole,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct Envelope {
+    v: u8,
+    id: u64,
+    // unix ms
+    ts: i64,
+    // pesan lewat topik room juga membawa `seq` (nomor urut per room, dipakai client
+    // untuk resume) yang disisipkan bus: Bus::publish_replayable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    room: Option<String>,
+    // nama tampilan pengirim (kompatibel dengan client lama yang membaca `msg.user`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<RoomRole>,
+    #[serde(flatten)]
+    body: Body,
+}
+
+impl Envelope {
+    pub fn new(room: Option<&str>, from: Option<&Sender>, body: Body) -> Self {
+        Envelope {
+            v: PROTOCOL_VERSION,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            ts: chrono::Utc::now().timestamp_millis(),
+            room: room.map(str::to_string),
+            user: from.and_then(|s| s.name.clone()),
+            user_id: from.and_then(|s| s.user_id),
+            role: from.map(|s| s.role),
+            body,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("envelope serializes")
+    }
+}
+
+/// Pesan server tanpa pengirim (event global, notifikasi order, dll).
+pub(crate) fn event(room: Option<&str>, body: Body) -> String {
+    Envelope::new(room, None, body).to_json()
+}
+
+/// Kesalahan pesan dari client; dibalas ke pengirim sebagai `{"t":"error"}`.
+#[derive(Debug)]
+pub(crate) enum ProtoError {
+    Malformed(String),
+    Invalid(String),
+    TooLarge { kind: &'static str, max: usize },
+    UnsupportedVersion,
+    Forbidden { kind: &'static str },
+}
+
+impl ProtoError {
+    pub fn into_body(self) -> Body {
+        let (code, message) = match self {
+            ProtoError::Malformed(m) => ("malformed", m),
+            ProtoError::Invalid(m) => ("invalid", m),
+            ProtoError::TooLarge { kind, max } => ("too_large", format!("{kind} exceeds {max} bytes")),
+            ProtoError::UnsupportedVersion => {
+                ("unsupported_version", format!("server speaks v{PROTOCOL_VERSION}"))
+            }
+            ProtoError::Forbidden { kind } => ("forbidden", format!("role may not send '{kind}'")),
+        };
+        Body::Error { code, message }
+    }
+}
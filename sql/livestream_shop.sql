@@ -37,6 +37,46 @@ CREATE TABLE `admins` (
 
 -- --------------------------------------------------------
 
+--
+-- Table structure for table `auction_bids`
+--
+
+CREATE TABLE `auction_bids` (
+  `id` bigint(20) NOT NULL,
+  `auction_id` bigint(20) NOT NULL,
+  `user_id` bigint(20) NOT NULL,
+  `amount` int(11) NOT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `auctions`
+--
+
+CREATE TABLE `auctions` (
+  `id` bigint(20) NOT NULL,
+  `room` varchar(64) NOT NULL,
+  `product_id` bigint(20) NOT NULL,
+  `variant_id` bigint(20) DEFAULT NULL,
+  `start_price` int(11) NOT NULL,
+  `min_increment` int(11) NOT NULL,
+  `extend_secs` int(11) NOT NULL DEFAULT 30,
+  `ends_at` timestamp NOT NULL DEFAULT current_timestamp(),
+  `status` enum('running','closed','cancelled') NOT NULL DEFAULT 'running',
+  `current_bid` int(11) DEFAULT NULL,
+  `current_bidder_id` bigint(20) DEFAULT NULL,
+  `bid_count` int(11) NOT NULL DEFAULT 0,
+  `cart_id` bigint(20) DEFAULT NULL,
+  `created_by` bigint(20) DEFAULT NULL,
+  `closed_at` timestamp NULL DEFAULT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp(),
+  `running_room` varchar(64) GENERATED ALWAYS AS (if(`status` = 'running',`room`,NULL)) STORED
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
 --
 -- Table structure for table `carts`
 --
@@ -45,6 +85,7 @@ CREATE TABLE `carts` (
   `id` bigint(20) NOT NULL,
   `user_id` bigint(20) NOT NULL,
   `status` enum('open','ordered') NOT NULL DEFAULT 'open',
+  `voucher_id` bigint(20) DEFAULT NULL,
   `created_at` timestamp NOT NULL DEFAULT current_timestamp()
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
 
@@ -58,8 +99,88 @@ CREATE TABLE `cart_items` (
   `id` bigint(20) NOT NULL,
   `cart_id` bigint(20) NOT NULL,
   `product_id` bigint(20) NOT NULL,
+  `variant_id` bigint(20) DEFAULT NULL,
   `qty` int(11) NOT NULL,
   `price_at_add` int(11) NOT NULL,
+  `room` varchar(64) DEFAULT NULL,
+  `watch_room` varchar(64) DEFAULT NULL,
+  `auction_id` bigint(20) DEFAULT NULL,
+  `flash_sale_id` bigint(20) DEFAULT NULL,
+  `stream_id` bigint(20) DEFAULT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp(),
+  `reserved_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `chat_claims`
+--
+
+CREATE TABLE `chat_claims` (
+  `id` bigint(20) NOT NULL,
+  `room` varchar(64) NOT NULL,
+  `chat_id` bigint(20) DEFAULT NULL,
+  `user_id` bigint(20) NOT NULL,
+  `code` varchar(64) NOT NULL,
+  `qty` int(11) NOT NULL,
+  `product_id` bigint(20) DEFAULT NULL,
+  `variant_id` bigint(20) DEFAULT NULL,
+  `status` enum('added','failed') NOT NULL,
+  `error` varchar(120) DEFAULT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `chat_messages`
+--
+
+CREATE TABLE `chat_messages` (
+  `id` bigint(20) NOT NULL,
+  `room` varchar(64) NOT NULL,
+  `user_id` bigint(20) DEFAULT NULL,
+  `display_name` varchar(80) NOT NULL,
+  `text` varchar(1000) NOT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp(),
+  `deleted_at` timestamp NULL DEFAULT NULL,
+  `deleted_by` bigint(20) DEFAULT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `flash_sales`
+--
+
+CREATE TABLE `flash_sales` (
+  `id` bigint(20) NOT NULL,
+  `product_id` bigint(20) NOT NULL,
+  `sale_price` int(11) NOT NULL,
+  `starts_at` timestamp NOT NULL DEFAULT current_timestamp(),
+  `ends_at` timestamp NOT NULL DEFAULT current_timestamp(),
+  `qty_cap` int(11) DEFAULT NULL,
+  `room` varchar(64) DEFAULT NULL,
+  `is_active` tinyint(1) NOT NULL DEFAULT 1,
+  `notified` enum('none','started','ended') NOT NULL DEFAULT 'none',
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `moderation_log`
+--
+
+CREATE TABLE `moderation_log` (
+  `id` bigint(20) NOT NULL,
+  `room` varchar(64) NOT NULL,
+  `actor_id` bigint(20) DEFAULT NULL,
+  `action` varchar(32) NOT NULL,
+  `target_user_id` bigint(20) DEFAULT NULL,
+  `chat_id` bigint(20) DEFAULT NULL,
+  `detail` varchar(500) DEFAULT NULL,
   `created_at` timestamp NOT NULL DEFAULT current_timestamp()
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
 
@@ -74,13 +195,62 @@ CREATE TABLE `orders` (
   `user_id` bigint(20) NOT NULL,
   `cart_id` bigint(20) NOT NULL,
   `subtotal` int(11) NOT NULL,
+  `discount` int(11) NOT NULL DEFAULT 0,
+  `voucher_id` bigint(20) DEFAULT NULL,
   `delivery_fee` int(11) NOT NULL DEFAULT 0,
+  `unique_code` int(11) NOT NULL DEFAULT 0,
   `total` int(11) NOT NULL,
   `shipping_name` varchar(120) NOT NULL,
   `shipping_phone` varchar(30) NOT NULL,
   `shipping_address` text NOT NULL,
+  `shipping_province` varchar(80) DEFAULT NULL,
+  `shipping_city` varchar(80) DEFAULT NULL,
+  `shipping_postcode` varchar(10) DEFAULT NULL,
+  `weight_grams` int(11) NOT NULL DEFAULT 0,
   `note` text DEFAULT NULL,
   `status` enum('new','paid','shipped','done','cancelled','deleted') NOT NULL DEFAULT 'new',
+  `idempotency_key` varchar(64) DEFAULT NULL,
+  `stream_id` bigint(20) DEFAULT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp(),
+  `updated_at` timestamp NOT NULL DEFAULT current_timestamp() ON UPDATE current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `order_status_history`
+--
+
+CREATE TABLE `order_status_history` (
+  `id` bigint(20) NOT NULL,
+  `order_id` bigint(20) NOT NULL,
+  `from_status` enum('new','paid','shipped','done','cancelled','deleted') DEFAULT NULL,
+  `to_status` enum('new','paid','shipped','done','cancelled','deleted') NOT NULL,
+  `changed_by` bigint(20) DEFAULT NULL,
+  `note` text DEFAULT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `payments`
+--
+
+CREATE TABLE `payments` (
+  `id` bigint(20) NOT NULL,
+  `order_id` bigint(20) NOT NULL,
+  `provider` varchar(32) NOT NULL,
+  `provider_ref` varchar(100) NOT NULL,
+  `method` varchar(32) NOT NULL,
+  `amount` int(11) NOT NULL,
+  `status` enum('pending','paid','failed','expired') NOT NULL DEFAULT 'pending',
+  `pay_url` varchar(255) DEFAULT NULL,
+  `va_number` varchar(64) DEFAULT NULL,
+  `qr_string` text DEFAULT NULL,
+  `expires_at` timestamp NULL DEFAULT NULL,
+  `paid_at` timestamp NULL DEFAULT NULL,
+  `raw_callback` text DEFAULT NULL,
   `created_at` timestamp NOT NULL DEFAULT current_timestamp(),
   `updated_at` timestamp NOT NULL DEFAULT current_timestamp() ON UPDATE current_timestamp()
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
@@ -93,16 +263,137 @@ CREATE TABLE `orders` (
 
 CREATE TABLE `products` (
   `id` bigint(20) NOT NULL,
+  `code` varchar(32) DEFAULT NULL,
   `name` varchar(120) NOT NULL,
   `description` text DEFAULT NULL,
   `image_url` varchar(255) DEFAULT NULL,
   `price_idr` int(11) NOT NULL,
+  `stock` int(11) NOT NULL,
+  `weight_grams` int(11) NOT NULL DEFAULT 0,
+  `is_active` tinyint(1) NOT NULL DEFAULT 1,
+  `option1_name` varchar(40) DEFAULT NULL,
+  `option2_name` varchar(40) DEFAULT NULL,
+  `option3_name` varchar(40) DEFAULT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `product_variants`
+--
+
+CREATE TABLE `product_variants` (
+  `id` bigint(20) NOT NULL,
+  `product_id` bigint(20) NOT NULL,
+  `sku` varchar(64) NOT NULL,
+  `option1` varchar(60) DEFAULT NULL,
+  `option2` varchar(60) DEFAULT NULL,
+  `option3` varchar(60) DEFAULT NULL,
+  `price_idr` int(11) DEFAULT NULL,
+  `stock` int(11) NOT NULL DEFAULT 0,
+  `image_url` varchar(255) DEFAULT NULL,
   `is_active` tinyint(1) NOT NULL DEFAULT 1,
   `created_at` timestamp NOT NULL DEFAULT current_timestamp()
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
 
 -- --------------------------------------------------------
 
+--
+-- Table structure for table `room_moderation`
+--
+
+CREATE TABLE `room_moderation` (
+  `room` varchar(64) NOT NULL,
+  `slow_mode_secs` int(10) UNSIGNED NOT NULL DEFAULT 0,
+  `block_links` tinyint(1) NOT NULL DEFAULT 0,
+  `blocked_words` text DEFAULT NULL,
+  `updated_at` timestamp NOT NULL DEFAULT current_timestamp() ON UPDATE current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `room_pins`
+--
+
+CREATE TABLE `room_pins` (
+  `id` bigint(20) NOT NULL,
+  `room` varchar(64) NOT NULL,
+  `product_id` bigint(20) NOT NULL,
+  `live_price` int(11) DEFAULT NULL,
+  `ends_at` timestamp NULL DEFAULT NULL,
+  `pinned_by` bigint(20) DEFAULT NULL,
+  `unpinned_at` timestamp NULL DEFAULT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `room_roles`
+--
+
+CREATE TABLE `room_roles` (
+  `room` varchar(64) NOT NULL,
+  `user_id` bigint(20) NOT NULL,
+  `role` enum('host','moderator') NOT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `room_samples`
+--
+
+CREATE TABLE `room_samples` (
+  `id` bigint(20) NOT NULL,
+  `room` varchar(64) NOT NULL,
+  `stream_id` bigint(20) DEFAULT NULL,
+  `sampled_at` timestamp NOT NULL DEFAULT current_timestamp(),
+  `viewers` int(11) NOT NULL,
+  `chat_messages` int(11) NOT NULL DEFAULT 0,
+  `cart_adds` int(11) NOT NULL DEFAULT 0
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `room_sanctions`
+--
+
+CREATE TABLE `room_sanctions` (
+  `id` bigint(20) NOT NULL,
+  `room` varchar(64) NOT NULL,
+  `user_id` bigint(20) NOT NULL,
+  `kind` enum('mute','ban') NOT NULL,
+  `expires_at` timestamp NULL DEFAULT NULL,
+  `reason` varchar(255) DEFAULT NULL,
+  `created_by` bigint(20) DEFAULT NULL,
+  `lifted_at` timestamp NULL DEFAULT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `room_views`
+--
+
+CREATE TABLE `room_views` (
+  `id` bigint(20) NOT NULL,
+  `room` varchar(64) NOT NULL,
+  `viewer_key` varchar(64) NOT NULL,
+  `user_id` bigint(20) DEFAULT NULL,
+  `node` varchar(32) NOT NULL DEFAULT '',
+  `joined_at` timestamp NOT NULL DEFAULT current_timestamp(),
+  `seen_at` timestamp NULL DEFAULT NULL,
+  `left_at` timestamp NULL DEFAULT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
 --
 -- Table structure for table `sessions`
 --
@@ -116,6 +407,63 @@ CREATE TABLE `sessions` (
 
 -- --------------------------------------------------------
 
+--
+-- Table structure for table `shipping_rates`
+--
+
+CREATE TABLE `shipping_rates` (
+  `id` bigint(20) NOT NULL,
+  `zone_id` bigint(20) NOT NULL,
+  `max_weight_grams` int(11) DEFAULT NULL,
+  `fee` int(11) NOT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `shipping_zones`
+--
+
+CREATE TABLE `shipping_zones` (
+  `id` bigint(20) NOT NULL,
+  `name` varchar(80) NOT NULL,
+  `province` varchar(80) DEFAULT NULL,
+  `city` varchar(80) DEFAULT NULL,
+  `postcode_prefix` varchar(10) DEFAULT NULL,
+  `flat_fee` int(11) DEFAULT NULL,
+  `free_min_subtotal` int(11) DEFAULT NULL,
+  `is_active` tinyint(1) NOT NULL DEFAULT 1,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+--
+-- Dumping data for table `shipping_zones`
+--
+
+INSERT INTO `shipping_zones` (`id`, `name`, `province`, `city`, `postcode_prefix`, `flat_fee`, `free_min_subtotal`, `is_active`) VALUES
+(1, 'Seluruh Indonesia', NULL, NULL, NULL, 0, NULL, 1);
+
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `streams`
+--
+
+CREATE TABLE `streams` (
+  `id` bigint(20) NOT NULL,
+  `room` varchar(64) NOT NULL,
+  `title` varchar(160) NOT NULL,
+  `host_user_id` bigint(20) DEFAULT NULL,
+  `scheduled_at` timestamp NULL DEFAULT NULL,
+  `started_at` timestamp NULL DEFAULT NULL,
+  `ended_at` timestamp NULL DEFAULT NULL,
+  `status` enum('scheduled','live','ended') NOT NULL DEFAULT 'scheduled',
+  `created_by` bigint(20) DEFAULT NULL,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+-- --------------------------------------------------------
+
 --
 -- Table structure for table `users`
 --
@@ -130,6 +478,29 @@ CREATE TABLE `users` (
   `created_at` timestamp NOT NULL DEFAULT current_timestamp()
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
 
+-- --------------------------------------------------------
+
+--
+-- Table structure for table `vouchers`
+--
+
+CREATE TABLE `vouchers` (
+  `id` bigint(20) NOT NULL,
+  `code` varchar(32) NOT NULL,
+  `kind` enum('percent','fixed') NOT NULL,
+  `value` int(11) NOT NULL,
+  `min_subtotal` int(11) NOT NULL DEFAULT 0,
+  `max_discount` int(11) DEFAULT NULL,
+  `usage_limit` int(11) DEFAULT NULL,
+  `per_user_limit` int(11) DEFAULT NULL,
+  `starts_at` timestamp NULL DEFAULT NULL,
+  `ends_at` timestamp NULL DEFAULT NULL,
+  `product_id` bigint(20) DEFAULT NULL,
+  `room` varchar(64) DEFAULT NULL,
+  `is_active` tinyint(1) NOT NULL DEFAULT 1,
+  `created_at` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
 --
 -- Indexes for dumped tables
 --
@@ -141,12 +512,31 @@ ALTER TABLE `admins`
   ADD PRIMARY KEY (`id`),
   ADD UNIQUE KEY `email` (`email`);
 
+--
+-- Indexes for table `auction_bids`
+--
+ALTER TABLE `auction_bids`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `auction_id` (`auction_id`),
+  ADD KEY `user_id` (`user_id`);
+
+--
+-- Indexes for table `auctions`
+--
+ALTER TABLE `auctions`
+  ADD PRIMARY KEY (`id`),
+  ADD UNIQUE KEY `running_room` (`running_room`),
+  ADD KEY `room_status` (`room`,`status`),
+  ADD KEY `product_id` (`product_id`),
+  ADD KEY `current_bidder_id` (`current_bidder_id`);
+
 --
 -- Indexes for table `carts`
 --
 ALTER TABLE `carts`
   ADD PRIMARY KEY (`id`),
-  ADD KEY `user_id` (`user_id`);
+  ADD KEY `user_id` (`user_id`),
+  ADD KEY `voucher_id` (`voucher_id`);
 
 --
 -- Indexes for table `cart_items`
@@ -154,21 +544,131 @@ ALTER TABLE `carts`
 ALTER TABLE `cart_items`
   ADD PRIMARY KEY (`id`),
   ADD KEY `cart_id` (`cart_id`),
+  ADD KEY `product_id` (`product_id`),
+  ADD KEY `variant_id` (`variant_id`),
+  ADD KEY `flash_sale_id` (`flash_sale_id`),
+  ADD KEY `stream_id` (`stream_id`);
+
+--
+-- Indexes for table `chat_claims`
+--
+ALTER TABLE `chat_claims`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `room_id` (`room`,`id`),
+  ADD KEY `user_id` (`user_id`),
   ADD KEY `product_id` (`product_id`);
 
+--
+-- Indexes for table `chat_messages`
+--
+ALTER TABLE `chat_messages`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `room_id` (`room`,`id`),
+  ADD KEY `user_id` (`user_id`);
+
+--
+-- Indexes for table `flash_sales`
+--
+ALTER TABLE `flash_sales`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `product_window` (`product_id`,`starts_at`,`ends_at`),
+  ADD KEY `notified` (`notified`);
+
+--
+-- Indexes for table `moderation_log`
+--
+ALTER TABLE `moderation_log`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `room_id` (`room`,`id`);
+
 --
 -- Indexes for table `orders`
 --
 ALTER TABLE `orders`
   ADD PRIMARY KEY (`id`),
   ADD KEY `user_id` (`user_id`),
-  ADD KEY `cart_id` (`cart_id`);
+  ADD UNIQUE KEY `cart_id` (`cart_id`),
+  ADD UNIQUE KEY `user_idempotency_key` (`user_id`,`idempotency_key`),
+  ADD KEY `status_total` (`status`,`total`),
+  ADD KEY `voucher_id` (`voucher_id`),
+  ADD KEY `stream_id` (`stream_id`);
+
+--
+-- Indexes for table `order_status_history`
+--
+ALTER TABLE `order_status_history`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `order_id` (`order_id`),
+  ADD KEY `changed_by` (`changed_by`);
+
+--
+-- Indexes for table `payments`
+--
+ALTER TABLE `payments`
+  ADD PRIMARY KEY (`id`),
+  ADD UNIQUE KEY `provider_ref` (`provider`,`provider_ref`),
+  ADD KEY `order_id` (`order_id`);
 
 --
 -- Indexes for table `products`
 --
 ALTER TABLE `products`
-  ADD PRIMARY KEY (`id`);
+  ADD PRIMARY KEY (`id`),
+  ADD UNIQUE KEY `code` (`code`);
+
+--
+-- Indexes for table `product_variants`
+--
+ALTER TABLE `product_variants`
+  ADD PRIMARY KEY (`id`),
+  ADD UNIQUE KEY `sku` (`sku`),
+  ADD KEY `product_id` (`product_id`);
+
+--
+-- Indexes for table `room_moderation`
+--
+ALTER TABLE `room_moderation`
+  ADD PRIMARY KEY (`room`);
+
+--
+-- Indexes for table `room_pins`
+--
+ALTER TABLE `room_pins`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `room_active` (`room`,`unpinned_at`),
+  ADD KEY `product_id` (`product_id`);
+
+--
+-- Indexes for table `room_roles`
+--
+ALTER TABLE `room_roles`
+  ADD PRIMARY KEY (`room`,`user_id`),
+  ADD KEY `user_id` (`user_id`);
+
+--
+-- Indexes for table `room_samples`
+--
+ALTER TABLE `room_samples`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `room_sampled` (`room`,`sampled_at`),
+  ADD KEY `stream_sampled` (`stream_id`,`sampled_at`);
+
+--
+-- Indexes for table `room_sanctions`
+--
+ALTER TABLE `room_sanctions`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `room_user` (`room`,`user_id`),
+  ADD KEY `user_id` (`user_id`);
+
+--
+-- Indexes for table `room_views`
+--
+ALTER TABLE `room_views`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `room_joined` (`room`,`joined_at`),
+  ADD KEY `node_left` (`node`,`left_at`),
+  ADD KEY `left_at` (`left_at`);
 
 --
 -- Indexes for table `sessions`
@@ -177,12 +677,42 @@ ALTER TABLE `sessions`
   ADD PRIMARY KEY (`sid`),
   ADD KEY `user_id` (`user_id`);
 
+--
+-- Indexes for table `shipping_rates`
+--
+ALTER TABLE `shipping_rates`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `zone_id` (`zone_id`);
+
+--
+-- Indexes for table `shipping_zones`
+--
+ALTER TABLE `shipping_zones`
+  ADD PRIMARY KEY (`id`);
+
+--
+-- Indexes for table `streams`
+--
+ALTER TABLE `streams`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `room_status` (`room`,`status`),
+  ADD KEY `status_scheduled` (`status`,`scheduled_at`),
+  ADD KEY `host_user_id` (`host_user_id`);
+
 --
 -- Indexes for table `users`
 --
 ALTER TABLE `users`
   ADD PRIMARY KEY (`id`);
 
+--
+-- Indexes for table `vouchers`
+--
+ALTER TABLE `vouchers`
+  ADD PRIMARY KEY (`id`),
+  ADD UNIQUE KEY `code` (`code`),
+  ADD KEY `product_id` (`product_id`);
+
 --
 -- AUTO_INCREMENT for dumped tables
 --
@@ -193,6 +723,18 @@ ALTER TABLE `users`
 ALTER TABLE `admins`
   MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
 
+--
+-- AUTO_INCREMENT for table `auction_bids`
+--
+ALTER TABLE `auction_bids`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `auctions`
+--
+ALTER TABLE `auctions`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
 --
 -- AUTO_INCREMENT for table `carts`
 --
@@ -205,53 +747,234 @@ ALTER TABLE `carts`
 ALTER TABLE `cart_items`
   MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
 
+--
+-- AUTO_INCREMENT for table `chat_claims`
+--
+ALTER TABLE `chat_claims`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `chat_messages`
+--
+ALTER TABLE `chat_messages`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `flash_sales`
+--
+ALTER TABLE `flash_sales`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `moderation_log`
+--
+ALTER TABLE `moderation_log`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
 --
 -- AUTO_INCREMENT for table `orders`
 --
 ALTER TABLE `orders`
   MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
 
+--
+-- AUTO_INCREMENT for table `order_status_history`
+--
+ALTER TABLE `order_status_history`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `payments`
+--
+ALTER TABLE `payments`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
 --
 -- AUTO_INCREMENT for table `products`
 --
 ALTER TABLE `products`
   MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
 
+--
+-- AUTO_INCREMENT for table `product_variants`
+--
+ALTER TABLE `product_variants`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `room_pins`
+--
+ALTER TABLE `room_pins`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `room_samples`
+--
+ALTER TABLE `room_samples`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `room_sanctions`
+--
+ALTER TABLE `room_sanctions`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `room_views`
+--
+ALTER TABLE `room_views`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `shipping_rates`
+--
+ALTER TABLE `shipping_rates`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
+--
+-- AUTO_INCREMENT for table `shipping_zones`
+--
+ALTER TABLE `shipping_zones`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT, AUTO_INCREMENT=2;
+
+--
+-- AUTO_INCREMENT for table `streams`
+--
+ALTER TABLE `streams`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
 --
 -- AUTO_INCREMENT for table `users`
 --
 ALTER TABLE `users`
   MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
 
+--
+-- AUTO_INCREMENT for table `vouchers`
+--
+ALTER TABLE `vouchers`
+  MODIFY `id` bigint(20) NOT NULL AUTO_INCREMENT;
+
 --
 -- Constraints for dumped tables
 --
 
+--
+-- Constraints for table `auction_bids`
+--
+ALTER TABLE `auction_bids`
+  ADD CONSTRAINT `fk_auction_bids_auction` FOREIGN KEY (`auction_id`) REFERENCES `auctions` (`id`) ON DELETE CASCADE,
+  ADD CONSTRAINT `fk_auction_bids_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE;
+
+--
+-- Constraints for table `auctions`
+--
+ALTER TABLE `auctions`
+  ADD CONSTRAINT `fk_auctions_product` FOREIGN KEY (`product_id`) REFERENCES `products` (`id`) ON DELETE CASCADE,
+  ADD CONSTRAINT `fk_auctions_bidder` FOREIGN KEY (`current_bidder_id`) REFERENCES `users` (`id`) ON DELETE SET NULL;
+
 --
 -- Constraints for table `carts`
 --
 ALTER TABLE `carts`
-  ADD CONSTRAINT `fk_carts_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE;
+  ADD CONSTRAINT `fk_carts_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE,
+  ADD CONSTRAINT `fk_carts_voucher` FOREIGN KEY (`voucher_id`) REFERENCES `vouchers` (`id`) ON DELETE SET NULL;
 
 --
 -- Constraints for table `cart_items`
 --
 ALTER TABLE `cart_items`
   ADD CONSTRAINT `fk_ci_cart` FOREIGN KEY (`cart_id`) REFERENCES `carts` (`id`) ON DELETE CASCADE,
-  ADD CONSTRAINT `fk_ci_product` FOREIGN KEY (`product_id`) REFERENCES `products` (`id`);
+  ADD CONSTRAINT `fk_ci_product` FOREIGN KEY (`product_id`) REFERENCES `products` (`id`),
+  ADD CONSTRAINT `fk_ci_variant` FOREIGN KEY (`variant_id`) REFERENCES `product_variants` (`id`);
+
+--
+-- Constraints for table `chat_claims`
+--
+ALTER TABLE `chat_claims`
+  ADD CONSTRAINT `fk_chat_claims_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE,
+  ADD CONSTRAINT `fk_chat_claims_product` FOREIGN KEY (`product_id`) REFERENCES `products` (`id`) ON DELETE SET NULL;
+
+--
+-- Constraints for table `chat_messages`
+--
+ALTER TABLE `chat_messages`
+  ADD CONSTRAINT `fk_chat_messages_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE SET NULL;
+
+--
+-- Constraints for table `flash_sales`
+--
+ALTER TABLE `flash_sales`
+  ADD CONSTRAINT `fk_flash_sales_product` FOREIGN KEY (`product_id`) REFERENCES `products` (`id`) ON DELETE CASCADE;
 
 --
 -- Constraints for table `orders`
 --
 ALTER TABLE `orders`
   ADD CONSTRAINT `fk_orders_cart` FOREIGN KEY (`cart_id`) REFERENCES `carts` (`id`),
-  ADD CONSTRAINT `fk_orders_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`);
+  ADD CONSTRAINT `fk_orders_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`),
+  ADD CONSTRAINT `fk_orders_voucher` FOREIGN KEY (`voucher_id`) REFERENCES `vouchers` (`id`);
+
+--
+-- Constraints for table `order_status_history`
+--
+ALTER TABLE `order_status_history`
+  ADD CONSTRAINT `fk_osh_order` FOREIGN KEY (`order_id`) REFERENCES `orders` (`id`) ON DELETE CASCADE,
+  ADD CONSTRAINT `fk_osh_user` FOREIGN KEY (`changed_by`) REFERENCES `users` (`id`) ON DELETE SET NULL;
+
+--
+-- Constraints for table `payments`
+--
+ALTER TABLE `payments`
+  ADD CONSTRAINT `fk_payments_order` FOREIGN KEY (`order_id`) REFERENCES `orders` (`id`);
+
+--
+-- Constraints for table `product_variants`
+--
+ALTER TABLE `product_variants`
+  ADD CONSTRAINT `fk_variants_product` FOREIGN KEY (`product_id`) REFERENCES `products` (`id`) ON DELETE CASCADE;
+
+--
+-- Constraints for table `room_pins`
+--
+ALTER TABLE `room_pins`
+  ADD CONSTRAINT `fk_room_pins_product` FOREIGN KEY (`product_id`) REFERENCES `products` (`id`) ON DELETE CASCADE;
+
+--
+-- Constraints for table `room_roles`
+--
+ALTER TABLE `room_roles`
+  ADD CONSTRAINT `fk_room_roles_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE;
+
+--
+-- Constraints for table `room_sanctions`
+--
+ALTER TABLE `room_sanctions`
+  ADD CONSTRAINT `fk_room_sanctions_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE;
 
 --
 -- Constraints for table `sessions`
 --
 ALTER TABLE `sessions`
   ADD CONSTRAINT `fk_sessions_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE;
+
+--
+-- Constraints for table `shipping_rates`
+--
+ALTER TABLE `shipping_rates`
+  ADD CONSTRAINT `fk_rates_zone` FOREIGN KEY (`zone_id`) REFERENCES `shipping_zones` (`id`) ON DELETE CASCADE;
+
+--
+-- Constraints for table `streams`
+--
+ALTER TABLE `streams`
+  ADD CONSTRAINT `fk_streams_host` FOREIGN KEY (`host_user_id`) REFERENCES `users` (`id`) ON DELETE SET NULL;
+
+--
+-- Constraints for table `vouchers`
+--
+ALTER TABLE `vouchers`
+  ADD CONSTRAINT `fk_vouchers_product` FOREIGN KEY (`product_id`) REFERENCES `products` (`id`) ON DELETE CASCADE;
 COMMIT;
 
 /*!40101 SET CHARACTER_SET_CLIENT=@OLD_CHARACTER_SET_CLIENT */;